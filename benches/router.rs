@@ -0,0 +1,62 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use spit::{
+    config::RouteTable,
+    router::{scan_routes, RouteTree},
+};
+
+/// 400 routes: a collection, item, sub-collection and sub-item template for
+/// each of 100 resources.
+fn routes() -> RouteTable {
+    (0..100)
+        .flat_map(|n| {
+            [
+                format!("/resource{}", n),
+                format!("/resource{}/{{id}}", n),
+                format!("/resource{}/{{id}}/items", n),
+                format!("/resource{}/{{id}}/items/{{item}}", n),
+            ]
+        })
+        .map(|template| (template, Vec::new()))
+        .collect()
+}
+
+/// A request for each route, and one matching none per resource.
+fn paths() -> Vec<String> {
+    (0..100)
+        .flat_map(|n| {
+            [
+                format!("/resource{}", n),
+                format!("/resource{}/42", n),
+                format!("/resource{}/42/items", n),
+                format!("/resource{}/42/items/7", n),
+                format!("/resource{}/42/unknown", n),
+            ]
+        })
+        .collect()
+}
+
+fn route_matching(c: &mut Criterion) {
+    let routes = routes();
+    let tree = RouteTree::new(&routes, false);
+    let paths = paths();
+
+    let mut group = c.benchmark_group("match 500 paths against 400 routes");
+    group.bench_function("route tree", |b| {
+        b.iter(|| {
+            for path in &paths {
+                black_box(tree.find(black_box(path)));
+            }
+        })
+    });
+    group.bench_function("linear scan", |b| {
+        b.iter(|| {
+            for path in &paths {
+                black_box(scan_routes(&routes, black_box(path), false));
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, route_matching);
+criterion_main!(benches);
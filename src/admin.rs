@@ -0,0 +1,174 @@
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::{from_fn, Next},
+    web, Error, HttpResponse, Scope,
+};
+use ipnet::IpNet;
+use log::warn;
+use serde::Deserialize;
+use serde_json::json;
+use std::{collections::BTreeMap, sync::RwLock};
+
+use crate::{
+    config::{MockState, RequestLog},
+    swagger::glob_matches,
+    VirtualHosts,
+};
+
+/// Path prefix of spit's own endpoints, kept out of the mocked routes.
+pub const ADMIN_PREFIX: &str = "/__spit";
+
+/// Networks allowed to reach the admin endpoints.
+pub struct AdminAccess {
+    allow: Vec<IpNet>,
+}
+
+impl AdminAccess {
+    /// Uses `allow` when configured, otherwise loopback only.
+    pub fn new(allow: Option<Vec<IpNet>>) -> Self {
+        let allow = allow.unwrap_or_else(|| {
+            vec![
+                "127.0.0.0/8".parse().expect("valid loopback network"),
+                "::1/128".parse().expect("valid loopback network"),
+            ]
+        });
+        Self { allow }
+    }
+
+    fn permits(&self, ip: std::net::IpAddr) -> bool {
+        let ip = match ip {
+            std::net::IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, std::net::IpAddr::V4),
+            v4 => v4,
+        };
+        self.allow.iter().any(|net| net.contains(&ip))
+    }
+}
+
+/// Scope holding the admin endpoints, guarded by `AdminAccess`. Must be
+/// registered before the catch-all mock resource.
+pub fn admin_scope() -> Scope<
+    impl actix_web::dev::ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = Error,
+        InitError = (),
+    >,
+> {
+    web::scope(ADMIN_PREFIX)
+        .wrap(from_fn(admin_access_guard))
+        .route("/requests", web::get().to(logged_requests))
+        .route("/unmatched", web::get().to(unmatched_paths))
+}
+
+/// Filters for `/__spit/requests`.
+#[derive(Debug, Deserialize)]
+struct RequestLogQuery {
+    method: Option<String>,
+    /// Exact path, or a glob like `/users/**`.
+    path: Option<String>,
+    status: Option<u16>,
+    /// Keep only the most recent entries.
+    limit: Option<usize>,
+}
+
+/// The in-memory request log across all hosts, oldest first, as a JSON
+/// array `spit replay` can read back.
+async fn logged_requests(
+    query: web::Query<RequestLogQuery>,
+    state: web::Data<RwLock<MockState>>,
+    virtual_hosts: web::Data<VirtualHosts>,
+) -> HttpResponse {
+    let mut entries: Vec<RequestLog> = Vec::new();
+    let states = std::iter::once(&state).chain(virtual_hosts.services.values().map(|(s, _)| s));
+    for state in states {
+        if let Ok(state) = state.read() {
+            let Ok(request_log) = state.request_log.lock() else {
+                continue;
+            };
+            entries.extend(
+                request_log
+                    .iter()
+                    .filter(|entry| {
+                        query
+                            .method
+                            .as_ref()
+                            .is_none_or(|method| entry.method.eq_ignore_ascii_case(method))
+                            && query
+                                .path
+                                .as_ref()
+                                .is_none_or(|path| glob_matches(path, &entry.path))
+                            && query
+                                .status
+                                .is_none_or(|status| entry.response_status == status)
+                    })
+                    .cloned(),
+            );
+        }
+    }
+
+    entries.sort_by_key(|entry| entry.timestamp);
+    if let Some(limit) = query.limit {
+        entries.drain(..entries.len().saturating_sub(limit));
+    }
+    HttpResponse::Ok().json(entries)
+}
+
+/// Distinct request paths that matched no route, across all hosts, most
+/// requested first.
+async fn unmatched_paths(
+    state: web::Data<RwLock<MockState>>,
+    virtual_hosts: web::Data<VirtualHosts>,
+) -> HttpResponse {
+    let mut hits: BTreeMap<String, u64> = BTreeMap::new();
+    let states = std::iter::once(&state).chain(virtual_hosts.services.values().map(|(s, _)| s));
+    for state in states {
+        let Ok(state) = state.read() else {
+            continue;
+        };
+        let Ok(unmatched) = state.unmatched.lock() else {
+            continue;
+        };
+        for (path, count) in unmatched.iter() {
+            *hits.entry(path.clone()).or_insert(0) += count;
+        }
+    }
+
+    let mut unmatched: Vec<(String, u64)> = hits.into_iter().collect();
+    unmatched.sort_by_key(|(_, hits)| std::cmp::Reverse(*hits));
+    HttpResponse::Ok().json(json!({
+        "unmatched": unmatched
+            .into_iter()
+            .map(|(path, hits)| json!({ "path": path, "hits": hits }))
+            .collect::<Vec<_>>()
+    }))
+}
+
+/// Rejects peers outside the allowed networks with a 403. Connections
+/// without an IP peer (e.g. a Unix socket) are local and let through.
+pub(crate) async fn admin_access_guard(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let allowed = match (req.app_data::<web::Data<AdminAccess>>(), req.peer_addr()) {
+        (Some(access), Some(peer)) => access.permits(peer.ip()),
+        (None, Some(peer)) => AdminAccess::new(None).permits(peer.ip()),
+        (_, None) => true,
+    };
+
+    if !allowed {
+        warn!(
+            "Refused admin request {} from {:?}",
+            req.path(),
+            req.peer_addr()
+        );
+        let response = HttpResponse::Forbidden().json(json!({
+            "error": "Forbidden",
+            "details": "Admin endpoints are not available from this address"
+        }));
+        return Ok(req.into_response(response).map_into_right_body());
+    }
+
+    Ok(next.call(req).await?.map_into_left_body())
+}
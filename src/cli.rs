@@ -1,6 +1,14 @@
-use std::path::PathBuf;
+use std::{fmt::Debug, path::PathBuf};
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
+
+use crate::{
+    config::{Delay, Locale, MockConfig},
+    cors::AllowHeaders,
+    export::ExportFormat,
+    routes::RoutesFormat,
+    MockServerError,
+};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -15,32 +23,334 @@ pub enum Commands {
         #[arg(short, long)]
         url: String,
 
-        #[arg(short, long, default_value = "8080")]
-        port: u16,
+        #[command(flatten)]
+        server: ServerArgs,
+    },
+    // `-p` has always been `--path` here; the port is set with `--port`.
+    #[command(mut_arg("port", |port| port.short(None)))]
+    File {
+        /// Spec file, or a directory of them; repeat for a spec split
+        /// across files
+        #[arg(short, long, required = true)]
+        path: Vec<PathBuf>,
 
-        #[arg(short = 'H', long, default_value = "127.0.0.1")]
-        host: String,
+        #[command(flatten)]
+        server: ServerArgs,
+    },
+    /// Print the JSON Schema of the configuration file
+    ConfigSchema,
+    /// Check that a spec is usable by spit without starting the server
+    Validate {
+        /// Spec file path or URL
+        spec: String,
+    },
+    /// Print the routes spit finds in a spec without starting the server
+    Routes {
+        /// Spec file path or URL
+        spec: String,
 
-        #[arg(short, long)]
-        delay: Option<u64>,
+        #[arg(short, long, value_enum, default_value = "table")]
+        format: RoutesFormat,
+    },
+    /// Re-send requests from a saved request log to a server
+    Replay {
+        /// JSON array or JSON Lines file of request log entries
+        #[arg(long)]
+        log: PathBuf,
 
-        #[arg(short = 'C', long)]
-        config: Option<std::path::PathBuf>,
+        /// Base URL the requests are sent to
+        #[arg(short, long)]
+        target: String,
     },
-    File {
+    /// Proxy requests to a real backend and write its responses as a spec
+    /// `spit file` can serve
+    Record {
+        /// Base URL of the backend
         #[arg(short, long)]
-        path: PathBuf,
+        url: String,
+
+        /// File the recorded spec is written to on shutdown
+        #[arg(short, long)]
+        out: PathBuf,
 
         #[arg(short, long, default_value = "8080")]
         port: u16,
 
         #[arg(short = 'H', long, default_value = "127.0.0.1")]
         host: String,
+    },
+    /// Write generated responses for every operation as client-side fixtures
+    Export {
+        /// Spec file path or URL
+        #[arg(long)]
+        spec: String,
 
+        #[arg(short, long, value_enum, default_value = "msw")]
+        format: ExportFormat,
+
+        /// File the fixtures are written to
         #[arg(short, long)]
-        delay: Option<u64>,
+        out: PathBuf,
+
+        /// Prefix for the handler URLs, e.g. https://api.example.com
+        #[arg(long, default_value = "")]
+        base_url: String,
 
         #[arg(short = 'C', long)]
-        config: Option<std::path::PathBuf>,
+        config: Option<PathBuf>,
+
+        /// Generate the same fixtures on every run
+        #[arg(long)]
+        seed: Option<u64>,
     },
 }
+
+#[derive(Args, Debug, Clone)]
+pub struct ServerArgs {
+    #[arg(short, long, default_value = "8080")]
+    pub port: u16,
+
+    #[arg(short = 'H', long, default_value = "127.0.0.1")]
+    pub host: String,
+
+    /// Listen on this Unix domain socket instead of TCP
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["port", "host"])]
+    pub unix_socket: Option<PathBuf>,
+
+    /// Also accept HTTP/2 over cleartext TCP (h2c with prior knowledge)
+    #[arg(long, conflicts_with = "unix_socket")]
+    pub http2: bool,
+
+    /// Serve HTTPS with this PEM certificate chain (needs --tls-key)
+    #[arg(
+        long,
+        value_name = "PEM",
+        requires = "tls_key",
+        conflicts_with_all = ["unix_socket", "http2"]
+    )]
+    pub tls_cert: Option<PathBuf>,
+
+    /// PEM private key for --tls-cert
+    #[arg(long, value_name = "PEM", requires = "tls_cert")]
+    pub tls_key: Option<PathBuf>,
+
+    #[arg(short, long)]
+    pub delay: Option<u64>,
+
+    /// Milliseconds to wait before sending the response headers
+    #[arg(long, value_name = "MS")]
+    pub delay_first_byte: Option<u64>,
+
+    /// Milliseconds to wait before each 1 KiB chunk of the response body
+    #[arg(long, value_name = "MS")]
+    pub delay_body: Option<u64>,
+
+    #[arg(short = 'C', long)]
+    pub config: Option<PathBuf>,
+
+    /// Reload the spec (when a local file) and the config when they change
+    #[arg(long)]
+    pub watch: bool,
+
+    /// Append every request to this file in Combined Log Format
+    #[arg(long)]
+    pub access_log: Option<PathBuf>,
+
+    /// Append every request, bodies included, to this file as JSON Lines
+    #[arg(long, value_name = "FILE")]
+    pub log_file: Option<PathBuf>,
+
+    /// Record all traffic and write it to this file as a HAR on shutdown
+    #[arg(long, value_name = "FILE")]
+    pub har: Option<PathBuf>,
+
+    /// Answer requests recorded in this HAR file with their recorded responses
+    #[arg(long, value_name = "FILE")]
+    pub playback: Option<PathBuf>,
+
+    /// Forward requests no route matches to this base URL
+    #[arg(long, value_name = "URL")]
+    pub proxy: Option<String>,
+
+    /// Generate the same data for the same request on every run
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Language of generated names, companies, phone numbers and addresses
+    #[arg(long, value_enum)]
+    pub locale: Option<Locale>,
+
+    /// Enable CORS, allowing these preflight headers (comma separated) or
+    /// `reflect` to mirror Access-Control-Request-Headers
+    #[arg(long)]
+    pub cors_allow_headers: Option<AllowHeaders>,
+
+    /// Fail at startup on schema formats spit does not recognize instead of
+    /// only warning, and validate string formats of requests
+    #[arg(long)]
+    pub strict_formats: bool,
+
+    /// Compress responses per the client's Accept-Encoding
+    #[arg(long)]
+    pub compress: bool,
+
+    /// Exit non-zero after N requests (default 1) fail validation
+    #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "1")]
+    pub fail_on_validation_error: Option<usize>,
+
+    /// Only serve routes whose path matches this glob (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    pub only: Vec<String>,
+
+    /// Leave out routes whose path matches this glob (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    pub exclude: Vec<String>,
+}
+
+impl ServerArgs {
+    /// Fills settings the config file left unset with the CLI values. Options
+    /// given both ways with different values are rejected instead of silently
+    /// picking one.
+    pub fn apply_to(&self, config: &mut MockConfig) -> Result<(), MockServerError> {
+        let mut conflicts = Vec::new();
+
+        merge_option(
+            "--delay",
+            "delay",
+            &self.delay.map(Delay::Fixed),
+            &mut config.delay,
+            &mut conflicts,
+        );
+        merge_option(
+            "--delay-first-byte",
+            "ttfb_delay",
+            &self.delay_first_byte,
+            &mut config.ttfb_delay,
+            &mut conflicts,
+        );
+        merge_option(
+            "--delay-body",
+            "body_delay",
+            &self.delay_body,
+            &mut config.body_delay,
+            &mut conflicts,
+        );
+        merge_option(
+            "--access-log",
+            "access_log",
+            &self.access_log,
+            &mut config.access_log,
+            &mut conflicts,
+        );
+        merge_option(
+            "--fail-on-validation-error",
+            "fail_on_validation_error",
+            &self.fail_on_validation_error,
+            &mut config.fail_on_validation_error,
+            &mut conflicts,
+        );
+        merge_option(
+            "--only",
+            "only",
+            &Some(self.only.clone()).filter(|globs| !globs.is_empty()),
+            &mut config.only,
+            &mut conflicts,
+        );
+        merge_option(
+            "--exclude",
+            "exclude",
+            &Some(self.exclude.clone()).filter(|globs| !globs.is_empty()),
+            &mut config.exclude,
+            &mut conflicts,
+        );
+        merge_option(
+            "--log-file",
+            "log_file",
+            &self.log_file,
+            &mut config.log_file,
+            &mut conflicts,
+        );
+        merge_option("--har", "har", &self.har, &mut config.har, &mut conflicts);
+        merge_option(
+            "--seed",
+            "seed",
+            &self.seed,
+            &mut config.seed,
+            &mut conflicts,
+        );
+        merge_option(
+            "--locale",
+            "locale",
+            &self.locale,
+            &mut config.locale,
+            &mut conflicts,
+        );
+        merge_option(
+            "--playback",
+            "playback",
+            &self.playback,
+            &mut config.playback,
+            &mut conflicts,
+        );
+        merge_option(
+            "--proxy",
+            "proxy",
+            &self.proxy,
+            &mut config.proxy,
+            &mut conflicts,
+        );
+        if self.strict_formats {
+            config.strict_formats = Some(true);
+        }
+        if self.compress {
+            config.compress = Some(true);
+        }
+        if self.cors_allow_headers.is_some() {
+            let cors = config.cors.get_or_insert_with(Default::default);
+            merge_option(
+                "--cors-allow-headers",
+                "cors.allow_headers",
+                &self.cors_allow_headers,
+                &mut cors.allow_headers,
+                &mut conflicts,
+            );
+        }
+
+        conflicts_result(conflicts)
+    }
+}
+
+/// Merges the `--seed` of `spit export` into `config`, as `ServerArgs`
+/// merges its own.
+pub fn apply_seed(seed: &Option<u64>, config: &mut MockConfig) -> Result<(), MockServerError> {
+    let mut conflicts = Vec::new();
+    merge_option("--seed", "seed", seed, &mut config.seed, &mut conflicts);
+    conflicts_result(conflicts)
+}
+
+fn conflicts_result(conflicts: Vec<String>) -> Result<(), MockServerError> {
+    if conflicts.is_empty() {
+        Ok(())
+    } else {
+        Err(MockServerError::Config(format!(
+            "conflicting options: {}",
+            conflicts.join("; ")
+        )))
+    }
+}
+
+fn merge_option<T: Clone + PartialEq + Debug>(
+    flag: &str,
+    key: &str,
+    cli: &Option<T>,
+    config: &mut Option<T>,
+    conflicts: &mut Vec<String>,
+) {
+    match (cli, config.as_ref()) {
+        (Some(cli_value), Some(config_value)) if cli_value != config_value => conflicts.push(
+            format!("{flag} {cli_value:?} differs from config `{key}` {config_value:?}"),
+        ),
+        (Some(cli_value), None) => *config = Some(cli_value.clone()),
+        _ => {}
+    }
+}
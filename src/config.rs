@@ -1,16 +1,52 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs::File,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
 
 use fake::Fake;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    cors::CorsConfig,
+    expr::Expr,
+    generator::{fake_kind, faker_kinds},
+    jobs::AsyncJob,
+    proxy::parse_base_url,
+    rate_limit::{RateLimitConfig, TokenBucket},
+    regex_gen::generate_matching,
+    router::RouteTree,
+    store::Collections,
+    swagger::glob_matches,
+    MockServerError,
+};
+use log::warn;
 use serde::{Deserialize, Serialize};
 use serde_json::{Number, Value};
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RequestLog {
     pub timestamp: chrono::DateTime<chrono::Utc>,
     pub method: String,
     pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
     pub headers: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
     pub response_status: u16,
+    #[serde(default)]
+    pub response_size: u64,
+    #[serde(default)]
+    pub peer_addr: Option<String>,
+    /// Body the upstream answered with, kept by `spit record`, or the mock
+    /// response body in `log_file` entries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_body: Option<String>,
+    /// `Content-Type` of the response, if it had one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_content_type: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,43 +68,815 @@ pub enum MockPattern {
     },
     #[serde(rename = "date")]
     DateTime { format: Option<String> },
+    /// Strings matching a regular expression, e.g. `ORD-[0-9]{6}`.
+    #[serde(rename = "regex")]
+    Regex { pattern: String },
+    /// `start`, `start + step`, ... across the values generated for the
+    /// field, counted in `MockState::sequences`.
+    #[serde(rename = "sequence")]
+    Sequence {
+        #[serde(default = "default_sequence_start")]
+        start: i64,
+        #[serde(default = "default_sequence_step")]
+        step: i64,
+    },
+    /// Strings filling `{field}` references from the other fields of the
+    /// generated object, and `{now}`, `{seq}` and string format tokens such
+    /// as `{uuid}` or `{email}`.
+    #[serde(rename = "template")]
+    Template { template: String },
+    /// Values of a named faker generator, e.g. `address` or `job_title`.
+    #[serde(rename = "ref")]
+    Ref { kind: String },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct MockConfig {
-    pub delay: Option<u64>,
+    pub delay: Option<Delay>,
+    /// Delay before the response headers, in milliseconds; takes precedence
+    /// over `delay`, while `latency` takes precedence over it.
+    pub ttfb_delay: Option<u64>,
+    /// Delay before each 1 KiB chunk of the body, in milliseconds.
+    pub body_delay: Option<u64>,
     pub status_code: Option<u16>,
     pub headers: Option<HashMap<String, String>>,
     pub fields: Option<MockFieldConfig>,
+    /// Bodies returned when a response has no schema, keyed by status class
+    /// (`2xx`, `4xx`, `5xx`).
+    pub default_bodies: Option<HashMap<String, Value>>,
+    pub access_log: Option<PathBuf>,
+    /// Append every request, with request and response bodies, to this file
+    /// as JSON Lines.
+    pub log_file: Option<PathBuf>,
+    /// File receiving an HTTP Archive of all traffic when the server stops.
+    pub har: Option<PathBuf>,
+    /// HAR recording whose responses are served for the requests it contains.
+    pub playback: Option<PathBuf>,
+    /// Wait as long as each recorded response originally took (default: true).
+    pub playback_timings: Option<bool>,
+    /// Base URL of a real backend receiving the requests no route matches,
+    /// and those to operations marked `x-spit-passthrough`.
+    pub proxy: Option<String>,
+    pub recursion: Option<RecursionConfig>,
+    /// Extra specs served on the same port, keyed by `Host` header.
+    pub hosts: Option<HashMap<String, String>>,
+    /// Serve the first generated body again for the same method, path and status.
+    pub cache_responses: Option<bool>,
+    /// Serve the first generated body again for the same route and path
+    /// parameter values, so `/users/7` stays the same user.
+    pub cache_by_path_params: Option<bool>,
+    /// Copy path and query parameter values into same-named properties of
+    /// generated bodies, so `GET /users/7` returns `"id": 7`.
+    pub echo_params: Option<bool>,
+    /// Store POSTed entities and answer GET, PUT, PATCH and DELETE on their
+    /// routes from the store instead of generating bodies.
+    pub stateful: Option<bool>,
+    /// Compress responses with gzip, deflate, brotli or zstd when the client's
+    /// `Accept-Encoding` allows it.
+    pub compress: Option<bool>,
+    /// Answer clients preferring XML (`Accept: application/xml`) with XML
+    /// even where a response only documents `application/json`.
+    pub xml_responses: Option<bool>,
+    /// Serve request counts and latencies in the Prometheus text format at
+    /// `/metrics`, in place of any such route in the spec. Only reachable
+    /// from `admin_allow_ips`.
+    pub metrics: Option<bool>,
+    pub cors: Option<CorsConfig>,
+    /// Answer with the spec's named `examples` instead of generated data.
+    pub example_strategy: Option<ExampleStrategy>,
+    /// Use `example` values from the spec instead of generated data, for
+    /// whole responses and single schema fields (default: true).
+    pub prefer_examples: Option<bool>,
+    /// Refuse to start when the spec uses a `format` spit does not know, and
+    /// validate the `uuid`, `date-time`, `date`, `email`, `ipv4`, `ipv6`,
+    /// `uri` and `hostname` formats of requests.
+    pub strict_formats: Option<bool>,
+    /// Shifts generated `date-time` values and the `Date` header, e.g. `-2h`
+    /// or `+1d30m`.
+    pub clock_offset: Option<String>,
+    /// Networks (CIDR) allowed to use the `/__spit` admin endpoints and
+    /// `/metrics`; loopback only when unset.
+    pub admin_allow_ips: Option<Vec<ipnet::IpNet>>,
+    /// Shut down with an error after this many requests fail validation.
+    pub fail_on_validation_error: Option<usize>,
+    /// Adds `Link` headers with next/prev pages to collection responses.
+    pub pagination: Option<PaginationConfig>,
+    /// Path globs to keep; every route is kept when unset.
+    pub only: Option<Vec<String>>,
+    /// Path globs to drop, applied after `only`.
+    pub exclude: Option<Vec<String>>,
+    /// Match literal path segments regardless of case.
+    pub case_insensitive_paths: Option<bool>,
+    /// Samples each response delay from a distribution; takes precedence
+    /// over `ttfb_delay` and `delay`.
+    pub latency: Option<LatencyConfig>,
+    /// Fails or slows down a random share of requests.
+    pub chaos: Option<ChaosConfig>,
+    /// Answers 429 once a client or route exceeds a request rate.
+    pub rate_limit: Option<RateLimitConfig>,
+    /// Precision of generated non-integer numbers.
+    pub number_format: Option<NumberFormat>,
+    /// Report every validation failure of a request instead of the first.
+    pub aggregate_errors: Option<bool>,
+    /// Check generated bodies against their schema and answer 500 when one
+    /// doesn't match (e.g. a field pattern of the wrong type).
+    pub strict_responses: Option<bool>,
+    /// Generate bodies of complex schemas on the blocking thread pool.
+    pub offload_generation: Option<bool>,
+    /// Schema complexity (roughly, values generated) from which
+    /// `offload_generation` applies.
+    pub offload_threshold: Option<usize>,
+    /// Pick fakers for format-less strings from their field name, title or
+    /// description (`email`, `phone`, `firstName`, ...).
+    pub smart_generation: Option<bool>,
+    /// Makes generated data reproducible: the same request gets the same
+    /// body from any server started with the same seed. Also bases generated
+    /// dates on an instant picked by the seed, and seeds the draws of `random`
+    /// examples, async job and stored entity ids, delays and `chaos`, which
+    /// then repeat for the same sequence of requests.
+    pub seed: Option<u64>,
+    /// Language of generated names, companies, phone numbers and addresses.
+    pub locale: Option<Locale>,
+    /// Settings overriding the ones above for some routes, keyed by
+    /// `METHOD path` or just a path. Paths may be globs and match either the
+    /// route template or the request path.
+    pub routes: Option<HashMap<String, MockConfig>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NumberFormat {
+    #[serde(default = "default_decimals")]
+    pub decimals: u32,
+    /// Emit numbers as fixed-decimal strings (`"12.50"`) instead of JSON numbers.
+    #[serde(default)]
+    pub as_string: bool,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self {
+            decimals: default_decimals(),
+            as_string: false,
+        }
+    }
+}
+
+/// Response delay distribution, in milliseconds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencyConfig {
+    pub distribution: LatencyDistribution,
+    pub mean: f64,
+    /// Spread of the `normal` distribution; ignored by `exponential`.
+    #[serde(default)]
+    pub stddev: f64,
+    /// Upper bound on a sampled delay.
+    #[serde(default = "default_max_latency")]
+    pub max: f64,
+}
+
+/// Random failures and latency spikes, to exercise client retries. Draws
+/// follow `seed` when one is set, so a run's failures can be replayed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChaosConfig {
+    /// Share of requests (0.0-1.0) answered with one of `statuses`.
+    pub failure_rate: f64,
+    #[serde(default = "default_chaos_statuses")]
+    pub statuses: Vec<u16>,
+    /// Share of requests (0.0-1.0) delayed by an extra `spike_ms`.
+    #[serde(default)]
+    pub spike_rate: f64,
+    #[serde(default)]
+    pub spike_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LatencyDistribution {
+    Normal,
+    Exponential,
+}
+
+impl LatencyConfig {
+    /// Draws a delay, clamped to `0..=max`.
+    pub fn sample(&self, rng: &mut StdRng) -> std::time::Duration {
+        let millis = match self.distribution {
+            LatencyDistribution::Normal => {
+                // Box-Muller transform.
+                let u1 = 1.0 - rng.gen::<f64>();
+                let u2 = rng.gen::<f64>();
+                let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+                self.mean + self.stddev * z
+            }
+            LatencyDistribution::Exponential => -self.mean * (1.0 - rng.gen::<f64>()).ln(),
+        };
+        self.clamped(millis)
+    }
+
+    /// The mean delay, for when nothing can be drawn.
+    pub fn mean(&self) -> std::time::Duration {
+        self.clamped(self.mean)
+    }
+
+    fn clamped(&self, millis: f64) -> std::time::Duration {
+        std::time::Duration::from_secs_f64(millis.clamp(0.0, self.max) / 1000.0)
+    }
+}
+
+/// Parameter names and base URL used to build pagination `Link` headers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaginationConfig {
+    /// Prefix of the page URLs; the request's own scheme and host when unset.
+    #[serde(default)]
+    pub base_url: Option<String>,
+    #[serde(default = "default_limit_param")]
+    pub limit_param: String,
+    #[serde(default = "default_offset_param")]
+    pub offset_param: String,
+    #[serde(default = "default_page_limit")]
+    pub default_limit: u64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExampleStrategy {
+    First,
+    Random,
+    RoundRobin,
+}
+
+/// Response delay in milliseconds: a fixed `delay: 100`, or
+/// `delay: { base: 100, jitter: 50 }` drawn uniformly from 50 to 150.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Delay {
+    Fixed(u64),
+    Jitter {
+        base: u64,
+        #[serde(default)]
+        jitter: u64,
+    },
+}
+
+impl Delay {
+    pub fn sample(&self, rng: &mut StdRng) -> std::time::Duration {
+        let millis = match *self {
+            Delay::Fixed(millis) => millis,
+            Delay::Jitter { base, jitter } => {
+                rng.gen_range(base.saturating_sub(jitter)..=base.saturating_add(jitter))
+            }
+        };
+        std::time::Duration::from_millis(millis)
+    }
+
+    /// The delay without jitter, for when nothing can be drawn.
+    pub fn base(&self) -> std::time::Duration {
+        match *self {
+            Delay::Fixed(millis) | Delay::Jitter { base: millis, .. } => {
+                std::time::Duration::from_millis(millis)
+            }
+        }
+    }
+}
+
+/// Locales the faker has data for; fakes it lacks data for in a locale
+/// (e.g. Arabic addresses) fall back to English.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum Locale {
+    #[default]
+    En,
+    Fr,
+    PtBr,
+    Ja,
+    ZhCn,
+    ZhTw,
+    Ar,
+}
+
+/// Shape of the tree generated for self-referential schemas.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecursionConfig {
+    pub max_depth: usize,
+    pub children_per_level: usize,
 }
 
-#[derive(Default, Clone, Debug)]
+pub type RouteHandlers = Vec<(String, Value)>;
+
+/// Routes keyed by path template, kept sorted so listing and matching order
+/// is the same on every run.
+pub type RouteTable = BTreeMap<String, RouteHandlers>;
+
+/// Method, request path and status of a cached response.
+pub type ResponseKey = (String, String, u16);
+
+/// Method, route template, path parameter values and status of a cached
+/// entity.
+pub type EntityKey = (String, String, Vec<String>, u16);
+
+/// Everything a server answers from. It is shared behind an `RwLock` that
+/// requests only read; what they change sits behind a `Mutex` of its own,
+/// so requests for different things don't wait on each other.
+#[derive(Default, Debug)]
 pub struct MockState {
-    pub routes: HashMap<String, Vec<(String, Value)>>,
+    pub routes: RouteTable,
+    /// Index of `routes` used to match request paths.
+    pub router: RouteTree,
     pub config: MockConfig,
-    pub request_log: Vec<RequestLog>,
+    pub request_log: Mutex<Vec<RequestLog>>,
+    pub access_log: Option<Arc<File>>,
+    /// Set while the spec is being swapped; requests get a 503 meanwhile.
+    pub reloading: bool,
+    pub response_cache: Mutex<HashMap<ResponseKey, Value>>,
+    /// Bodies by method, route template, path parameter values and status for
+    /// `cache_by_path_params`.
+    pub entity_cache: Mutex<HashMap<EntityKey, Value>>,
+    /// Next example index per route for `round_robin` selection.
+    pub example_cursors: Mutex<HashMap<String, usize>>,
+    /// Jobs started through `x-spit-async` routes, keyed by id. Jobs `done`
+    /// for longer than `FINISHED_JOB_TTL` are evicted as new ones start.
+    pub jobs: Mutex<HashMap<String, AsyncJob>>,
+    /// Templates of the `x-spit-async` status routes in `routes`.
+    pub status_routes: HashSet<String>,
+    /// Hits per request path that matched no route.
+    pub unmatched: Mutex<BTreeMap<String, u64>>,
+    /// Source of `chaos`, `latency`, delay `jitter`, `random` example, async
+    /// job id and stored entity id draws, seeded from `seed` on first use.
+    pub rng: Mutex<Option<StdRng>>,
+    /// `rate_limit` buckets by `RateLimitConfig::bucket_key`.
+    pub rate_limits: Mutex<HashMap<String, TokenBucket>>,
+    /// Entities created in `stateful` mode.
+    pub collections: Mutex<Collections>,
+    /// Next value of each `sequence` field pattern.
+    pub sequences: Sequences,
+}
+
+/// Next value of each `sequence` field pattern, by its `patterns` key.
+pub type Sequences = Arc<Mutex<HashMap<String, i64>>>;
+
+/// Distinct unmatched paths kept; scanners would otherwise grow the map
+/// without bound.
+pub const MAX_UNMATCHED_PATHS: usize = 1000;
+
+impl MockState {
+    /// Counts a hit on `path`. Once `MAX_UNMATCHED_PATHS` paths are known,
+    /// a new one replaces the least hit.
+    pub fn record_unmatched(&self, path: &str) {
+        let Ok(mut unmatched) = self.unmatched.lock() else {
+            return;
+        };
+        if !unmatched.contains_key(path) && unmatched.len() >= MAX_UNMATCHED_PATHS {
+            let least_hit = unmatched
+                .iter()
+                .min_by_key(|(_, hits)| **hits)
+                .map(|(path, _)| path.clone());
+            if let Some(least_hit) = least_hit {
+                unmatched.remove(&least_hit);
+            }
+        }
+        *unmatched.entry(path.to_string()).or_insert(0) += 1;
+    }
+
+    /// Runs `draw` with `rng`, seeding it from `seed` on first use.
+    pub fn with_rng<T>(&self, seed: Option<u64>, draw: impl FnOnce(&mut StdRng) -> T) -> Option<T> {
+        let mut rng = self.rng.lock().ok()?;
+        let rng = rng
+            .get_or_insert_with(|| seed.map_or_else(StdRng::from_entropy, StdRng::seed_from_u64));
+        Some(draw(rng))
+    }
+}
+
+/// A random (version 4) UUID drawn from `rng`.
+pub fn random_uuid(rng: &mut StdRng) -> String {
+    uuid::Builder::from_random_bytes(rng.gen())
+        .into_uuid()
+        .to_string()
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct MockFieldConfig {
+    #[serde(default)]
     pub patterns: HashMap<String, MockPattern>,
+    /// Casing applied to string values of the named fields, keyed like
+    /// `patterns`.
+    #[serde(default)]
+    pub transforms: HashMap<String, StringTransform>,
+    /// Fields whose values must not repeat across the items of a generated array.
+    #[serde(default)]
+    pub unique: Vec<String>,
+    /// Relative weights of discriminator values, keyed by discriminator
+    /// property name, for `oneOf`/`anyOf` unions.
+    #[serde(default)]
+    pub discriminators: HashMap<String, HashMap<String, f64>>,
+    /// Fields set from an arithmetic expression over the other fields of the
+    /// same object, e.g. `total: price * quantity`.
+    #[serde(default)]
+    pub computed: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StringTransform {
+    Upper,
+    Lower,
+    Title,
+    Snake,
+    Kebab,
+}
+
+impl StringTransform {
+    pub fn apply(&self, value: &str) -> String {
+        match self {
+            StringTransform::Upper => value.to_uppercase(),
+            StringTransform::Lower => value.to_lowercase(),
+            StringTransform::Title => split_words(value)
+                .iter()
+                .map(|word| {
+                    let mut chars = word.chars();
+                    match chars.next() {
+                        Some(first) => {
+                            first.to_uppercase().collect::<String>()
+                                + &chars.as_str().to_lowercase()
+                        }
+                        None => String::new(),
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+            StringTransform::Snake => split_words(value)
+                .iter()
+                .map(|word| word.to_lowercase())
+                .collect::<Vec<_>>()
+                .join("_"),
+            StringTransform::Kebab => split_words(value)
+                .iter()
+                .map(|word| word.to_lowercase())
+                .collect::<Vec<_>>()
+                .join("-"),
+        }
+    }
+}
+
+/// Splits on separators and lower-to-upper camelCase boundaries.
+fn split_words(value: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut prev_lower = false;
+
+    for c in value.chars() {
+        if !c.is_alphanumeric() {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            prev_lower = false;
+            continue;
+        }
+        if c.is_uppercase() && prev_lower && !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+        }
+        prev_lower = c.is_lowercase() || c.is_numeric();
+        current.push(c);
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+
+    words
 }
 
 fn default_card_length() -> usize {
     16
 }
 
+fn default_sequence_start() -> i64 {
+    1
+}
+
+fn default_sequence_step() -> i64 {
+    1
+}
+
+fn default_decimals() -> u32 {
+    2
+}
+
+fn default_max_latency() -> f64 {
+    30_000.0
+}
+
+fn default_chaos_statuses() -> Vec<u16> {
+    vec![500, 503]
+}
+
+fn default_limit_param() -> String {
+    "limit".to_string()
+}
+
+fn default_offset_param() -> String {
+    "offset".to_string()
+}
+
+fn default_page_limit() -> u64 {
+    20
+}
+
+impl MockConfig {
+    /// The `regex` field patterns of this config and its `routes` overrides.
+    pub fn regex_patterns(&self) -> Vec<&str> {
+        let own = self
+            .fields
+            .iter()
+            .flat_map(|fields| fields.patterns.values())
+            .filter_map(|pattern| match pattern {
+                MockPattern::Regex { pattern } => Some(pattern.as_str()),
+                _ => None,
+            });
+        let routes = self
+            .routes
+            .iter()
+            .flatten()
+            .flat_map(|(_, overrides)| overrides.regex_patterns());
+        own.chain(routes).collect()
+    }
+
+    /// Checks for settings that cannot be honored together or at all.
+    pub fn validate(&self) -> Result<(), MockServerError> {
+        let mut problems = Vec::new();
+
+        if let Some(bodies) = &self.default_bodies {
+            for class in bodies.keys() {
+                let valid = class.len() == 3
+                    && matches!(class.as_bytes()[0], b'1'..=b'5')
+                    && class[1..].eq_ignore_ascii_case("xx");
+                if !valid {
+                    problems.push(format!(
+                        "default_bodies key `{}` is not a status class like 2xx",
+                        class
+                    ));
+                }
+            }
+        }
+
+        if let Some(recursion) = &self.recursion {
+            if recursion.max_depth > 1 && recursion.children_per_level == 0 {
+                problems.push(
+                    "recursion.max_depth has no effect with children_per_level 0".to_string(),
+                );
+            }
+        }
+
+        if let Some(latency) = &self.latency {
+            if !(latency.mean >= 0.0 && latency.stddev >= 0.0 && latency.max >= 0.0) {
+                problems.push("latency mean, stddev and max must not be negative".to_string());
+            }
+        }
+
+        if let Some(chaos) = &self.chaos {
+            if !(0.0..=1.0).contains(&chaos.failure_rate)
+                || !(0.0..=1.0).contains(&chaos.spike_rate)
+            {
+                problems.push("chaos failure_rate and spike_rate must be within 0-1".to_string());
+            }
+            if chaos.statuses.is_empty() {
+                problems.push("chaos.statuses cannot be empty".to_string());
+            }
+            for status in &chaos.statuses {
+                if !(100..=599).contains(status) {
+                    problems.push(format!("chaos status {} is not an HTTP status", status));
+                }
+            }
+        }
+
+        if let Some(limit) = &self.rate_limit {
+            if limit.requests == 0 || limit.window_secs == 0 {
+                problems.push("rate_limit requests and window_secs must be at least 1".to_string());
+            }
+        }
+
+        if let Some(proxy) = &self.proxy {
+            if let Err(e) = parse_base_url(proxy) {
+                problems.push(e);
+            }
+        }
+
+        if let Some(format) = &self.number_format {
+            if format.decimals > 15 {
+                problems.push("number_format.decimals cannot exceed 15".to_string());
+            }
+        }
+
+        if let Some(fields) = &self.fields {
+            for (field, pattern) in &fields.patterns {
+                match pattern {
+                    MockPattern::Regex { pattern } => match regex::Regex::new(pattern) {
+                        Ok(regex) => {
+                            let mut rng = StdRng::seed_from_u64(0);
+                            if generate_matching(&regex, 0..=usize::MAX, &mut rng).is_none() {
+                                problems.push(format!(
+                                    "fields.patterns.{}: spit cannot generate a string matching `{}`",
+                                    field, pattern
+                                ));
+                            }
+                        }
+                        Err(e) => problems.push(format!("fields.patterns.{}: {}", field, e)),
+                    },
+                    MockPattern::Ref { kind } if !faker_kinds().contains(&kind.as_str()) => {
+                        problems.push(format!(
+                            "fields.patterns.{}: unknown kind `{}`, expected one of {}",
+                            field,
+                            kind,
+                            faker_kinds().join(", ")
+                        ))
+                    }
+                    _ => {}
+                }
+            }
+            for (field, source) in &fields.computed {
+                if let Err(e) = Expr::parse(source) {
+                    problems.push(format!("fields.computed.{}: {}", field, e));
+                }
+            }
+        }
+
+        for (key, overrides) in self.routes.iter().flatten() {
+            match overrides.validate() {
+                Ok(()) => {}
+                Err(MockServerError::Config(problem)) => {
+                    problems.push(format!("routes.{}: {}", key, problem))
+                }
+                Err(e) => problems.push(format!("routes.{}: {}", key, e)),
+            }
+        }
+
+        if let Some(offset) = &self.clock_offset {
+            if parse_offset(offset).is_none() {
+                problems.push(format!(
+                    "clock_offset `{}` is not a duration like -2h or 1d30m",
+                    offset
+                ));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(MockServerError::Config(problems.join("; ")))
+        }
+    }
+
+    /// Current time as seen by the mock, shifted by `clock_offset`.
+    pub fn now(&self) -> chrono::DateTime<chrono::Utc> {
+        chrono::Utc::now() + self.offset()
+    }
+
+    /// The instant generated date values are based on: `now`, or with a
+    /// `seed` a still instant picked by the seed (shifted by `clock_offset`),
+    /// so generated dates repeat across runs too.
+    pub fn generated_now(&self) -> chrono::DateTime<chrono::Utc> {
+        match self.seed {
+            Some(seed) => seeded_now(seed) + self.offset(),
+            None => self.now(),
+        }
+    }
+
+    fn offset(&self) -> chrono::Duration {
+        self.clock_offset
+            .as_deref()
+            .and_then(parse_offset)
+            .unwrap_or_else(chrono::Duration::zero)
+    }
+
+    /// The config for a request to `path` matching the route `template`:
+    /// the `routes` entries matching it layered over this config, the most
+    /// specific key (fewest wildcards, then with a method) applied last.
+    pub fn for_route(&self, method: &str, template: &str, path: &str) -> MockConfig {
+        let mut matching: Vec<(&String, &MockConfig)> = self
+            .routes
+            .iter()
+            .flatten()
+            .filter(|(key, _)| route_key_matches(key, method, template, path))
+            .collect();
+        if matching.is_empty() {
+            return self.clone();
+        }
+        matching.sort_by_key(|(key, _)| (route_key_specificity(key), *key));
+
+        let mut config = self.clone();
+        for (key, overrides) in matching {
+            config = match overrides.clone().layered_over(config) {
+                Ok(config) => config,
+                Err(e) => {
+                    warn!("Ignoring routes.{}: {}", key, e);
+                    return self.clone();
+                }
+            };
+        }
+        config
+    }
+
+    /// Layers `self` on top of `base`: settings present in `self` win, nested
+    /// maps (headers, field patterns) are merged key by key.
+    pub fn layered_over(self, base: MockConfig) -> Result<MockConfig, serde_json::Error> {
+        let mut merged = serde_json::to_value(base)?;
+        merge_values(&mut merged, serde_json::to_value(self)?);
+        serde_json::from_value(merged)
+    }
+}
+
+/// Splits a `routes` key into its method (`None` for any, also written `*`)
+/// and path glob.
+fn split_route_key(key: &str) -> (Option<&str>, &str) {
+    match key.trim().split_once(char::is_whitespace) {
+        Some((method, glob)) => (Some(method).filter(|m| *m != "*"), glob.trim()),
+        None => (None, key.trim()),
+    }
+}
+
+fn route_key_matches(key: &str, method: &str, template: &str, path: &str) -> bool {
+    let (key_method, glob) = split_route_key(key);
+    key_method.is_none_or(|key_method| key_method.eq_ignore_ascii_case(method))
+        && (glob_matches(glob, template) || glob_matches(glob, path))
+}
+
+/// Orders `routes` keys from least to most specific: globs before exact
+/// paths, fewer literal characters first, and keys without a method before
+/// those with one.
+fn route_key_specificity(key: &str) -> (bool, usize, bool) {
+    let (method, glob) = split_route_key(key);
+    let wildcards = glob.chars().filter(|c| matches!(c, '*' | '?')).count();
+    (wildcards == 0, glob.len() - wildcards, method.is_some())
+}
+
+/// The still clock of a seeded mock: an instant within the ten years from
+/// 2020-01-01, picked by `seed`.
+fn seeded_now(seed: u64) -> chrono::DateTime<chrono::Utc> {
+    const EPOCH: i64 = 1_577_836_800;
+    const SPAN: u64 = 10 * 365 * 24 * 60 * 60;
+    let offset = StdRng::seed_from_u64(seed).gen_range(0..SPAN) as i64;
+    chrono::DateTime::from_timestamp(EPOCH + offset, 0).unwrap_or_default()
+}
+
+/// Parses a signed duration made of `<n><unit>` parts (`s`, `m`, `h`, `d`),
+/// e.g. `90s`, `-2h` or `+1d12h`.
+fn parse_offset(offset: &str) -> Option<chrono::Duration> {
+    let offset = offset.trim();
+    let (negative, mut rest) = match offset.as_bytes().first()? {
+        b'-' => (true, &offset[1..]),
+        b'+' => (false, &offset[1..]),
+        _ => (false, offset),
+    };
+    if rest.is_empty() {
+        return None;
+    }
+
+    let mut total = chrono::Duration::zero();
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let amount: i64 = rest[..digits].parse().ok()?;
+        let unit = rest[digits..].chars().next()?;
+        total += match unit {
+            's' => chrono::Duration::try_seconds(amount)?,
+            'm' => chrono::Duration::try_minutes(amount)?,
+            'h' => chrono::Duration::try_hours(amount)?,
+            'd' => chrono::Duration::try_days(amount)?,
+            _ => return None,
+        };
+        rest = &rest[digits + 1..];
+    }
+
+    Some(if negative { -total } else { total })
+}
+
+fn merge_values(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base_map), Value::Object(overlay_map)) => {
+            for (key, value) in overlay_map {
+                match base_map.get_mut(&key) {
+                    Some(existing) => merge_values(existing, value),
+                    None => {
+                        base_map.insert(key, value);
+                    }
+                }
+            }
+        }
+        (_, Value::Null) => {}
+        (base, overlay) => *base = overlay,
+    }
+}
+
 impl MockPattern {
-    pub fn generate_value(&self) -> serde_json::Value {
+    pub fn generate_value(&self, config: &MockConfig, rng: &mut StdRng) -> serde_json::Value {
         match self {
             MockPattern::Enum { values } => {
-                let index = (0..values.len()).fake::<usize>();
+                let index = (0..values.len()).fake_with_rng::<usize, _>(rng);
                 serde_json::Value::String(values[index].clone())
             }
             MockPattern::Number { min, max, decimals } => {
                 let min_val = min.unwrap_or(0.0);
                 let max_val = max.unwrap_or(100.0);
-                let num = min_val + (max_val - min_val) * rand::random::<f64>();
+                let num = min_val + (max_val - min_val) * rng.gen::<f64>();
 
                 if let Some(dec) = decimals {
                     let factor = 10_f64.powi(*dec as i32);
@@ -82,19 +890,26 @@ impl MockPattern {
             }
             MockPattern::CreditCard { length } => {
                 let card_num: String = (0..*length)
-                    .map(|_| rand::random::<u8>() % 10)
+                    .map(|_| rng.gen::<u8>() % 10)
                     .map(|n| n.to_string())
                     .collect();
                 serde_json::Value::String(card_num)
             }
             MockPattern::DateTime { format } => {
-                let now = chrono::Utc::now();
                 let formatted = match format {
-                    Some(fmt) => now.format(fmt),
-                    None => now.format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+                    Some(fmt) => config.generated_now().format(fmt),
+                    None => config.generated_now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
                 };
                 serde_json::Value::String(formatted.to_string())
             }
+            // Compiled once in `SwaggerState`; see `MockGenerator`.
+            MockPattern::Regex { .. } => serde_json::Value::Null,
+            // Counting needs the shared `Sequences`; see `MockGenerator`.
+            MockPattern::Sequence { start, .. } => serde_json::Value::from(*start),
+            // Filled in once the rest of the object exists; see `MockGenerator`.
+            MockPattern::Template { template } => serde_json::Value::String(template.clone()),
+            MockPattern::Ref { kind } => fake_kind(kind, config.locale.unwrap_or_default(), rng)
+                .map_or(serde_json::Value::Null, serde_json::Value::String),
         }
     }
 }
@@ -0,0 +1,414 @@
+use serde_json::{json, Value};
+
+/// JSON Schema (draft 2020-12) describing `MockConfig`, for editor
+/// autocompletion and validation of config files.
+pub fn mock_config_schema() -> Value {
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "$id": "https://github.com/ErickJ3/spit/mock-config.schema.json",
+        "title": "spit MockConfig",
+        "type": "object",
+        "additionalProperties": false,
+        "properties": {
+            "delay": {
+                "description": "Global response delay in milliseconds, fixed or as a base plus or minus a uniform jitter; ignored when latency or ttfb_delay is set",
+                "oneOf": [
+                    { "type": ["integer", "null"], "minimum": 0 },
+                    {
+                        "type": "object",
+                        "properties": {
+                            "base": { "type": "integer", "minimum": 0 },
+                            "jitter": { "type": "integer", "minimum": 0, "default": 0 }
+                        },
+                        "required": ["base"],
+                        "additionalProperties": false
+                    }
+                ]
+            },
+            "ttfb_delay": {
+                "description": "Delay in milliseconds before the response headers are sent; overrides delay, and is ignored when latency is set",
+                "type": ["integer", "null"],
+                "minimum": 0
+            },
+            "body_delay": {
+                "description": "Delay in milliseconds before each 1 KiB chunk of the response body",
+                "type": ["integer", "null"],
+                "minimum": 0
+            },
+            "status_code": {
+                "description": "Default response status code",
+                "type": ["integer", "null"],
+                "minimum": 100,
+                "maximum": 599
+            },
+            "headers": {
+                "description": "Headers added to every mocked response",
+                "type": ["object", "null"],
+                "additionalProperties": { "type": "string" }
+            },
+            "fields": {
+                "oneOf": [{ "type": "null" }, { "$ref": "#/$defs/MockFieldConfig" }]
+            },
+            "default_bodies": {
+                "description": "Bodies used when a response has no schema, keyed by status class",
+                "type": ["object", "null"],
+                "propertyNames": { "pattern": "^[1-5]xx$" }
+            },
+            "access_log": {
+                "description": "File receiving a Combined Log Format line per request",
+                "type": ["string", "null"]
+            },
+            "log_file": {
+                "description": "File receiving each request, with request and response bodies, as a JSON line",
+                "type": ["string", "null"]
+            },
+            "har": {
+                "description": "File receiving an HTTP Archive (HAR 1.2) of all traffic on shutdown",
+                "type": ["string", "null"]
+            },
+            "playback": {
+                "description": "HAR file whose recorded responses are served for matching requests",
+                "type": ["string", "null"]
+            },
+            "playback_timings": {
+                "description": "Delay each played back response by its recorded time (default: true)",
+                "type": ["boolean", "null"]
+            },
+            "proxy": {
+                "description": "Base URL receiving the requests no route matches and those to x-spit-passthrough operations",
+                "type": ["string", "null"],
+                "format": "uri"
+            },
+            "recursion": {
+                "description": "Tree shape generated for self-referential schemas",
+                "type": ["object", "null"],
+                "required": ["max_depth", "children_per_level"],
+                "properties": {
+                    "max_depth": { "type": "integer", "minimum": 0 },
+                    "children_per_level": { "type": "integer", "minimum": 0 }
+                }
+            },
+            "hosts": {
+                "description": "Extra spec files or URLs served on the same port, keyed by Host header",
+                "type": ["object", "null"],
+                "additionalProperties": { "type": "string" }
+            },
+            "cache_responses": {
+                "description": "Serve the first generated body again for the same method, path and status",
+                "type": ["boolean", "null"]
+            },
+            "cache_by_path_params": {
+                "description": "Serve the first generated body again for the same route and path parameter values",
+                "type": ["boolean", "null"]
+            },
+            "echo_params": {
+                "description": "Copy path and query parameter values into same-named properties of generated bodies",
+                "type": ["boolean", "null"]
+            },
+            "xml_responses": {
+                "description": "Answer clients preferring XML with XML even where a response only documents application/json",
+                "type": ["boolean", "null"]
+            },
+            "compress": {
+                "description": "Compress responses with gzip, deflate, brotli or zstd per the client's Accept-Encoding",
+                "type": ["boolean", "null"]
+            },
+            "stateful": {
+                "description": "Store POSTed entities and answer GET, PUT, PATCH and DELETE on their routes from the store",
+                "type": ["boolean", "null"]
+            },
+            "cors": {
+                "oneOf": [{ "type": "null" }, { "$ref": "#/$defs/CorsConfig" }]
+            },
+            "example_strategy": {
+                "description": "Answer with the spec's named examples instead of generated data",
+                "enum": ["first", "random", "round_robin", null]
+            },
+            "prefer_examples": {
+                "description": "Use example values from the spec instead of generated data (default: true)",
+                "type": ["boolean", "null"],
+                "default": true
+            },
+            "strict_formats": {
+                "description": "Refuse to start when the spec uses an unknown schema format, and validate the string formats of requests",
+                "type": ["boolean", "null"]
+            },
+            "clock_offset": {
+                "description": "Shift applied to generated date-time values and the Date header, e.g. -2h or 1d30m",
+                "type": ["string", "null"],
+                "pattern": "^[+-]?([0-9]+[smhd])+$"
+            },
+            "admin_allow_ips": {
+                "description": "CIDR networks allowed to use the /__spit admin endpoints (default: loopback only)",
+                "type": ["array", "null"],
+                "items": { "type": "string" }
+            },
+            "fail_on_validation_error": {
+                "description": "Exit with an error after this many requests fail validation",
+                "type": ["integer", "null"],
+                "minimum": 1
+            },
+            "pagination": {
+                "description": "Add Link headers with next/prev pages to collection responses",
+                "type": ["object", "null"],
+                "properties": {
+                    "base_url": { "type": ["string", "null"] },
+                    "limit_param": { "type": "string", "default": "limit" },
+                    "offset_param": { "type": "string", "default": "offset" },
+                    "default_limit": { "type": "integer", "minimum": 1, "default": 20 }
+                }
+            },
+            "only": {
+                "description": "Path globs of the routes to serve (* within a segment, ** across segments)",
+                "type": ["array", "null"],
+                "items": { "type": "string" }
+            },
+            "exclude": {
+                "description": "Path globs of routes to leave out",
+                "type": ["array", "null"],
+                "items": { "type": "string" }
+            },
+            "aggregate_errors": {
+                "description": "Answer invalid requests with all validation errors instead of the first",
+                "type": ["boolean", "null"]
+            },
+            "strict_responses": {
+                "description": "Answer 500 when a generated body breaks its own schema, e.g. through a field pattern",
+                "type": ["boolean", "null"]
+            },
+            "offload_generation": {
+                "description": "Generate bodies of complex schemas on the blocking thread pool",
+                "type": ["boolean", "null"]
+            },
+            "locale": {
+                "description": "Language of generated names, companies, phone numbers and addresses",
+                "enum": ["en", "fr", "pt_br", "ja", "zh_cn", "zh_tw", "ar", null],
+                "default": "en"
+            },
+            "metrics": {
+                "description": "Serve request counts and latencies in the Prometheus text format at /metrics",
+                "type": ["boolean", "null"]
+            },
+            "offload_threshold": {
+                "description": "Schema complexity (roughly, values generated) from which generation is offloaded",
+                "type": ["integer", "null"],
+                "minimum": 0,
+                "default": 500
+            },
+            "seed": {
+                "description": "Seed making generated data the same for the same request on every run",
+                "type": ["integer", "null"],
+                "minimum": 0
+            },
+            "smart_generation": {
+                "description": "Choose fakers for strings without a format from their name, title or description",
+                "type": ["boolean", "null"]
+            },
+            "case_insensitive_paths": {
+                "description": "Match literal path segments regardless of case",
+                "type": ["boolean", "null"]
+            },
+            "latency": {
+                "description": "Sample each response delay (ms) from a distribution; when set, ttfb_delay and delay are ignored",
+                "type": ["object", "null"],
+                "required": ["distribution", "mean"],
+                "properties": {
+                    "distribution": { "enum": ["normal", "exponential"] },
+                    "mean": { "type": "number", "minimum": 0 },
+                    "stddev": { "type": "number", "minimum": 0, "default": 0 },
+                    "max": { "type": "number", "minimum": 0, "default": 30000 }
+                }
+            },
+            "chaos": {
+                "description": "Fail or slow down a random share of requests",
+                "type": ["object", "null"],
+                "required": ["failure_rate"],
+                "properties": {
+                    "failure_rate": { "type": "number", "minimum": 0, "maximum": 1 },
+                    "statuses": {
+                        "type": "array",
+                        "items": { "type": "integer", "minimum": 100, "maximum": 599 },
+                        "default": [500, 503]
+                    },
+                    "spike_rate": { "type": "number", "minimum": 0, "maximum": 1, "default": 0 },
+                    "spike_ms": { "type": "integer", "minimum": 0, "default": 0 }
+                }
+            },
+            "rate_limit": {
+                "description": "Answer 429 with Retry-After once more than `requests` arrive per window",
+                "type": ["object", "null"],
+                "required": ["requests"],
+                "properties": {
+                    "requests": { "type": "integer", "minimum": 1 },
+                    "window_secs": { "type": "integer", "minimum": 1, "default": 60 },
+                    "per": {
+                        "description": "Count requests per client IP, per route, or per client and route",
+                        "enum": ["client", "route", "client_route"],
+                        "default": "client"
+                    }
+                }
+            },
+            "routes": {
+                "description": "Settings overriding the global ones for matching routes, keyed by `METHOD path` or a path glob",
+                "type": ["object", "null"],
+                "additionalProperties": { "$ref": "#" }
+            },
+            "number_format": {
+                "description": "Precision of generated non-integer numbers",
+                "type": ["object", "null"],
+                "properties": {
+                    "decimals": { "type": "integer", "minimum": 0, "maximum": 15, "default": 2 },
+                    "as_string": {
+                        "description": "Emit fixed-decimal strings such as \"12.50\" instead of numbers",
+                        "type": "boolean",
+                        "default": false
+                    }
+                }
+            }
+        },
+        "$defs": {
+            "CorsConfig": {
+                "type": "object",
+                "properties": {
+                    "allow_origins": {
+                        "description": "Origins allowed to call the mock (default: any)",
+                        "type": ["array", "null"],
+                        "items": { "type": "string" }
+                    },
+                    "allow_methods": {
+                        "description": "Methods allowed in preflight (default: the requested one)",
+                        "type": ["array", "null"],
+                        "items": { "type": "string" }
+                    },
+                    "allow_headers": {
+                        "description": "Preflight headers to allow, or \"reflect\" to mirror the requested ones",
+                        "oneOf": [
+                            { "type": "null" },
+                            { "const": "reflect" },
+                            { "type": "array", "items": { "type": "string" } }
+                        ]
+                    },
+                    "allow_credentials": {
+                        "description": "Send Access-Control-Allow-Credentials, echoing the request's origin",
+                        "type": ["boolean", "null"]
+                    }
+                }
+            },
+            "MockFieldConfig": {
+                "type": "object",
+                "properties": {
+                    "patterns": {
+                        "type": "object",
+                        "additionalProperties": { "$ref": "#/$defs/MockPattern" }
+                    },
+                    "transforms": {
+                        "description": "Casing applied to string values of the named fields",
+                        "type": "object",
+                        "additionalProperties": {
+                            "enum": ["upper", "lower", "title", "snake", "kebab"]
+                        }
+                    },
+                    "unique": {
+                        "description": "Fields whose values must not repeat across the items of a generated array",
+                        "type": "array",
+                        "items": { "type": "string" }
+                    },
+                    "discriminators": {
+                        "description": "Relative weights of discriminator values, keyed by discriminator property",
+                        "type": "object",
+                        "additionalProperties": {
+                            "type": "object",
+                            "additionalProperties": { "type": "number", "minimum": 0 }
+                        }
+                    },
+                    "computed": {
+                        "description": "Arithmetic expressions (+ - * / and field names) setting fields from the others in the same object",
+                        "type": "object",
+                        "additionalProperties": { "type": "string" }
+                    }
+                }
+            },
+            "MockPattern": {
+                "oneOf": [
+                    {
+                        "type": "object",
+                        "required": ["type", "values"],
+                        "properties": {
+                            "type": { "const": "enum" },
+                            "values": { "type": "array", "items": { "type": "string" } }
+                        }
+                    },
+                    {
+                        "type": "object",
+                        "required": ["type"],
+                        "properties": {
+                            "type": { "const": "number" },
+                            "min": { "type": ["number", "null"] },
+                            "max": { "type": ["number", "null"] },
+                            "decimals": { "type": ["integer", "null"], "minimum": 0 }
+                        }
+                    },
+                    {
+                        "type": "object",
+                        "required": ["type"],
+                        "properties": {
+                            "type": { "const": "card" },
+                            "length": { "type": "integer", "minimum": 1, "default": 16 }
+                        }
+                    },
+                    {
+                        "type": "object",
+                        "required": ["type"],
+                        "properties": {
+                            "type": { "const": "date" },
+                            "format": {
+                                "description": "chrono strftime format",
+                                "type": ["string", "null"]
+                            }
+                        }
+                    },
+                    {
+                        "type": "object",
+                        "required": ["type", "pattern"],
+                        "properties": {
+                            "type": { "const": "regex" },
+                            "pattern": {
+                                "description": "Regular expression the generated strings match",
+                                "type": "string",
+                                "format": "regex"
+                            }
+                        }
+                    },
+                    {
+                        "type": "object",
+                        "required": ["type"],
+                        "properties": {
+                            "type": { "const": "sequence" },
+                            "start": { "type": "integer", "default": 1 },
+                            "step": { "type": "integer", "default": 1 }
+                        }
+                    },
+                    {
+                        "type": "object",
+                        "required": ["type", "template"],
+                        "properties": {
+                            "type": { "const": "template" },
+                            "template": {
+                                "description": "Text with {field} references to the object's other fields and {now}, {seq} or string format tokens",
+                                "type": "string"
+                            }
+                        }
+                    },
+                    {
+                        "type": "object",
+                        "required": ["type", "kind"],
+                        "properties": {
+                            "type": { "const": "ref" },
+                            "kind": { "enum": crate::generator::faker_kinds() }
+                        }
+                    }
+                ]
+            }
+        }
+    })
+}
@@ -0,0 +1,269 @@
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::{
+        header::{self, HeaderMap, HeaderValue},
+        Method,
+    },
+    middleware::Next,
+    web, Error, HttpRequest, HttpResponse,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct CorsConfig {
+    /// Origins allowed to call the mock; any origin when unset or empty.
+    #[serde(default)]
+    pub allow_origins: Option<Vec<String>>,
+    /// Methods allowed in preflight; whatever is requested when unset.
+    #[serde(default)]
+    pub allow_methods: Option<Vec<String>>,
+    pub allow_headers: Option<AllowHeaders>,
+    /// Send `Access-Control-Allow-Credentials: true`. The request's origin is
+    /// then echoed instead of `*`, which browsers refuse with credentials.
+    #[serde(default)]
+    pub allow_credentials: Option<bool>,
+}
+
+/// Headers accepted in preflight: either a fixed list or `"reflect"` to allow
+/// whatever the client asks for in `Access-Control-Request-Headers`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AllowHeaders {
+    List(Vec<String>),
+    Mode(AllowHeadersMode),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AllowHeadersMode {
+    Reflect,
+}
+
+impl std::str::FromStr for AllowHeaders {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("reflect") {
+            Ok(AllowHeaders::Mode(AllowHeadersMode::Reflect))
+        } else {
+            Ok(AllowHeaders::List(
+                s.split(',')
+                    .map(str::trim)
+                    .filter(|h| !h.is_empty())
+                    .map(String::from)
+                    .collect(),
+            ))
+        }
+    }
+}
+
+/// Answers CORS preflight requests and decorates every other response from an
+/// allowed origin with `Access-Control-Allow-Origin`. A no-op unless a
+/// `CorsConfig` is registered as app data.
+pub async fn cors_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let cors = match req.app_data::<web::Data<CorsConfig>>() {
+        Some(cors) => cors.clone(),
+        None => return Ok(next.call(req).await?.map_into_left_body()),
+    };
+
+    if is_preflight(req.request()) {
+        let response = preflight_response(&cors, req.request());
+        return Ok(req.into_response(response).map_into_right_body());
+    }
+
+    let mut response = next.call(req).await?;
+    let origin = response.request().headers().get(header::ORIGIN).cloned();
+    if let Some(origin) = origin {
+        add_allow_origin(&cors, &origin, response.headers_mut());
+    }
+
+    Ok(response.map_into_left_body())
+}
+
+fn is_preflight(req: &HttpRequest) -> bool {
+    req.method() == Method::OPTIONS
+        && req.headers().contains_key(header::ORIGIN)
+        && req
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD)
+}
+
+fn preflight_response(cors: &CorsConfig, req: &HttpRequest) -> HttpResponse {
+    let mut response = HttpResponse::NoContent().finish();
+    let headers = response.headers_mut();
+    let allowed = req
+        .headers()
+        .get(header::ORIGIN)
+        .is_some_and(|origin| add_allow_origin(cors, origin, headers));
+    if !allowed {
+        // Without the allow headers the browser blocks the actual request.
+        return response;
+    }
+
+    match &cors.allow_methods {
+        Some(methods) if !methods.is_empty() => {
+            if let Ok(value) = HeaderValue::from_str(&methods.join(", ")) {
+                headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, value);
+            }
+        }
+        _ => {
+            if let Some(method) = req.headers().get(header::ACCESS_CONTROL_REQUEST_METHOD) {
+                headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, method.clone());
+            }
+        }
+    }
+
+    match &cors.allow_headers {
+        Some(AllowHeaders::Mode(AllowHeadersMode::Reflect)) => {
+            if let Some(requested) = req.headers().get(header::ACCESS_CONTROL_REQUEST_HEADERS) {
+                headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, requested.clone());
+            }
+            headers.append(
+                header::VARY,
+                HeaderValue::from_static("Access-Control-Request-Headers"),
+            );
+        }
+        Some(AllowHeaders::List(allowed)) if !allowed.is_empty() => {
+            if let Ok(value) = HeaderValue::from_str(&allowed.join(", ")) {
+                headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, value);
+            }
+        }
+        _ => {}
+    }
+
+    response
+}
+
+/// Adds `Access-Control-Allow-Origin` (and `-Allow-Credentials`) when
+/// `origin` is allowed, returning whether it is.
+fn add_allow_origin(cors: &CorsConfig, origin: &HeaderValue, headers: &mut HeaderMap) -> bool {
+    let any_origin = cors
+        .allow_origins
+        .as_ref()
+        .is_none_or(|origins| origins.is_empty() || origins.iter().any(|o| o == "*"));
+    let listed = || {
+        cors.allow_origins.iter().flatten().any(|allowed| {
+            origin
+                .to_str()
+                .is_ok_and(|origin| allowed.eq_ignore_ascii_case(origin))
+        })
+    };
+    if !any_origin && !listed() {
+        return false;
+    }
+
+    let credentials = cors.allow_credentials.unwrap_or(false);
+    if any_origin && !credentials {
+        headers.insert(
+            header::ACCESS_CONTROL_ALLOW_ORIGIN,
+            HeaderValue::from_static("*"),
+        );
+    } else {
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin.clone());
+        headers.append(header::VARY, HeaderValue::from_static("Origin"));
+    }
+    if credentials {
+        headers.insert(
+            header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+            HeaderValue::from_static("true"),
+        );
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use super::*;
+
+    fn preflight(cors: &CorsConfig, origin: &str) -> HttpResponse {
+        let req = TestRequest::default()
+            .method(Method::OPTIONS)
+            .insert_header((header::ORIGIN, origin))
+            .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "PUT"))
+            .insert_header((header::ACCESS_CONTROL_REQUEST_HEADERS, "X-Token"))
+            .to_http_request();
+        assert!(is_preflight(&req));
+        preflight_response(cors, &req)
+    }
+
+    fn header(response: &HttpResponse, name: header::HeaderName) -> Option<&str> {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+    }
+
+    #[test]
+    fn parses_allowed_headers() {
+        assert_eq!(
+            "reflect".parse::<AllowHeaders>(),
+            Ok(AllowHeaders::Mode(AllowHeadersMode::Reflect))
+        );
+        assert_eq!(
+            "X-Token, Content-Type,".parse::<AllowHeaders>(),
+            Ok(AllowHeaders::List(vec![
+                "X-Token".to_string(),
+                "Content-Type".to_string()
+            ]))
+        );
+    }
+
+    #[test]
+    fn any_origin_is_allowed_by_default() {
+        let response = preflight(&CorsConfig::default(), "https://app.test");
+        assert_eq!(response.status(), 204);
+        assert_eq!(
+            header(&response, header::ACCESS_CONTROL_ALLOW_ORIGIN),
+            Some("*")
+        );
+        assert_eq!(
+            header(&response, header::ACCESS_CONTROL_ALLOW_METHODS),
+            Some("PUT")
+        );
+        assert_eq!(
+            header(&response, header::ACCESS_CONTROL_ALLOW_HEADERS),
+            None
+        );
+    }
+
+    #[test]
+    fn credentials_echo_the_listed_origin() {
+        let cors = CorsConfig {
+            allow_origins: Some(vec!["https://app.test".to_string()]),
+            allow_methods: Some(vec!["GET".to_string(), "PUT".to_string()]),
+            allow_headers: Some(AllowHeaders::Mode(AllowHeadersMode::Reflect)),
+            allow_credentials: Some(true),
+        };
+        let response = preflight(&cors, "https://APP.test");
+        assert_eq!(
+            header(&response, header::ACCESS_CONTROL_ALLOW_ORIGIN),
+            Some("https://APP.test")
+        );
+        assert_eq!(
+            header(&response, header::ACCESS_CONTROL_ALLOW_CREDENTIALS),
+            Some("true")
+        );
+        assert_eq!(
+            header(&response, header::ACCESS_CONTROL_ALLOW_METHODS),
+            Some("GET, PUT")
+        );
+        assert_eq!(
+            header(&response, header::ACCESS_CONTROL_ALLOW_HEADERS),
+            Some("X-Token")
+        );
+
+        let response = preflight(&cors, "https://other.test");
+        assert_eq!(response.status(), 204);
+        assert_eq!(header(&response, header::ACCESS_CONTROL_ALLOW_ORIGIN), None);
+        assert_eq!(
+            header(&response, header::ACCESS_CONTROL_ALLOW_METHODS),
+            None
+        );
+    }
+}
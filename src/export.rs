@@ -0,0 +1,118 @@
+use std::{fmt::Write as _, path::Path};
+
+use log::info;
+use serde_json::Value;
+
+use crate::{
+    config::MockConfig, generator::MockGenerator, load_service, swagger::SpecSource,
+    MockServerError,
+};
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+pub enum ExportFormat {
+    /// JavaScript module of Mock Service Worker (v2) request handlers
+    Msw,
+}
+
+/// Generates one mocked response per operation of the spec at `source` and
+/// writes them to `out` in `format`, with handler URLs prefixed by `base_url`.
+pub async fn export_fixtures(
+    source: &str,
+    config: &MockConfig,
+    format: ExportFormat,
+    base_url: &str,
+    out: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (state, swagger_state) = load_service(&SpecSource::from(source), config).await?;
+
+    let mut fixtures = Vec::new();
+    for (path, handlers) in &state.routes {
+        for (method, operation) in handlers {
+            let status = response_status(operation, &state.config);
+            let body = operation
+                .get("responses")
+                .and_then(|responses| responses.get(status.to_string()))
+                .and_then(|response| swagger_state.resolve_object(response))
+                .and_then(|response| response.pointer("/content/application~1json/schema"))
+                .map(|schema| {
+                    MockGenerator::new(&swagger_state, &state.config)
+                        .seeded_for(&format!("{} {}", method, path))
+                        .generate_mock_value(schema, None)
+                });
+            fixtures.push((method.as_str(), path.as_str(), status, body));
+        }
+    }
+
+    let module = match format {
+        ExportFormat::Msw => msw_module(&fixtures, base_url)?,
+    };
+    std::fs::write(out, module).map_err(MockServerError::from)?;
+    info!("Exported {} handlers to {}", fixtures.len(), out.display());
+
+    Ok(())
+}
+
+/// The configured status code, or else the first 2xx response the operation
+/// documents.
+fn response_status(operation: &Value, config: &MockConfig) -> u16 {
+    if let Some(status) = config.status_code {
+        return status;
+    }
+
+    operation
+        .get("responses")
+        .and_then(Value::as_object)
+        .and_then(|responses| {
+            responses
+                .keys()
+                .filter_map(|code| code.parse::<u16>().ok())
+                .find(|code| (200..300).contains(code))
+        })
+        .unwrap_or(200)
+}
+
+fn msw_module(
+    fixtures: &[(&str, &str, u16, Option<Value>)],
+    base_url: &str,
+) -> Result<String, serde_json::Error> {
+    let mut module = String::new();
+    module.push_str("// Generated by spit. Do not edit.\n");
+    module.push_str("import { http, HttpResponse } from 'msw';\n\n");
+    let _ = writeln!(
+        module,
+        "export const BASE_URL = {};\n",
+        serde_json::to_string(base_url.trim_end_matches('/'))?
+    );
+    module.push_str("export const handlers = [\n");
+
+    for (method, path, status, body) in fixtures {
+        let body = match body {
+            Some(body) => serde_json::to_string_pretty(body)?.replace('\n', "\n    "),
+            None => "null".to_string(),
+        };
+        let _ = writeln!(
+            module,
+            "  http.{}(`${{BASE_URL}}{}`, () =>\n    HttpResponse.json({}, {{ status: {} }}),\n  ),",
+            method.to_lowercase(),
+            msw_path(path),
+            body,
+            status
+        );
+    }
+
+    module.push_str("];\n");
+    Ok(module)
+}
+
+/// Rewrites OpenAPI `{param}` segments to MSW's `:param` form.
+fn msw_path(path: &str) -> String {
+    path.split('/')
+        .map(
+            |segment| match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                Some(name) => format!(":{}", name),
+                None => segment.to_string(),
+            },
+        )
+        .collect::<Vec<_>>()
+        .join("/")
+}
@@ -0,0 +1,145 @@
+/// Arithmetic over generated fields for `fields.computed`: numbers, field
+/// references (dotted for nested objects), `+ - * /` and parentheses.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Field(String),
+    Neg(Box<Expr>),
+    Binary(Box<Expr>, char, Box<Expr>),
+}
+
+impl Expr {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut parser = Parser {
+            chars: source.chars().collect(),
+            pos: 0,
+        };
+        let expr = parser.sum()?;
+        parser.skip_whitespace();
+        match parser.peek() {
+            None => Ok(expr),
+            Some(c) => Err(format!("unexpected `{}` at {}", c, parser.pos)),
+        }
+    }
+
+    /// The field references in the expression.
+    pub fn fields(&self) -> Vec<&str> {
+        match self {
+            Expr::Number(_) => Vec::new(),
+            Expr::Field(name) => vec![name.as_str()],
+            Expr::Neg(inner) => inner.fields(),
+            Expr::Binary(lhs, _, rhs) => {
+                let mut fields = lhs.fields();
+                fields.extend(rhs.fields());
+                fields
+            }
+        }
+    }
+
+    /// Evaluates the expression, looking fields up with `field`. Missing
+    /// fields and division by zero give `None`.
+    pub fn eval(&self, field: &impl Fn(&str) -> Option<f64>) -> Option<f64> {
+        match self {
+            Expr::Number(n) => Some(*n),
+            Expr::Field(name) => field(name),
+            Expr::Neg(inner) => inner.eval(field).map(|n| -n),
+            Expr::Binary(lhs, op, rhs) => {
+                let (lhs, rhs) = (lhs.eval(field)?, rhs.eval(field)?);
+                match op {
+                    '+' => Some(lhs + rhs),
+                    '-' => Some(lhs - rhs),
+                    '*' => Some(lhs * rhs),
+                    _ if rhs == 0.0 => None,
+                    _ => Some(lhs / rhs),
+                }
+            }
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        let mut expr = self.product()?;
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(op @ ('+' | '-')) => {
+                    self.pos += 1;
+                    expr = Expr::Binary(Box::new(expr), op, Box::new(self.product()?));
+                }
+                _ => return Ok(expr),
+            }
+        }
+    }
+
+    fn product(&mut self) -> Result<Expr, String> {
+        let mut expr = self.factor()?;
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(op @ ('*' | '/')) => {
+                    self.pos += 1;
+                    expr = Expr::Binary(Box::new(expr), op, Box::new(self.factor()?));
+                }
+                _ => return Ok(expr),
+            }
+        }
+    }
+
+    fn factor(&mut self) -> Result<Expr, String> {
+        self.skip_whitespace();
+        let start = self.pos;
+        match self.peek() {
+            Some('-') => {
+                self.pos += 1;
+                Ok(Expr::Neg(Box::new(self.factor()?)))
+            }
+            Some('(') => {
+                self.pos += 1;
+                let expr = self.sum()?;
+                self.skip_whitespace();
+                if self.peek() != Some(')') {
+                    return Err(format!("missing `)` for `(` at {}", start));
+                }
+                self.pos += 1;
+                Ok(expr)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let text = self.take_while(|c| c.is_ascii_digit() || c == '.');
+                text.parse()
+                    .map(Expr::Number)
+                    .map_err(|_| format!("invalid number `{}` at {}", text, start))
+            }
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                Ok(Expr::Field(self.take_while(|c| {
+                    c.is_alphanumeric() || c == '_' || c == '.'
+                })))
+            }
+            Some(c) => Err(format!("unexpected `{}` at {}", c, start)),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+
+    fn take_while(&mut self, accept: impl Fn(char) -> bool) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(&accept) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+}
@@ -0,0 +1,1465 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ops::RangeInclusive,
+};
+
+use fake::Fake;
+use log::warn;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde_json::{json, Value};
+
+use crate::{
+    config::{Locale, MockConfig, MockPattern, Sequences},
+    expr::Expr,
+    regex_gen::generate_matching,
+    swagger::{discriminator_choices, lower_bound, tuple_items, upper_bound, SwaggerState},
+};
+
+/// Fakes `$faker` in `$locale`. Faker locales are distinct types, so each
+/// one needs its own arm.
+macro_rules! localized {
+    ($locale:expr, $faker:ident $(, $arg:expr)*; $rng:expr) => {{
+        use fake::locales::{AR_SA, EN, FR_FR, JA_JP, PT_BR, ZH_CN, ZH_TW};
+        match $locale {
+            Locale::En => $faker(EN $(, $arg)*).fake_with_rng($rng),
+            Locale::Fr => $faker(FR_FR $(, $arg)*).fake_with_rng($rng),
+            Locale::PtBr => $faker(PT_BR $(, $arg)*).fake_with_rng($rng),
+            Locale::Ja => $faker(JA_JP $(, $arg)*).fake_with_rng($rng),
+            Locale::ZhCn => $faker(ZH_CN $(, $arg)*).fake_with_rng($rng),
+            Locale::ZhTw => $faker(ZH_TW $(, $arg)*).fake_with_rng($rng),
+            Locale::Ar => $faker(AR_SA $(, $arg)*).fake_with_rng($rng),
+        }
+    }};
+}
+
+/// A string `format` and how `generate_mock_string` fakes it. Values of
+/// formats that `fit_length` is true for (free text, names) are padded or
+/// cut to `minLength`/`maxLength`; the others would break if altered.
+struct StringFormat {
+    name: &'static str,
+    fake: fn(&MockConfig, &mut StdRng) -> String,
+    fit_length: bool,
+}
+
+const STRING_FORMATS: &[StringFormat] = {
+    use fake::faker::company::raw::*;
+    use fake::faker::internet::raw::*;
+    use fake::faker::name::raw::*;
+    use fake::faker::phone_number::raw::*;
+    use fake::locales::EN;
+
+    &[
+        StringFormat {
+            name: "date-time",
+            fake: |config, _| config.generated_now().to_rfc3339(),
+            fit_length: false,
+        },
+        StringFormat {
+            name: "date",
+            fake: |config, rng| {
+                let days_ago = rng.gen_range(0..3650);
+                (config.generated_now() - chrono::Duration::days(days_ago))
+                    .format("%Y-%m-%d")
+                    .to_string()
+            },
+            fit_length: false,
+        },
+        StringFormat {
+            name: "email",
+            fake: |config, rng| localized!(config.locale.unwrap_or_default(), FreeEmail; rng),
+            fit_length: false,
+        },
+        StringFormat {
+            name: "uuid",
+            fake: |_, rng| {
+                uuid::Builder::from_random_bytes(rng.gen())
+                    .into_uuid()
+                    .to_string()
+            },
+            fit_length: false,
+        },
+        StringFormat {
+            name: "ipv4",
+            fake: |_, rng| IPv4(EN).fake_with_rng(rng),
+            fit_length: false,
+        },
+        StringFormat {
+            name: "ipv6",
+            fake: |_, rng| IPv6(EN).fake_with_rng(rng),
+            fit_length: false,
+        },
+        StringFormat {
+            name: "uri",
+            fake: |_, rng| fake_url(rng),
+            fit_length: false,
+        },
+        StringFormat {
+            name: "url",
+            fake: |_, rng| fake_url(rng),
+            fit_length: false,
+        },
+        StringFormat {
+            name: "hostname",
+            fake: |_, rng| fake_hostname(rng),
+            fit_length: false,
+        },
+        StringFormat {
+            name: "phone",
+            fake: |config, rng| localized!(config.locale.unwrap_or_default(), PhoneNumber; rng),
+            fit_length: false,
+        },
+        StringFormat {
+            name: "password",
+            fake: |_, rng| Password(EN, 12..20).fake_with_rng(rng),
+            fit_length: true,
+        },
+        StringFormat {
+            name: "name",
+            fake: |config, rng| localized!(config.locale.unwrap_or_default(), Name; rng),
+            fit_length: true,
+        },
+        StringFormat {
+            name: "username",
+            fake: |_, rng| Username(EN).fake_with_rng(rng),
+            fit_length: true,
+        },
+        StringFormat {
+            name: "company",
+            fake: |config, rng| localized!(config.locale.unwrap_or_default(), CompanyName; rng),
+            fit_length: true,
+        },
+    ]
+};
+
+/// Number formats `generate_mock_number` knows about.
+const NUMBER_FORMATS: &[&str] = &["int32", "int64", "float", "double"];
+
+/// Formats spit generates data for; any other `format` falls back to
+/// generic data.
+pub fn known_formats() -> Vec<&'static str> {
+    STRING_FORMATS
+        .iter()
+        .map(|format| format.name)
+        .chain(NUMBER_FORMATS.iter().copied())
+        .collect()
+}
+
+/// Faker generators `ref` field patterns name by `kind`.
+struct FakerKind {
+    name: &'static str,
+    fake: fn(Locale, &mut StdRng) -> String,
+}
+
+const FAKER_KINDS: &[FakerKind] = {
+    use fake::faker::address::raw::*;
+    use fake::faker::company::raw::*;
+    use fake::faker::internet::raw::*;
+    use fake::faker::job::raw::Title as JobTitle;
+    use fake::faker::name::raw::*;
+    use fake::faker::phone_number::raw::*;
+    use fake::locales::EN;
+
+    &[
+        FakerKind {
+            name: "address",
+            fake: |locale, rng| {
+                let number = BuildingNumber(EN).fake_with_rng::<String, _>(rng);
+                let street: String = localized!(locale, StreetName; rng);
+                let city: String = localized!(locale, CityName; rng);
+                let zip: String = localized!(locale, ZipCode; rng);
+                format!("{} {}, {} {}", number, street, zip, city)
+            },
+        },
+        FakerKind {
+            name: "city",
+            fake: |locale, rng| localized!(locale, CityName; rng),
+        },
+        FakerKind {
+            name: "color",
+            fake: |_, rng| format!("#{:06x}", rng.gen_range(0..0x100_0000)),
+        },
+        FakerKind {
+            name: "company",
+            fake: |locale, rng| localized!(locale, CompanyName; rng),
+        },
+        FakerKind {
+            name: "country",
+            fake: |_, rng| CountryName(EN).fake_with_rng(rng),
+        },
+        FakerKind {
+            name: "email",
+            fake: |locale, rng| localized!(locale, FreeEmail; rng),
+        },
+        FakerKind {
+            name: "first_name",
+            fake: |locale, rng| localized!(locale, FirstName; rng),
+        },
+        FakerKind {
+            name: "job_title",
+            fake: |_, rng| JobTitle(EN).fake_with_rng(rng),
+        },
+        FakerKind {
+            name: "last_name",
+            fake: |locale, rng| localized!(locale, LastName; rng),
+        },
+        FakerKind {
+            name: "name",
+            fake: |locale, rng| localized!(locale, Name; rng),
+        },
+        FakerKind {
+            name: "phone",
+            fake: |locale, rng| localized!(locale, PhoneNumber; rng),
+        },
+        FakerKind {
+            name: "url",
+            fake: |_, rng| fake_url(rng),
+        },
+        FakerKind {
+            name: "username",
+            fake: |_, rng| Username(EN).fake_with_rng(rng),
+        },
+    ]
+};
+
+/// Kinds `ref` field patterns can name.
+pub fn faker_kinds() -> Vec<&'static str> {
+    FAKER_KINDS.iter().map(|kind| kind.name).collect()
+}
+
+/// A value from the faker generator named `kind`, `None` for unknown kinds.
+pub fn fake_kind(kind: &str, locale: Locale, rng: &mut StdRng) -> Option<String> {
+    FAKER_KINDS
+        .iter()
+        .find(|faker| faker.name == kind)
+        .map(|faker| (faker.fake)(locale, rng))
+}
+
+/// Regenerations tried per array item before accepting a duplicate.
+const MAX_UNIQUE_ATTEMPTS: usize = 20;
+
+pub struct MockGenerator<'a> {
+    swagger_state: &'a SwaggerState,
+    config: &'a MockConfig,
+    ref_stack: Vec<String>,
+    rng: StdRng,
+    /// Request parameter values copied into same-named properties of the
+    /// outermost generated objects.
+    echoed: HashMap<String, String>,
+    object_depth: usize,
+    sequences: Sequences,
+    /// Where the value being generated sits: property names, and `[]` for
+    /// array items.
+    path: Vec<String>,
+}
+
+impl<'a> MockGenerator<'a> {
+    /// Draws from the configured `seed`, or from entropy without one.
+    pub fn new(swagger_state: &'a SwaggerState, config: &'a MockConfig) -> Self {
+        let rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        Self {
+            swagger_state,
+            config,
+            ref_stack: Vec::new(),
+            rng,
+            echoed: HashMap::new(),
+            object_depth: 0,
+            sequences: Sequences::default(),
+            path: Vec::new(),
+        }
+    }
+
+    /// With a `seed`, derives the generator's randomness from the seed and
+    /// `key` (e.g. method and path), so a request generates the same data
+    /// whatever was served before it.
+    pub fn seeded_for(mut self, key: &str) -> Self {
+        if let Some(seed) = self.config.seed {
+            self.rng = StdRng::seed_from_u64(seed ^ stable_hash(key));
+        }
+        self
+    }
+
+    /// Fills properties named after `params` with their values, in the type
+    /// generated for the property, in the response object or the items of a
+    /// response array. Nested objects are left alone.
+    pub fn echoing(mut self, params: HashMap<String, String>) -> Self {
+        self.echoed = params;
+        self
+    }
+
+    /// Counts `sequence` field patterns in `sequences`, shared with other
+    /// generators, rather than from their start for every generator.
+    pub fn counting(mut self, sequences: Sequences) -> Self {
+        self.sequences = sequences;
+        self
+    }
+
+    pub fn generate_mock_value(&mut self, schema: &Value, field_name: Option<&str>) -> Value {
+        let value = self.generate_field_value(schema, field_name);
+        self.transformed(field_name, value)
+    }
+
+    /// `value` with the `transforms` casing of the value being generated
+    /// applied: the one keyed by its path, else by its bare `field_name`.
+    fn transformed(&self, field_name: Option<&str>, value: Value) -> Value {
+        let transform = self.config.fields.as_ref().and_then(|fields| {
+            fields
+                .transforms
+                .get(self.path_key().as_str())
+                .or_else(|| fields.transforms.get(field_name?))
+        });
+
+        match (transform, value) {
+            (Some(transform), Value::String(s)) => Value::String(transform.apply(&s)),
+            (_, value) => value,
+        }
+    }
+
+    fn generate_field_value(&mut self, schema: &Value, field_name: Option<&str>) -> Value {
+        match self.field_pattern(field_name) {
+            Some((key, MockPattern::Sequence { start, step })) => {
+                return self.next_in_sequence(key, *start, *step)
+            }
+            Some((_, MockPattern::Regex { pattern })) => {
+                return self
+                    .swagger_state
+                    .regex(pattern)
+                    .ok()
+                    .and_then(|regex| generate_matching(&regex, 0..=usize::MAX, &mut self.rng))
+                    .map_or(Value::Null, Value::String)
+            }
+            Some((_, pattern)) => return pattern.generate_value(self.config, &mut self.rng),
+            None => {}
+        }
+
+        match schema {
+            Value::Object(map) => {
+                if let Some(example) = self.schema_example(map) {
+                    return example;
+                }
+                if let Some(default) = map.get("default") {
+                    return default.clone();
+                }
+
+                if let Some(ref_path) = map.get("$ref").and_then(Value::as_str) {
+                    if let Some(resolved_schema) = self.swagger_state.resolve_ref(ref_path) {
+                        if self.recursion_depth(ref_path) >= self.max_recursion_depth() {
+                            return json!(null);
+                        }
+
+                        self.ref_stack.push(ref_path.to_string());
+                        let value = self.generate_field_value(&resolved_schema, field_name);
+                        self.ref_stack.pop();
+                        return value;
+                    }
+                }
+
+                if let Some(parts) = map
+                    .get("allOf")
+                    .and_then(Value::as_array)
+                    .filter(|parts| !parts.is_empty())
+                {
+                    return self.generate_mock_all_of(map, parts, field_name);
+                }
+
+                if let Some(variants) = map
+                    .get("oneOf")
+                    .or_else(|| map.get("anyOf"))
+                    .and_then(Value::as_array)
+                    .filter(|variants| !variants.is_empty())
+                {
+                    return self.generate_mock_union(map, variants, field_name);
+                }
+
+                let type_val = match map.get("type") {
+                    Some(Value::String(type_name)) => type_name.as_str(),
+                    Some(Value::Array(types)) => pick_union_type(types),
+                    _ => "object",
+                };
+                match type_val {
+                    "string" => self.generate_mock_string(map, field_name),
+                    "integer" | "number" => self.generate_mock_number(map, type_val),
+                    "boolean" => json!(self.rng.gen::<bool>()),
+                    "array" => self.generate_mock_array(map, field_name),
+                    "object" => self.generate_mock_object(map),
+                    _ => json!(null),
+                }
+            }
+            _ => json!(null),
+        }
+    }
+
+    /// The schema's `example`, or the first of its `examples`, unless
+    /// `prefer_examples` is turned off.
+    fn schema_example(&self, schema: &serde_json::Map<String, Value>) -> Option<Value> {
+        if !self.config.prefer_examples.unwrap_or(true) {
+            return None;
+        }
+        schema
+            .get("example")
+            .or_else(|| {
+                schema
+                    .get("examples")
+                    .and_then(Value::as_array)
+                    .and_then(|examples| examples.first())
+            })
+            .cloned()
+    }
+
+    /// The `patterns` entry, and its key, for the value being generated: the
+    /// one keyed by its path (e.g. `user.address.zip` or `items[].sku`),
+    /// else the one keyed by its bare `field_name`.
+    fn field_pattern(&self, field_name: Option<&str>) -> Option<(&'a str, &'a MockPattern)> {
+        let patterns = &self.config.fields.as_ref()?.patterns;
+        let path = self.path_key();
+        patterns
+            .get_key_value(path.as_str())
+            .or_else(|| patterns.get_key_value(field_name?))
+            .map(|(key, pattern)| (key.as_str(), pattern))
+    }
+
+    /// `path` as a `patterns` key. Items of a top-level array are addressed
+    /// like a top-level object, so list and item responses share keys.
+    fn path_key(&self) -> String {
+        let mut key = String::new();
+        for segment in &self.path {
+            match segment.as_str() {
+                "[]" if key.is_empty() => {}
+                "[]" => key.push_str("[]"),
+                name => {
+                    if !key.is_empty() {
+                        key.push('.');
+                    }
+                    key.push_str(name);
+                }
+            }
+        }
+        key
+    }
+
+    /// Generates the `segment` (property name or `[]`) child of the value
+    /// being generated.
+    fn generate_child(&mut self, segment: &str, schema: &Value, field_name: Option<&str>) -> Value {
+        self.path.push(segment.to_string());
+        let value = self.generate_mock_value(schema, field_name);
+        self.path.pop();
+        value
+    }
+
+    /// Generates one `oneOf`/`anyOf` variant. With a `discriminator`, the
+    /// variant is chosen by a discriminator value drawn from the configured
+    /// weights (uniformly otherwise) and that value is written to the
+    /// discriminator property.
+    fn generate_mock_union(
+        &mut self,
+        schema: &serde_json::Map<String, Value>,
+        variants: &[Value],
+        field_name: Option<&str>,
+    ) -> Value {
+        let discriminator = schema.get("discriminator");
+        let property = discriminator
+            .and_then(|d| d.get("propertyName"))
+            .and_then(Value::as_str);
+
+        let property = match property {
+            Some(property) => property,
+            None => {
+                let variant =
+                    &variants[(0..variants.len()).fake_with_rng::<usize, _>(&mut self.rng)];
+                return self.generate_field_value(variant, field_name);
+            }
+        };
+
+        let choices = discriminator_choices(schema, variants);
+        if choices.is_empty() {
+            let variant = &variants[(0..variants.len()).fake_with_rng::<usize, _>(&mut self.rng)];
+            return self.generate_field_value(variant, field_name);
+        }
+
+        let weights = self
+            .config
+            .fields
+            .as_ref()
+            .and_then(|fields| fields.discriminators.get(property));
+        let index = match weights {
+            Some(weights) => weighted_index(
+                &choices
+                    .iter()
+                    .map(|(value, _)| weights.get(value).copied().unwrap_or(0.0))
+                    .collect::<Vec<_>>(),
+                &mut self.rng,
+            ),
+            None => (0..choices.len()).fake_with_rng::<usize, _>(&mut self.rng),
+        };
+        let (value, variant) = &choices[index];
+
+        let mut generated = self.generate_field_value(variant, field_name);
+        if let Value::Object(object) = &mut generated {
+            object.insert(property.to_string(), json!(value));
+        }
+        generated
+    }
+
+    /// Generates every `allOf` part, plus any properties declared next to
+    /// `allOf`, and deep-merges the resulting objects. A part that is not an
+    /// object replaces what came before it.
+    fn generate_mock_all_of(
+        &mut self,
+        schema: &serde_json::Map<String, Value>,
+        parts: &[Value],
+        field_name: Option<&str>,
+    ) -> Value {
+        let mut merged = json!({});
+        for part in parts {
+            let generated = self.generate_field_value(part, field_name);
+            merge_values(&mut merged, generated);
+        }
+
+        if schema.contains_key("properties") {
+            let mut own = schema.clone();
+            own.shift_remove("allOf");
+            let generated = self.generate_field_value(&Value::Object(own), field_name);
+            merge_values(&mut merged, generated);
+        }
+        merged
+    }
+
+    /// How many times `ref_path` is already being generated further up the tree.
+    fn recursion_depth(&self, ref_path: &str) -> usize {
+        self.ref_stack.iter().filter(|r| *r == ref_path).count()
+    }
+
+    /// Self-referential schemas are cut off at the first repetition unless a
+    /// `recursion` config allows a deeper tree.
+    fn max_recursion_depth(&self) -> usize {
+        self.config
+            .recursion
+            .as_ref()
+            .map_or(1, |recursion| recursion.max_depth)
+    }
+
+    fn generate_mock_string(
+        &mut self,
+        schema: &serde_json::Map<String, Value>,
+        field_name: Option<&str>,
+    ) -> Value {
+        use fake::faker::lorem::raw::*;
+        use fake::locales::EN;
+
+        if let Some(value) = self.pattern_string(schema) {
+            return json!(value);
+        }
+
+        let bounds = string_bounds(schema);
+        let format = schema
+            .get("format")
+            .and_then(Value::as_str)
+            .map(|format| STRING_FORMATS.iter().find(|known| known.name == format));
+        let text = if let Some(format) = format {
+            match format {
+                Some(format) if !format.fit_length => {
+                    return json!((format.fake)(self.config, &mut self.rng))
+                }
+                Some(format) => (format.fake)(self.config, &mut self.rng),
+                None => Sentence(EN, 3..10).fake_with_rng::<String, _>(&mut self.rng),
+            }
+        } else if let Some(enum_values) = schema
+            .get("enum")
+            .and_then(Value::as_array)
+            .filter(|values| !values.is_empty())
+        {
+            let fitting: Vec<&Value> = enum_values
+                .iter()
+                .filter(|value| {
+                    value
+                        .as_str()
+                        .is_some_and(|value| bounds.contains(&value.chars().count()))
+                })
+                .collect();
+            let choices: Vec<&Value> = if fitting.is_empty() {
+                enum_values.iter().collect()
+            } else {
+                fitting
+            };
+            let index = (0..choices.len()).fake_with_rng::<usize, _>(&mut self.rng);
+            return choices[index].clone();
+        } else if let Some(value) = self.smart_string(schema, field_name) {
+            return json!(value);
+        } else {
+            Sentence(EN, 3..10).fake_with_rng::<String, _>(&mut self.rng)
+        };
+
+        json!(self.fit_length(text, bounds))
+    }
+
+    /// Pads `text` with words or cuts it to fit `bounds`. When the text is
+    /// out of bounds and both are set, the length is drawn from them.
+    fn fit_length(&mut self, text: String, bounds: RangeInclusive<usize>) -> String {
+        use fake::faker::lorem::raw::Word;
+        use fake::locales::EN;
+
+        let length = text.chars().count();
+        if bounds.contains(&length) {
+            return text;
+        }
+        let target = if *bounds.end() == usize::MAX {
+            *bounds.start()
+        } else if *bounds.start() == 0 {
+            *bounds.end()
+        } else {
+            self.rng.gen_range(bounds.clone())
+        };
+
+        let mut text = text;
+        while text.chars().count() < target {
+            text.push(' ');
+            text.push_str(&Word(EN).fake_with_rng::<String, _>(&mut self.rng));
+        }
+        let cut: String = text.chars().take(target).collect();
+        let trimmed = cut.trim_end();
+        if bounds.contains(&trimmed.chars().count()) {
+            trimmed.to_string()
+        } else {
+            cut
+        }
+    }
+
+    /// A string matching the schema's `pattern`, within `minLength` and
+    /// `maxLength` when possible.
+    fn pattern_string(&mut self, schema: &serde_json::Map<String, Value>) -> Option<String> {
+        let pattern = schema.get("pattern").and_then(Value::as_str)?;
+        let swagger_state = self.swagger_state;
+        let regex = swagger_state.regex(pattern).ok()?;
+        generate_matching(&regex, string_bounds(schema), &mut self.rng)
+    }
+
+    /// With `smart_generation`, picks a faker from the field name, falling
+    /// back to its `title` and `description`.
+    fn smart_string(
+        &mut self,
+        schema: &serde_json::Map<String, Value>,
+        field_name: Option<&str>,
+    ) -> Option<String> {
+        if !self.config.smart_generation.unwrap_or(false) {
+            return None;
+        }
+
+        let locale = self.config.locale.unwrap_or_default();
+        let text_hints = ["title", "description"]
+            .into_iter()
+            .filter_map(|key| schema.get(key).and_then(Value::as_str));
+        field_name
+            .into_iter()
+            .chain(text_hints)
+            .find_map(|hint| fake_for_hint(&hint_key(hint), locale, &mut self.rng))
+    }
+
+    fn generate_mock_number(
+        &mut self,
+        schema: &serde_json::Map<String, Value>,
+        type_val: &str,
+    ) -> Value {
+        let bounds = NumberBounds::new(schema, type_val);
+        let multiple_of = schema
+            .get("multipleOf")
+            .and_then(Value::as_f64)
+            .filter(|m| *m > 0.0);
+
+        if type_val == "integer" {
+            return json!(self.generate_integer(&bounds, multiple_of));
+        }
+
+        // Multiples of `multipleOf: 0.001` need more places than the default.
+        let decimals = self.decimals().max(multiple_of.map_or(0, decimal_places));
+        let value = self.generate_float(&bounds, multiple_of, decimals);
+        self.format_float(value, decimals)
+    }
+
+    /// A random integer within `bounds`, a multiple of `multiple_of` when
+    /// set. The lowest allowed value when the bounds leave none.
+    fn generate_integer(&mut self, bounds: &NumberBounds, multiple_of: Option<f64>) -> i64 {
+        let lowest = if bounds.exclusive_min {
+            bounds.min.floor() + 1.0
+        } else {
+            bounds.min.ceil()
+        };
+        let highest = if bounds.exclusive_max {
+            bounds.max.ceil() - 1.0
+        } else {
+            bounds.max.floor()
+        };
+
+        // The integer multiples of a fractional `multipleOf` (say 2.5) are
+        // those of its smallest integer multiple (5).
+        let step = multiple_of.and_then(|m| {
+            (1..=1000)
+                .map(|n| n as f64 * m)
+                .find(|multiple| (multiple - multiple.round()).abs() < 1e-9)
+                .map(f64::round)
+        });
+        if let Some(step) = step {
+            let (first, last) = ((lowest / step).ceil(), (highest / step).floor());
+            if first <= last {
+                let k = self.rng.gen_range(first as i64..=last as i64);
+                return (k as f64 * step) as i64;
+            }
+        }
+
+        if lowest > highest {
+            return lowest as i64;
+        }
+        self.rng.gen_range(lowest as i64..=highest as i64)
+    }
+
+    /// A random number within `bounds` with at most `decimals` places, a
+    /// multiple of `multiple_of` when set.
+    fn generate_float(
+        &mut self,
+        bounds: &NumberBounds,
+        multiple_of: Option<f64>,
+        decimals: u32,
+    ) -> f64 {
+        if let Some(m) = multiple_of {
+            let mut first = (bounds.min / m).ceil();
+            if bounds.exclusive_min && first * m <= bounds.min {
+                first += 1.0;
+            }
+            let mut last = (bounds.max / m).floor();
+            if bounds.exclusive_max && last * m >= bounds.max {
+                last -= 1.0;
+            }
+            if first <= last && first >= i64::MIN as f64 && last <= i64::MAX as f64 {
+                let k = self.rng.gen_range(first as i64..=last as i64);
+                return round_to(k as f64 * m, decimals);
+            }
+        }
+
+        let value = bounds.min + (bounds.max - bounds.min) * self.rng.gen::<f64>();
+        let rounded = round_to(value, decimals);
+        if bounds.contains(rounded) {
+            return rounded;
+        }
+
+        // Rounding landed on an exclusive bound or past one: try the first
+        // value with `decimals` places inside, or give up on rounding.
+        let step = 10_f64.powi(-(decimals as i32));
+        let mut first = round_to((bounds.min / step).ceil() * step, decimals);
+        if !bounds.contains(first) {
+            first = round_to(first + step, decimals);
+        }
+        if bounds.contains(first) {
+            first
+        } else {
+            (bounds.min + bounds.max) / 2.0
+        }
+    }
+
+    fn decimals(&self) -> u32 {
+        self.config
+            .number_format
+            .clone()
+            .unwrap_or_default()
+            .decimals
+    }
+
+    /// Rounds `value` to `decimals` places, as a string when `number_format`
+    /// asks for one.
+    fn format_float(&self, value: f64, decimals: u32) -> Value {
+        let format = self.config.number_format.clone().unwrap_or_default();
+        let value = round_to(value, decimals);
+
+        if format.as_string {
+            json!(format!("{:.*}", decimals as usize, value))
+        } else {
+            json!(value)
+        }
+    }
+
+    fn generate_mock_array(
+        &mut self,
+        schema: &serde_json::Map<String, Value>,
+        field_name: Option<&str>,
+    ) -> Value {
+        let (prefix_items, rest_items) = tuple_items(schema);
+        if !prefix_items.is_empty() {
+            return self.generate_mock_tuple(schema, prefix_items, rest_items, field_name);
+        }
+
+        let items = match rest_items {
+            Some(items) => items,
+            None => return json!([]),
+        };
+
+        let recursive_ref = items
+            .get("$ref")
+            .and_then(Value::as_str)
+            .filter(|ref_path| self.recursion_depth(ref_path) > 0);
+
+        let count = match (recursive_ref, &self.config.recursion) {
+            (Some(ref_path), _) if self.recursion_depth(ref_path) >= self.max_recursion_depth() => {
+                return json!([]);
+            }
+            (Some(_), Some(recursion)) => recursion.children_per_level,
+            _ => {
+                let min_items =
+                    schema.get("minItems").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
+                let max_items = schema
+                    .get("maxItems")
+                    .and_then(|v| v.as_u64())
+                    .map_or(min_items.max(5), |max| max as usize)
+                    .max(min_items);
+                (min_items..=max_items).fake_with_rng::<usize, _>(&mut self.rng)
+            }
+        };
+
+        let unique_items = schema
+            .get("uniqueItems")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        let unique_fields: &[String] = self
+            .config
+            .fields
+            .as_ref()
+            .map_or(&[], |fields| fields.unique.as_slice());
+
+        let mut values: Vec<Value> = Vec::with_capacity(count);
+        let mut seen_fields: HashMap<&str, HashSet<String>> = HashMap::new();
+
+        for _ in 0..count {
+            let mut attempts = 0;
+            let item = loop {
+                let candidate = self.generate_child("[]", items, field_name);
+                attempts += 1;
+
+                let duplicate_item = unique_items && values.contains(&candidate);
+                let duplicate_field = unique_fields.iter().any(|field| {
+                    candidate.get(field).is_some_and(|value| {
+                        seen_fields
+                            .get(field.as_str())
+                            .is_some_and(|seen| seen.contains(&value.to_string()))
+                    })
+                });
+
+                if !(duplicate_item || duplicate_field) || attempts >= MAX_UNIQUE_ATTEMPTS {
+                    if duplicate_item || duplicate_field {
+                        warn!(
+                            "Could not generate a unique array item after {} attempts",
+                            attempts
+                        );
+                    }
+                    break candidate;
+                }
+            };
+
+            for field in unique_fields {
+                if let Some(value) = item.get(field) {
+                    seen_fields
+                        .entry(field.as_str())
+                        .or_default()
+                        .insert(value.to_string());
+                }
+            }
+            values.push(item);
+        }
+
+        json!(values)
+    }
+
+    fn generate_mock_tuple(
+        &mut self,
+        schema: &serde_json::Map<String, Value>,
+        prefix_items: &[Value],
+        rest_items: Option<&Value>,
+        field_name: Option<&str>,
+    ) -> Value {
+        let mut values: Vec<Value> = prefix_items
+            .iter()
+            .map(|item_schema| self.generate_child("[]", item_schema, field_name))
+            .collect();
+
+        if let Some(rest_schema) = rest_items.filter(|rest| rest.is_object()) {
+            let min_items = schema
+                .get("minItems")
+                .and_then(Value::as_u64)
+                .map_or(0, |min| min as usize)
+                .max(prefix_items.len());
+            let max_items = schema
+                .get("maxItems")
+                .and_then(Value::as_u64)
+                .map_or(min_items, |max| max as usize)
+                .max(min_items);
+            let count = (min_items..=max_items).fake_with_rng::<usize, _>(&mut self.rng);
+
+            while values.len() < count {
+                values.push(self.generate_child("[]", rest_schema, field_name));
+            }
+        }
+
+        json!(values)
+    }
+
+    fn generate_mock_object(&mut self, schema: &serde_json::Map<String, Value>) -> Value {
+        let echo = self.object_depth == 0 && !self.echoed.is_empty();
+        self.object_depth += 1;
+        let mock = self.generate_object_fields(schema, echo);
+        self.object_depth -= 1;
+        mock
+    }
+
+    fn generate_object_fields(
+        &mut self,
+        schema: &serde_json::Map<String, Value>,
+        echo: bool,
+    ) -> Value {
+        let mut mock = serde_json::Map::new();
+
+        let swagger_state = self.swagger_state;
+        if let Some((pattern, prop_schema, regex)) = schema
+            .get("patternProperties")
+            .and_then(Value::as_object)
+            .and_then(|patterns| patterns.iter().next())
+            .and_then(|(pattern, prop_schema)| {
+                Some((pattern, prop_schema, swagger_state.regex(pattern).ok()?))
+            })
+        {
+            for _ in 0..(1..=3).fake_with_rng::<usize, _>(&mut self.rng) {
+                if let Some(key) = pattern_key(pattern, &regex, &mut self.rng) {
+                    let value = self.generate_child(&key, prop_schema, Some(&key));
+                    mock.insert(key, value);
+                }
+            }
+        }
+
+        let props = match schema.get("properties").and_then(Value::as_object) {
+            Some(props) => props,
+            None => return Value::Object(mock),
+        };
+
+        let required_fields: HashSet<_> = schema
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|req| req.iter().filter_map(|v| v.as_str()).collect())
+            .unwrap_or_default();
+
+        for (key, prop_schema) in props {
+            // `writeOnly` fields such as passwords are only ever sent to the
+            // server, never returned by it.
+            if self.is_write_only(prop_schema) {
+                continue;
+            }
+            let echoed = self.echoed.get(key).filter(|_| echo).cloned();
+            if required_fields.contains(key.as_str())
+                || required_fields.is_empty()
+                || echoed.is_some()
+            {
+                let generated = self.generate_child(key, prop_schema, Some(key));
+                let value = echoed
+                    .and_then(|raw| echoed_value(&raw, &generated))
+                    .unwrap_or(generated);
+                mock.insert(key.clone(), value);
+            }
+        }
+
+        self.apply_computed_fields(&mut mock);
+        self.apply_templates(&mut mock);
+        Value::Object(mock)
+    }
+
+    fn next_in_sequence(&self, field: &str, start: i64, step: i64) -> Value {
+        let Ok(mut sequences) = self.sequences.lock() else {
+            return Value::from(start);
+        };
+        let next = sequences.entry(field.to_string()).or_insert(start);
+        let value = *next;
+        *next = next.saturating_add(step);
+        Value::from(value)
+    }
+
+    fn is_write_only(&self, prop_schema: &Value) -> bool {
+        self.swagger_state
+            .resolve_object(prop_schema)
+            .into_iter()
+            .chain([prop_schema])
+            .any(|schema| schema.get("writeOnly").and_then(Value::as_bool) == Some(true))
+    }
+
+    /// Overwrites fields named in `fields.computed` with their expression
+    /// evaluated over the other generated fields of the object, keeping the
+    /// generated value's kind (integer, number or numeric string). Fields
+    /// referencing other computed fields are evaluated after them.
+    fn apply_computed_fields(&self, object: &mut serde_json::Map<String, Value>) {
+        let computed = match &self.config.fields {
+            Some(fields) if !fields.computed.is_empty() => &fields.computed,
+            _ => return,
+        };
+
+        let mut pending: Vec<(&String, Expr)> = Vec::new();
+        for (field, source) in computed {
+            if !object.contains_key(field) {
+                continue;
+            }
+            match Expr::parse(source) {
+                Ok(expr) => pending.push((field, expr)),
+                Err(e) => warn!("Skipping computed field {}: {}", field, e),
+            }
+        }
+
+        while !pending.is_empty() {
+            let waits_on_pending = |expr: &Expr, field: &str| {
+                expr.fields().iter().any(|path| {
+                    let root = path.split('.').next().unwrap_or_default();
+                    root != field && pending.iter().any(|(other, _)| other.as_str() == root)
+                })
+            };
+            let next = pending
+                .iter()
+                .position(|(field, expr)| !waits_on_pending(expr, field))
+                .unwrap_or(0);
+            let (field, expr) = pending.remove(next);
+
+            let integer = object
+                .get(field)
+                .is_some_and(|generated| generated.is_i64() || generated.is_u64());
+            let Some(result) = expr.eval(&|path: &str| {
+                let mut segments = path.split('.');
+                let first = object.get(segments.next()?)?;
+                segments
+                    .try_fold(first, |value, key| value.get(key))
+                    .and_then(numeric_value)
+            }) else {
+                continue;
+            };
+
+            let value = if integer {
+                json!(result.round() as i64)
+            } else {
+                self.format_float(result, self.decimals())
+            };
+            object.insert(field.clone(), value);
+        }
+    }
+
+    /// Renders the `template` field patterns of a generated object against
+    /// its other fields, in the object's key order except that templates
+    /// referencing other template fields are rendered after them.
+    fn apply_templates(&mut self, object: &mut serde_json::Map<String, Value>) {
+        let mut pending: Vec<(String, &str, &str)> = Vec::new();
+        for field in object.keys() {
+            self.path.push(field.clone());
+            if let Some((key, MockPattern::Template { template })) = self.field_pattern(Some(field))
+            {
+                pending.push((field.clone(), key, template));
+            }
+            self.path.pop();
+        }
+
+        while !pending.is_empty() {
+            let waits_on_pending = |template: &str, field: &str| {
+                template_tokens(template).any(|token| {
+                    let root = token.split('.').next().unwrap_or_default();
+                    root != field && pending.iter().any(|(other, _, _)| other == root)
+                })
+            };
+            let next = pending
+                .iter()
+                .position(|(field, _, template)| !waits_on_pending(template, field))
+                .unwrap_or(0);
+            let (field, key, template) = pending.remove(next);
+
+            let mut rendered = String::new();
+            let mut rest = template;
+            while let Some(open) = rest.find('{') {
+                rendered.push_str(&rest[..open]);
+                let Some(close) = rest[open..].find('}').map(|close| open + close) else {
+                    break;
+                };
+                match self.template_token(key, &rest[open + 1..close], object) {
+                    Some(value) => rendered.push_str(&value),
+                    None => rendered.push_str(&rest[open..=close]),
+                }
+                rest = &rest[close + 1..];
+            }
+            rendered.push_str(rest);
+
+            let value = self.transformed(Some(&field), Value::String(rendered));
+            object.insert(field, value);
+        }
+    }
+
+    /// The text of a `{token}` in the template `patterns` has under `key`:
+    /// another field of `object` (dotted paths reach into nested objects),
+    /// else `now`, `seq` or a string format. Unknown tokens give `None` and
+    /// are kept as is.
+    fn template_token(
+        &mut self,
+        key: &str,
+        token: &str,
+        object: &serde_json::Map<String, Value>,
+    ) -> Option<String> {
+        let mut segments = token.split('.');
+        let referenced = object
+            .get(segments.next()?)
+            .and_then(|first| segments.try_fold(first, |value, key| value.get(key)));
+        if let Some(value) = referenced {
+            return Some(match value {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            });
+        }
+
+        match token {
+            "now" => Some(self.config.now().to_rfc3339()),
+            "seq" => Some(self.next_in_sequence(key, 1, 1).to_string()),
+            _ => STRING_FORMATS
+                .iter()
+                .find(|format| format.name == token)
+                .map(|format| (format.fake)(self.config, &mut self.rng)),
+        }
+    }
+}
+
+/// FNV-1a, which unlike `DefaultHasher` is the same in every build.
+fn stable_hash(key: &str) -> u64 {
+    key.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Merges `value` into `target`, recursing into objects present in both.
+fn merge_values(target: &mut Value, value: Value) {
+    match (target, value) {
+        (Value::Object(target), Value::Object(value)) => {
+            for (key, value) in value {
+                match target.get_mut(&key) {
+                    Some(existing) => merge_values(existing, value),
+                    None => {
+                        target.insert(key, value);
+                    }
+                }
+            }
+        }
+        (target, value) => *target = value,
+    }
+}
+
+/// `hint` lowercased with everything but letters and digits removed, so
+/// `first_name`, `firstName` and "First name" all read `firstname`.
+fn hint_key(hint: &str) -> String {
+    hint.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Fake data for a field whose name or description contains one of the
+/// known keywords. More specific keywords are checked first.
+fn fake_for_hint(hint: &str, locale: Locale, rng: &mut StdRng) -> Option<String> {
+    use fake::faker::address::raw::*;
+    use fake::faker::company::raw::*;
+    use fake::faker::currency::raw::*;
+    use fake::faker::internet::raw::*;
+    use fake::faker::lorem::raw::*;
+    use fake::faker::name::raw::*;
+    use fake::faker::phone_number::raw::*;
+    use fake::locales::EN;
+
+    let has = |keywords: &[&str]| keywords.iter().any(|keyword| hint.contains(keyword));
+
+    let value = if has(&["email"]) {
+        localized!(locale, FreeEmail; rng)
+    } else if has(&["username"]) {
+        Username(EN).fake_with_rng(rng)
+    } else if has(&["password", "secret"]) {
+        Password(EN, 12..20).fake_with_rng(rng)
+    } else if has(&["firstname", "givenname", "forename"]) {
+        localized!(locale, FirstName; rng)
+    } else if has(&["lastname", "surname", "familyname"]) {
+        localized!(locale, LastName; rng)
+    } else if has(&["company", "organization", "organisation", "employer"]) {
+        localized!(locale, CompanyName; rng)
+    } else if hint == "name"
+        || has(&[
+            "fullname",
+            "displayname",
+            "personname",
+            "contactname",
+            "customername",
+            "authorname",
+        ])
+    {
+        localized!(locale, Name; rng)
+    } else if has(&["phone", "mobile", "telephone"]) {
+        localized!(locale, PhoneNumber; rng)
+    } else if has(&["url", "website", "homepage"]) {
+        let word = Word(EN).fake_with_rng::<String, _>(rng).to_lowercase();
+        let suffix: String = localized!(locale, DomainSuffix; rng);
+        format!("https://{}.{}", word, suffix)
+    } else if has(&["ipaddress", "ipv4"]) {
+        IPv4(EN).fake_with_rng(rng)
+    } else if has(&["ipv6"]) {
+        IPv6(EN).fake_with_rng(rng)
+    } else if has(&["macaddress"]) {
+        MACAddress(EN).fake_with_rng(rng)
+    } else if has(&["zip", "postal", "postcode"]) {
+        localized!(locale, ZipCode; rng)
+    } else if has(&["street", "address"]) {
+        let number = BuildingNumber(EN).fake_with_rng::<String, _>(rng);
+        let street: String = localized!(locale, StreetName; rng);
+        format!("{} {}", number, street)
+    } else if has(&["city", "town"]) {
+        localized!(locale, CityName; rng)
+    } else if has(&["countrycode"]) {
+        CountryCode(EN).fake_with_rng(rng)
+    } else if has(&["country"]) {
+        CountryName(EN).fake_with_rng(rng)
+    } else if has(&["state", "province", "region"]) {
+        localized!(locale, StateName; rng)
+    } else if has(&["currency"]) {
+        CurrencyCode(EN).fake_with_rng(rng)
+    } else {
+        return None;
+    };
+    Some(value)
+}
+
+/// A generated number, or a string holding one (`number_format.as_string`).
+fn numeric_value(value: &Value) -> Option<f64> {
+    match value {
+        Value::String(s) => s.parse().ok(),
+        value => value.as_f64(),
+    }
+}
+
+/// The `{token}`s of a template pattern, without their braces.
+fn template_tokens(template: &str) -> impl Iterator<Item = &str> {
+    template
+        .split('{')
+        .skip(1)
+        .filter_map(|part| Some(part.split_once('}')?.0))
+}
+
+/// A request parameter's `raw` value as the same kind of JSON value as
+/// `generated`, or `None` when it does not parse as one.
+fn echoed_value(raw: &str, generated: &Value) -> Option<Value> {
+    match generated {
+        Value::Number(n) if n.is_f64() => raw
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map(Value::Number),
+        Value::Number(_) => raw.parse::<i64>().ok().map(Value::from),
+        Value::Bool(_) => raw.parse().ok().map(Value::Bool),
+        Value::String(_) | Value::Null => Some(Value::String(raw.to_string())),
+        Value::Array(_) | Value::Object(_) => None,
+    }
+}
+
+/// Rough number of values generating `schema` produces: schema nodes,
+/// with array items counted `maxItems` (default 5) times. Each `$ref` is
+/// followed once per branch.
+pub fn schema_complexity(swagger_state: &SwaggerState, schema: &Value) -> usize {
+    fn walk(swagger_state: &SwaggerState, schema: &Value, refs: &mut Vec<String>) -> usize {
+        let map = match schema.as_object() {
+            Some(map) => map,
+            None => return 0,
+        };
+
+        if let Some(ref_path) = map.get("$ref").and_then(Value::as_str) {
+            if refs.iter().any(|r| r == ref_path) {
+                return 1;
+            }
+            let resolved = match swagger_state.resolve_ref(ref_path) {
+                Some(resolved) => resolved,
+                None => return 1,
+            };
+            refs.push(ref_path.to_string());
+            let complexity = walk(swagger_state, &resolved, refs);
+            refs.pop();
+            return complexity;
+        }
+
+        let mut complexity = 1;
+        for key in ["properties", "patternProperties"] {
+            if let Some(props) = map.get(key).and_then(Value::as_object) {
+                complexity += props
+                    .values()
+                    .map(|prop| walk(swagger_state, prop, refs))
+                    .sum::<usize>();
+            }
+        }
+        for key in ["oneOf", "anyOf", "allOf", "prefixItems"] {
+            if let Some(variants) = map.get(key).and_then(Value::as_array) {
+                complexity += variants
+                    .iter()
+                    .map(|variant| walk(swagger_state, variant, refs))
+                    .sum::<usize>();
+            }
+        }
+        if let Some(items) = map.get("items") {
+            let count = map.get("maxItems").and_then(Value::as_u64).unwrap_or(5) as usize;
+            complexity += walk(swagger_state, items, refs).saturating_mul(count);
+        }
+
+        complexity
+    }
+
+    walk(swagger_state, schema, &mut Vec::new())
+}
+
+/// Makes up a property name matching `pattern` from its literal prefix plus
+/// a random word or number, or `None` when no such guess matches.
+fn pattern_key(pattern: &str, regex: &regex::Regex, rng: &mut StdRng) -> Option<String> {
+    use fake::faker::lorem::raw::Word;
+    use fake::locales::EN;
+
+    let prefix: String = pattern
+        .trim_start_matches('^')
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | ':' | '/'))
+        .collect();
+
+    [
+        format!("{}{}", prefix, Word(EN).fake_with_rng::<String, _>(rng)),
+        format!("{}{}", prefix, (0..1000).fake_with_rng::<u32, _>(rng)),
+        prefix.clone(),
+    ]
+    .into_iter()
+    .find(|candidate| !candidate.is_empty() && regex.is_match(candidate))
+}
+
+/// An `https` URL on a made-up host.
+fn fake_url(rng: &mut StdRng) -> String {
+    use fake::faker::lorem::raw::Word;
+    use fake::locales::EN;
+
+    format!(
+        "https://{}/{}",
+        fake_hostname(rng),
+        Word(EN).fake_with_rng::<String, _>(rng).to_lowercase()
+    )
+}
+
+fn fake_hostname(rng: &mut StdRng) -> String {
+    use fake::faker::internet::raw::DomainSuffix;
+    use fake::faker::lorem::raw::Word;
+    use fake::locales::EN;
+
+    format!(
+        "{}.{}",
+        Word(EN).fake_with_rng::<String, _>(rng).to_lowercase(),
+        DomainSuffix(EN).fake_with_rng::<String, _>(rng)
+    )
+}
+
+/// Range of generated numbers when the schema leaves one side open.
+const DEFAULT_NUMBER_SPAN: f64 = 100.0;
+
+/// The range a number schema allows, from its bounds and the range of its
+/// `format`. Defaults to 0-100.
+struct NumberBounds {
+    min: f64,
+    max: f64,
+    exclusive_min: bool,
+    exclusive_max: bool,
+}
+
+impl NumberBounds {
+    fn new(schema: &serde_json::Map<String, Value>, type_val: &str) -> Self {
+        let lower = lower_bound(schema);
+        let upper = upper_bound(schema);
+        let ((min, exclusive_min), (max, exclusive_max)) = match (lower, upper) {
+            (Some(lower), Some(upper)) => (lower, upper),
+            (Some(lower), None) => (
+                lower,
+                (
+                    (lower.0 + DEFAULT_NUMBER_SPAN).max(DEFAULT_NUMBER_SPAN),
+                    false,
+                ),
+            ),
+            (None, Some(upper)) => (((upper.0 - DEFAULT_NUMBER_SPAN).min(0.0), false), upper),
+            (None, None) => ((0.0, false), (DEFAULT_NUMBER_SPAN, false)),
+        };
+
+        let (format_min, format_max) = match schema.get("format").and_then(Value::as_str) {
+            Some("int32") => (i32::MIN as f64, i32::MAX as f64),
+            Some("float") => (f32::MIN as f64, f32::MAX as f64),
+            Some("double") => (f64::MIN, f64::MAX),
+            _ if type_val == "integer" => (i64::MIN as f64, i64::MAX as f64),
+            _ => (f64::MIN, f64::MAX),
+        };
+        Self {
+            min: min.max(format_min),
+            max: max.min(format_max),
+            exclusive_min: exclusive_min && min >= format_min,
+            exclusive_max: exclusive_max && max <= format_max,
+        }
+    }
+
+    fn contains(&self, value: f64) -> bool {
+        let above = if self.exclusive_min {
+            value > self.min
+        } else {
+            value >= self.min
+        };
+        let below = if self.exclusive_max {
+            value < self.max
+        } else {
+            value <= self.max
+        };
+        above && below
+    }
+}
+
+fn round_to(value: f64, decimals: u32) -> f64 {
+    let factor = 10_f64.powi(decimals as i32);
+    (value * factor).round() / factor
+}
+
+/// Places after the decimal point in `value`, e.g. 2 for `0.01`.
+fn decimal_places(value: f64) -> u32 {
+    value
+        .to_string()
+        .split_once('.')
+        .map_or(0, |(_, fraction)| fraction.len() as u32)
+        .min(15)
+}
+
+/// Allowed string lengths (in characters) per `minLength` and `maxLength`.
+fn string_bounds(schema: &serde_json::Map<String, Value>) -> RangeInclusive<usize> {
+    let length = |key: &str| schema.get(key).and_then(Value::as_u64).map(|n| n as usize);
+    let min = length("minLength").unwrap_or(0);
+    let max = length("maxLength").unwrap_or(usize::MAX).max(min);
+    min..=max
+}
+
+/// Index drawn with probability proportional to its weight; uniform when no
+/// weight is positive.
+fn weighted_index(weights: &[f64], rng: &mut StdRng) -> usize {
+    let total: f64 = weights.iter().filter(|w| **w > 0.0).sum();
+    if total <= 0.0 {
+        return (0..weights.len()).fake_with_rng::<usize, _>(rng);
+    }
+
+    let mut target = rng.gen::<f64>() * total;
+    for (index, weight) in weights.iter().enumerate() {
+        if *weight > 0.0 {
+            if target < *weight {
+                return index;
+            }
+            target -= weight;
+        }
+    }
+    weights.iter().rposition(|w| *w > 0.0).unwrap_or(0)
+}
+
+/// The type generated for an OpenAPI 3.1 `type` array: its first member
+/// other than `null`, so the same schema always yields the same shape.
+fn pick_union_type(types: &[Value]) -> &str {
+    types
+        .iter()
+        .filter_map(Value::as_str)
+        .find(|type_name| *type_name != "null")
+        .unwrap_or("null")
+}
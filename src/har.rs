@@ -0,0 +1,261 @@
+use std::{collections::HashMap, path::Path, sync::Mutex, time::Duration};
+
+use actix_web::{http::header::HeaderMap, HttpRequest, HttpResponse};
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+
+use crate::MockServerError;
+
+/// Response headers describing the recorded connection rather than the
+/// response, left for actix to set.
+const SKIPPED_HEADERS: [&str; 5] = [
+    "content-length",
+    "transfer-encoding",
+    "connection",
+    "content-encoding",
+    "date",
+];
+
+/// Collects request/response pairs and writes them as an HTTP Archive (HAR
+/// 1.2) document.
+#[derive(Default)]
+pub struct HarRecorder {
+    entries: Mutex<Vec<Value>>,
+}
+
+/// One exchange as captured by the request handler.
+pub struct HarExchange<'a> {
+    pub req: &'a HttpRequest,
+    pub request_body: Option<&'a [u8]>,
+    pub response: &'a HttpResponse<()>,
+    pub response_body: &'a [u8],
+    pub started: DateTime<Utc>,
+    pub elapsed: Duration,
+}
+
+impl HarRecorder {
+    pub fn record(&self, exchange: HarExchange<'_>) {
+        let entry = har_entry(exchange);
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.push(entry);
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> std::io::Result<()> {
+        let entries = self
+            .entries
+            .lock()
+            .map(|entries| entries.clone())
+            .unwrap_or_default();
+        let document = json!({
+            "log": {
+                "version": "1.2",
+                "creator": { "name": "spit", "version": env!("CARGO_PKG_VERSION") },
+                "entries": entries
+            }
+        });
+
+        std::fs::write(path, serde_json::to_vec_pretty(&document)?)
+    }
+}
+
+/// Serves the responses of a HAR recording, optionally after the time each
+/// one originally took.
+pub struct HarPlayback {
+    responses: HashMap<String, Vec<RecordedResponse>>,
+    cursors: Mutex<HashMap<String, usize>>,
+    timings: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordedResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+    pub time: Duration,
+}
+
+impl HarPlayback {
+    pub fn load(path: &Path, timings: bool) -> Result<Self, MockServerError> {
+        let document: Value = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        let entries = document
+            .pointer("/log/entries")
+            .and_then(Value::as_array)
+            .ok_or_else(|| {
+                MockServerError::Config(format!("{}: no log.entries array", path.display()))
+            })?;
+
+        let mut responses: HashMap<String, Vec<RecordedResponse>> = HashMap::new();
+        for entry in entries {
+            if let Some((key, response)) = recorded_response(entry) {
+                responses.entry(key).or_default().push(response);
+            }
+        }
+
+        Ok(Self {
+            responses,
+            cursors: Mutex::new(HashMap::new()),
+            timings,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.responses.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.responses.is_empty()
+    }
+
+    /// The next recorded response for this method, path and query. Repeated
+    /// requests walk through the recordings in order, starting over at the end.
+    pub fn next(&self, method: &str, path: &str, query: &str) -> Option<RecordedResponse> {
+        let key = playback_key(method, path, query);
+        let recorded = self.responses.get(&key)?;
+        let mut cursors = self.cursors.lock().ok()?;
+        let cursor = cursors.entry(key).or_insert(0);
+        let response = recorded[*cursor % recorded.len()].clone();
+        *cursor += 1;
+        Some(response)
+    }
+
+    /// Replays the next recorded response for `req`, waiting out its recorded
+    /// time first when timings are enabled.
+    pub async fn respond(&self, req: &HttpRequest) -> Option<HttpResponse> {
+        let recorded = self.next(req.method().as_str(), req.path(), req.query_string())?;
+        if self.timings {
+            tokio::time::sleep(recorded.time).await;
+        }
+
+        let status = actix_web::http::StatusCode::from_u16(recorded.status).ok()?;
+        let mut response = HttpResponse::build(status);
+        for (name, value) in recorded.headers {
+            response.append_header((name, value));
+        }
+        Some(response.body(recorded.body))
+    }
+}
+
+fn playback_key(method: &str, path: &str, query: &str) -> String {
+    match query {
+        "" => format!("{} {}", method.to_uppercase(), path),
+        query => format!("{} {}?{}", method.to_uppercase(), path, query),
+    }
+}
+
+fn recorded_response(entry: &Value) -> Option<(String, RecordedResponse)> {
+    let method = entry.pointer("/request/method")?.as_str()?;
+    let url = reqwest::Url::parse(entry.pointer("/request/url")?.as_str()?).ok()?;
+    let response = entry.get("response")?;
+
+    let headers = response
+        .get("headers")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|header| {
+            let name = header.get("name")?.as_str()?;
+            let value = header.get("value")?.as_str()?;
+            (!SKIPPED_HEADERS.contains(&name.to_lowercase().as_str()))
+                .then(|| (name.to_string(), value.to_string()))
+        })
+        .collect();
+    let time = entry.get("time").and_then(Value::as_f64).unwrap_or(0.0);
+
+    Some((
+        playback_key(method, url.path(), url.query().unwrap_or_default()),
+        RecordedResponse {
+            status: response.get("status")?.as_u64()?.try_into().ok()?,
+            headers,
+            body: response
+                .pointer("/content/text")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            time: Duration::from_secs_f64(time.max(0.0) / 1000.0),
+        },
+    ))
+}
+
+fn har_entry(exchange: HarExchange<'_>) -> Value {
+    let req = exchange.req;
+    let info = req.connection_info();
+    let url = match req.query_string() {
+        "" => format!("{}://{}{}", info.scheme(), info.host(), req.path()),
+        query => format!(
+            "{}://{}{}?{}",
+            info.scheme(),
+            info.host(),
+            req.path(),
+            query
+        ),
+    };
+    let http_version = format!("{:?}", req.version());
+    let query_string: Vec<(String, String)> =
+        serde_urlencoded::from_str(req.query_string()).unwrap_or_default();
+    let time = exchange.elapsed.as_secs_f64() * 1000.0;
+
+    let mut request = json!({
+        "method": req.method().as_str(),
+        "url": url,
+        "httpVersion": http_version,
+        "cookies": [],
+        "headers": har_headers(req.headers()),
+        "queryString": query_string
+            .iter()
+            .map(|(name, value)| json!({ "name": name, "value": value }))
+            .collect::<Vec<_>>(),
+        "headersSize": -1,
+        "bodySize": exchange.request_body.map_or(0, <[u8]>::len)
+    });
+    if let Some(body) = exchange.request_body.filter(|body| !body.is_empty()) {
+        request["postData"] = json!({
+            "mimeType": header_value(req.headers(), "content-type"),
+            "text": String::from_utf8_lossy(body)
+        });
+    }
+
+    let response = exchange.response;
+    json!({
+        "startedDateTime": exchange.started.to_rfc3339(),
+        "time": time,
+        "request": request,
+        "response": {
+            "status": response.status().as_u16(),
+            "statusText": response.status().canonical_reason().unwrap_or_default(),
+            "httpVersion": http_version,
+            "cookies": [],
+            "headers": har_headers(response.headers()),
+            "content": {
+                "size": exchange.response_body.len(),
+                "mimeType": header_value(response.headers(), "content-type"),
+                "text": String::from_utf8_lossy(exchange.response_body)
+            },
+            "redirectURL": header_value(response.headers(), "location"),
+            "headersSize": -1,
+            "bodySize": exchange.response_body.len()
+        },
+        "cache": {},
+        "timings": { "send": 0, "wait": time, "receive": 0 }
+    })
+}
+
+fn har_headers(headers: &HeaderMap) -> Vec<Value> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            json!({
+                "name": name.as_str(),
+                "value": value.to_str().unwrap_or_default()
+            })
+        })
+        .collect()
+}
+
+fn header_value(headers: &HeaderMap, name: &str) -> String {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string()
+}
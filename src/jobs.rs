@@ -0,0 +1,142 @@
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::config::RouteTable;
+
+/// Name of the operation extension turning a route into an async job
+/// submission.
+pub const ASYNC_EXTENSION: &str = "x-spit-async";
+
+/// How long a `done` job can still be polled before it is forgotten.
+pub const FINISHED_JOB_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// `x-spit-async` settings: the route polled for the job, and how long the
+/// job stays `pending` before it reports `done`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AsyncJobSpec {
+    /// Path template of the status route, e.g. `/jobs/{id}`.
+    pub location: String,
+    #[serde(default = "default_job_duration")]
+    pub duration_ms: u64,
+}
+
+fn default_job_duration() -> u64 {
+    5000
+}
+
+#[derive(Debug, Clone)]
+pub struct AsyncJob {
+    pub location: String,
+    pub created: Instant,
+    pub duration: Duration,
+}
+
+impl AsyncJobSpec {
+    pub fn from_operation(operation: &Value) -> Option<Self> {
+        operation
+            .get(ASYNC_EXTENSION)
+            .and_then(|spec| serde_json::from_value(spec.clone()).ok())
+    }
+
+    /// Starts a job, returning it with the URL path of its status route.
+    pub fn start(&self, id: &str) -> (AsyncJob, String) {
+        let job = AsyncJob {
+            location: self.location.clone(),
+            created: Instant::now(),
+            duration: Duration::from_millis(self.duration_ms),
+        };
+        (job, fill_id(&self.location, id))
+    }
+}
+
+impl AsyncJob {
+    pub fn status(&self) -> &'static str {
+        if self.created.elapsed() >= self.duration {
+            "done"
+        } else {
+            "pending"
+        }
+    }
+
+    /// Whether the job has been `done` for longer than `FINISHED_JOB_TTL`.
+    pub fn is_expired(&self) -> bool {
+        self.created.elapsed() >= self.duration + FINISHED_JOB_TTL
+    }
+}
+
+/// The routes some operation declares as its job status route.
+pub fn status_routes(routes: &RouteTable) -> HashSet<String> {
+    routes
+        .values()
+        .flatten()
+        .filter_map(|(_, operation)| AsyncJobSpec::from_operation(operation))
+        .map(|spec| spec.location)
+        .collect()
+}
+
+/// The value of the first `{param}` segment of `template` in `path`.
+pub fn job_id<'a>(template: &str, path: &'a str) -> Option<&'a str> {
+    template
+        .split('/')
+        .zip(path.split('/'))
+        .find(|(template_seg, _)| template_seg.starts_with('{'))
+        .map(|(_, path_seg)| path_seg)
+}
+
+fn fill_id(template: &str, id: &str) -> String {
+    template
+        .split('/')
+        .map(|segment| {
+            if segment.starts_with('{') {
+                id
+            } else {
+                segment
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn status_routes_are_the_declared_locations() {
+        let submit = json!({ ASYNC_EXTENSION: { "location": "/jobs/{id}" } });
+        let routes: RouteTable = [
+            ("/jobs".to_string(), vec![("post".to_string(), submit)]),
+            (
+                "/jobs/{id}".to_string(),
+                vec![("get".to_string(), json!({}))],
+            ),
+            ("/users".to_string(), vec![("get".to_string(), json!({}))]),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            status_routes(&routes),
+            HashSet::from(["/jobs/{id}".to_string()])
+        );
+    }
+
+    #[test]
+    fn jobs_expire_once_done_for_the_ttl() {
+        let duration = Duration::from_secs(5);
+        let started = |ago: Duration| AsyncJob {
+            location: "/jobs/{id}".to_string(),
+            created: Instant::now().checked_sub(ago).unwrap(),
+            duration,
+        };
+        assert!(!started(Duration::ZERO).is_expired());
+        assert!(!started(duration + FINISHED_JOB_TTL / 2).is_expired());
+        assert!(started(duration + FINISHED_JOB_TTL).is_expired());
+    }
+}
@@ -1,18 +1,68 @@
-use std::sync::Mutex;
+// `config_schema` builds one large `json!` literal.
+#![recursion_limit = "256"]
 
-use actix_web::{middleware::Logger, web, App, HttpServer};
-use config::{MockConfig, MockState};
-use log::{error, info};
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, RwLock,
+    },
+};
+
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceFactory, ServiceRequest, ServiceResponse},
+    middleware::{from_fn, Compress, Condition, Logger},
+    web, App, HttpServer,
+};
+use admin::{admin_access_guard, admin_scope, AdminAccess};
+use config::{Delay, MockConfig, MockState};
+use cors::{cors_middleware, CorsConfig};
+use generator::known_formats;
+use har::{HarPlayback, HarRecorder};
+use log::{error, info, warn};
+use log_file::RequestLogFile;
+use metrics::{metrics_endpoint, Metrics, METRICS_PATH};
+use proxy::Proxy;
 use request::handle_request;
-use reqwest;
+use router::RouteTree;
 use serde_json::Value;
-use swagger::{process_swagger_paths, SwaggerState};
+use swagger::{
+    filter_routes, find_case_duplicates, find_unknown_formats, merge_specs, process_swagger_paths,
+    SharedSwagger, SpecSource, SwaggerState,
+};
 use thiserror::Error;
+use tokio::sync::Notify;
+use watch::{watch_sources, Watch, WatchedHost, WatchedSpec};
 
+pub mod admin;
 pub mod cli;
 pub mod config;
+pub mod config_schema;
+pub mod cors;
+pub mod export;
+pub mod expr;
+pub mod generator;
+pub mod har;
+pub mod jobs;
+pub mod lint;
+pub mod log_file;
+pub mod metrics;
+pub mod proxy;
+pub mod rate_limit;
+pub mod record;
+pub mod regex_gen;
+pub mod replay;
 pub mod request;
+pub mod router;
+pub mod routes;
+pub mod store;
 pub mod swagger;
+pub mod swagger2;
+pub mod tls;
+pub mod watch;
+pub mod xml;
 
 #[derive(Error, Debug)]
 pub enum MockServerError {
@@ -20,10 +70,14 @@ pub enum MockServerError {
     SwaggerFetch(#[from] reqwest::Error),
     #[error("Failed to parse JSON: {0}")]
     JsonParse(#[from] serde_json::Error),
+    #[error("Failed to parse YAML: {0}")]
+    YamlParse(#[from] serde_yaml::Error),
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
     #[error("Invalid configuration: {0}")]
     Config(String),
+    #[error("Stopped after {0} request(s) failed validation")]
+    ValidationFailed(usize),
 }
 
 pub fn load_config(
@@ -33,7 +87,7 @@ pub fn load_config(
         let content = std::fs::read_to_string(path)?;
         if path
             .extension()
-            .map_or(false, |ext| ext == "yaml" || ext == "yml")
+            .is_some_and(|ext| ext == "yaml" || ext == "yml")
         {
             Ok(serde_yaml::from_str(&content)?)
         } else {
@@ -44,38 +98,457 @@ pub fn load_config(
     }
 }
 
+/// Mock state and spec for one API, answering requests whose host (the
+/// `Host` header, or `:authority` over HTTP/2) matches `hosts` in the config.
+pub type MockService = (web::Data<RwLock<MockState>>, web::Data<SharedSwagger>);
+
+#[derive(Default)]
+pub struct VirtualHosts {
+    pub services: HashMap<String, MockService>,
+}
+
+impl VirtualHosts {
+    pub fn get(&self, host_header: &str) -> Option<&MockService> {
+        let host = host_header.to_lowercase();
+        self.services.get(&host).or_else(|| {
+            host.rsplit_once(':')
+                .and_then(|(name, _port)| self.services.get(name))
+        })
+    }
+}
+
+/// Stops the server once `limit` requests have been rejected by validation,
+/// for using spit as a contract check in CI.
+pub struct ValidationGate {
+    limit: usize,
+    failures: AtomicUsize,
+    tripped: Notify,
+}
+
+impl ValidationGate {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit: limit.max(1),
+            failures: AtomicUsize::new(0),
+            tripped: Notify::new(),
+        }
+    }
+
+    pub fn record_failure(&self) {
+        if self.failures.fetch_add(1, Ordering::SeqCst) + 1 == self.limit {
+            self.tripped.notify_one();
+        }
+    }
+
+    fn is_tripped(&self) -> bool {
+        self.failures.load(Ordering::SeqCst) >= self.limit
+    }
+}
+
+/// Where the server accepts connections.
+pub enum Listener<'a> {
+    Tcp {
+        host: &'a str,
+        port: u16,
+        /// Also accept HTTP/2 with prior knowledge (h2c) next to HTTP/1.x.
+        http2: bool,
+    },
+    /// A Unix domain socket, removed again on shutdown.
+    Unix(&'a Path),
+    /// HTTPS with the PEM certificate chain and private key at `cert` and `key`.
+    Tls {
+        host: &'a str,
+        port: u16,
+        cert: &'a Path,
+        key: &'a Path,
+    },
+}
+
+/// What the server's apps are built from: the spec at a source with the
+/// state serving it, and the settings shared by every worker.
+#[derive(Clone)]
+pub struct MockApp {
+    /// The config, with the spec's `x-spit-config` layered under it.
+    pub config: MockConfig,
+    pub state: web::Data<RwLock<MockState>>,
+    pub swagger_state: web::Data<SharedSwagger>,
+    virtual_hosts: web::Data<VirtualHosts>,
+    admin_access: web::Data<AdminAccess>,
+    cors: Option<web::Data<CorsConfig>>,
+    validation_gate: Option<web::Data<ValidationGate>>,
+    har: Option<web::Data<HarRecorder>>,
+    playback: Option<web::Data<HarPlayback>>,
+    proxy: Option<web::Data<Proxy>>,
+    metrics: Option<web::Data<Metrics>>,
+    log_file: Option<web::Data<RequestLogFile>>,
+    compress: bool,
+}
+
+impl MockApp {
+    /// Loads the spec at `source` and opens the files `config` names.
+    pub async fn load(
+        source: &SpecSource,
+        config: &MockConfig,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        // Loaded first so the server-wide settings below include those of the
+        // spec's `x-spit-config`.
+        let (mut state, swagger_state) = load_service(source, config).await?;
+        let config = state.config.clone();
+
+        let access_log = match &config.access_log {
+            Some(path) => {
+                info!("Writing access log to {}", path.display());
+                Some(Arc::new(
+                    std::fs::OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(path)?,
+                ))
+            }
+            None => None,
+        };
+
+        let mut virtual_hosts = VirtualHosts::default();
+        for (virtual_host, host_source) in config.hosts.clone().unwrap_or_default() {
+            info!("Mounting {} for host {}", host_source, virtual_host);
+            let (mut host_state, host_swagger) =
+                load_service(&SpecSource::from(host_source.as_str()), &config).await?;
+            host_state.access_log = access_log.clone();
+            virtual_hosts.services.insert(
+                virtual_host.to_lowercase(),
+                (
+                    web::Data::new(RwLock::new(host_state)),
+                    web::Data::new(RwLock::new(Arc::new(host_swagger))),
+                ),
+            );
+        }
+        let virtual_hosts = web::Data::new(virtual_hosts);
+
+        state.access_log = access_log;
+        let state = web::Data::new(RwLock::new(state));
+        let swagger_state = web::Data::new(RwLock::new(Arc::new(swagger_state)));
+
+        let cors = config.cors.clone().map(web::Data::new);
+        let admin_access = web::Data::new(AdminAccess::new(config.admin_allow_ips.clone()));
+        let validation_gate = config
+            .fail_on_validation_error
+            .map(|limit| web::Data::new(ValidationGate::new(limit)));
+
+        let har = config.har.as_ref().map(|path| {
+            info!("Recording traffic to {}", path.display());
+            web::Data::new(HarRecorder::default())
+        });
+
+        let playback = match &config.playback {
+            Some(path) => {
+                let playback = HarPlayback::load(path, config.playback_timings.unwrap_or(true))?;
+                info!(
+                    "Playing back {} recorded responses from {}",
+                    playback.len(),
+                    path.display()
+                );
+                Some(web::Data::new(playback))
+            }
+            None => None,
+        };
+
+        let proxy = match &state
+            .read()
+            .ok()
+            .and_then(|state| state.config.proxy.clone())
+        {
+            Some(base_url) => {
+                info!("Forwarding unmocked requests to {}", base_url);
+                Some(web::Data::new(Proxy::new(base_url)?))
+            }
+            None => None,
+        };
+
+        let metrics = config.metrics.unwrap_or(false).then(|| {
+            info!("Serving Prometheus metrics at {}", METRICS_PATH);
+            web::Data::new(Metrics::default())
+        });
+
+        let log_file = match &config.log_file {
+            Some(path) => {
+                info!("Appending requests to {}", path.display());
+                Some(web::Data::new(RequestLogFile::open(path)?))
+            }
+            None => None,
+        };
+
+        let compress = config.compress.unwrap_or(false);
+        if compress {
+            info!("Compressing responses per Accept-Encoding");
+        }
+
+        Ok(Self {
+            config,
+            state,
+            swagger_state,
+            virtual_hosts,
+            admin_access,
+            cors,
+            validation_gate,
+            har,
+            playback,
+            proxy,
+            metrics,
+            log_file,
+            compress,
+        })
+    }
+
+    /// An app answering requests from the loaded spec; the server builds
+    /// one per worker.
+    pub fn app(
+        &self,
+    ) -> App<
+        impl ServiceFactory<
+            ServiceRequest,
+            Config = (),
+            Response = ServiceResponse<impl MessageBody>,
+            Error = actix_web::Error,
+            InitError = (),
+        >,
+    > {
+        App::new()
+            .wrap(from_fn(cors_middleware))
+            .wrap(Logger::default())
+            .wrap(Condition::new(self.compress, Compress::default()))
+            .configure(|cfg| {
+                if let Some(cors) = &self.cors {
+                    cfg.app_data(cors.clone());
+                }
+                if let Some(gate) = &self.validation_gate {
+                    cfg.app_data(gate.clone());
+                }
+                if let Some(har) = &self.har {
+                    cfg.app_data(har.clone());
+                }
+                if let Some(playback) = &self.playback {
+                    cfg.app_data(playback.clone());
+                }
+                if let Some(proxy) = &self.proxy {
+                    cfg.app_data(proxy.clone());
+                }
+                if let Some(log_file) = &self.log_file {
+                    cfg.app_data(log_file.clone());
+                }
+                if let Some(metrics) = &self.metrics {
+                    cfg.app_data(metrics.clone()).service(
+                        web::resource(METRICS_PATH)
+                            .wrap(from_fn(admin_access_guard))
+                            .route(web::get().to(metrics_endpoint)),
+                    );
+                }
+            })
+            .app_data(self.state.clone())
+            .app_data(self.swagger_state.clone())
+            .app_data(self.virtual_hosts.clone())
+            .app_data(self.admin_access.clone())
+            .service(admin_scope())
+            .service(web::resource("/{tail:.*}").route(web::route().to(handle_request)))
+            .default_service(web::route().to(|req: actix_web::HttpRequest| {
+                error!("Unhandled request: {} {}", req.method(), req.path());
+                if let Some(state) = req.app_data::<web::Data<RwLock<MockState>>>() {
+                    if let Ok(state) = state.read() {
+                        state.record_unmatched(req.path());
+                    }
+                }
+                async move {
+                    actix_web::HttpResponse::NotFound().json(serde_json::json!({
+                        "error": "Route not found",
+                        "path": req.path(),
+                        "method": req.method().as_str()
+                    }))
+                }
+            }))
+    }
+
+    /// Watches `source`, the spec `self` was loaded from, and the specs of
+    /// its virtual hosts, reloading them on change; see `watch_sources`.
+    pub fn watch(
+        &self,
+        source: SpecSource,
+        delay: Option<u64>,
+        watch: Watch,
+    ) -> impl std::future::Future<Output = ()> {
+        let main = WatchedSpec {
+            source,
+            service: (self.state.clone(), self.swagger_state.clone()),
+        };
+        let hosts = self
+            .config
+            .hosts
+            .iter()
+            .flatten()
+            .filter_map(|(host, source)| {
+                let service = self.virtual_hosts.services.get(&host.to_lowercase())?;
+                Some(WatchedHost {
+                    host: host.clone(),
+                    spec: WatchedSpec {
+                        source: SpecSource::from(source.as_str()),
+                        service: service.clone(),
+                    },
+                })
+            })
+            .collect();
+        watch_sources(main, hosts, delay, watch)
+    }
+}
+
 pub async fn start_server(
-    source: &str,
-    host: &str,
-    port: u16,
+    source: SpecSource,
+    listener: Listener<'_>,
     delay: Option<u64>,
     mut config: MockConfig,
+    watch: Option<Watch>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     env_logger::init_from_env(env_logger::Env::new().default_filter_or("debug"));
 
     info!("Initializing mock server...");
-    let swagger = fetch_swagger(source).await?;
-    info!("Loaded swagger configuration");
-
-    let swagger_state = web::Data::new(SwaggerState {
-        components: swagger
-            .get("components")
-            .and_then(|c| c.get("schemas"))
-            .and_then(|schemas| schemas.as_object())
-            .map(|schemas| {
-                schemas
-                    .iter()
-                    .map(|(k, v)| (k.clone(), v.clone()))
-                    .collect()
-            })
-            .unwrap_or_default(),
-    });
 
     if config.delay.is_none() {
-        config.delay = delay;
+        config.delay = delay.map(Delay::Fixed);
+    }
+
+    let mock = MockApp::load(&source, &config).await?;
+    let config = mock.config.clone();
+
+    if let Some(watch) = watch {
+        actix_web::rt::spawn(mock.watch(source, delay, watch));
+    }
+
+    let app = {
+        let mock = mock.clone();
+        move || mock.app()
+    };
+
+    let server = match listener {
+        Listener::Tcp { host, port, http2 } => {
+            info!("Starting mock server on http://{}:{}", host, port);
+            let server = HttpServer::new(app);
+            if http2 {
+                info!("Accepting HTTP/2 (h2c) connections");
+                server.bind_auto_h2c(format!("{}:{}", host, port))?.run()
+            } else {
+                server.bind(format!("{}:{}", host, port))?.run()
+            }
+        }
+        #[cfg(unix)]
+        Listener::Unix(path) => {
+            use std::os::unix::fs::FileTypeExt;
+            if std::fs::metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+                std::fs::remove_file(path)?;
+            }
+            info!("Starting mock server on unix:{}", path.display());
+            HttpServer::new(app).bind_uds(path)?.run()
+        }
+        #[cfg(not(unix))]
+        Listener::Unix(_) => {
+            return Err(MockServerError::Config(
+                "unix sockets are not supported on this platform".to_string(),
+            )
+            .into())
+        }
+        Listener::Tls {
+            host,
+            port,
+            cert,
+            key,
+        } => {
+            let tls_config = tls::load_tls_config(cert, key)?;
+            info!("Starting mock server on https://{}:{}", host, port);
+            HttpServer::new(app)
+                .bind_rustls_0_23(format!("{}:{}", host, port), tls_config)?
+                .run()
+        }
+    };
+
+    if let Some(gate) = &mock.validation_gate {
+        let handle = server.handle();
+        let gate = gate.clone();
+        tokio::spawn(async move {
+            gate.tripped.notified().await;
+            warn!("Validation failure limit reached, shutting down");
+            handle.stop(true).await;
+        });
+    }
+
+    server.await?;
+
+    if let Listener::Unix(path) = listener {
+        if let Err(e) = std::fs::remove_file(path) {
+            warn!("Failed to remove socket {}: {}", path.display(), e);
+        }
+    }
+
+    if let Some(log_file) = &mock.log_file {
+        log_file.close().await;
+    }
+
+    if let (Some(har), Some(path)) = (&mock.har, &config.har) {
+        har.write(path)?;
+        info!("Wrote HAR recording to {}", path.display());
+    }
+
+    match &mock.validation_gate {
+        Some(gate) if gate.is_tripped() => {
+            Err(MockServerError::ValidationFailed(gate.limit).into())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Loads a spec and builds the state serving it, layering `config` over any
+/// `x-spit-config` embedded in the spec.
+async fn load_service(
+    source: &SpecSource,
+    config: &MockConfig,
+) -> Result<(MockState, SwaggerState), Box<dyn std::error::Error>> {
+    let swagger = fetch_swagger(source).await?;
+    info!("Loaded swagger configuration from {}", source);
+
+    let config = match swagger.get("x-spit-config") {
+        Some(embedded) => {
+            let embedded: MockConfig = serde_json::from_value(embedded.clone())
+                .map_err(|e| MockServerError::Config(format!("x-spit-config: {}", e)))?;
+            info!("Applying embedded x-spit-config from spec");
+            config.clone().layered_over(embedded)?
+        }
+        None => config.clone(),
+    };
+    config.validate()?;
+
+    let unknown_formats = find_unknown_formats(&swagger, &known_formats());
+    for (location, format) in &unknown_formats {
+        warn!("Unknown format `{}` at {}", format, location);
+    }
+    if config.strict_formats.unwrap_or(false) && !unknown_formats.is_empty() {
+        return Err(MockServerError::Config(format!(
+            "{} unknown schema format(s) in {} (strict_formats is enabled)",
+            unknown_formats.len(),
+            source
+        ))
+        .into());
     }
 
-    let routes = process_swagger_paths(&swagger);
+    let mut swagger_state = SwaggerState::from_spec(&swagger, source)?;
+    swagger_state.add_patterns(config.regex_patterns());
+
+    let mut routes = process_swagger_paths(&swagger);
+    let filtered = filter_routes(
+        &mut routes,
+        config.only.as_deref().unwrap_or_default(),
+        config.exclude.as_deref().unwrap_or_default(),
+    );
+    if filtered > 0 {
+        info!("Filtered out {} routes by only/exclude", filtered);
+    }
+    for duplicates in find_case_duplicates(&routes) {
+        warn!("Paths differ only by case: {}", duplicates.join(", "));
+    }
     info!("Processed {} routes", routes.len());
     for (path, methods) in &routes {
         info!(
@@ -85,56 +558,99 @@ pub async fn start_server(
         );
     }
 
-    let state = web::Data::new(Mutex::new(MockState {
+    let router = RouteTree::new(&routes, config.case_insensitive_paths.unwrap_or(false));
+    let state = MockState {
+        router,
+        status_routes: jobs::status_routes(&routes),
         routes,
         config,
-        request_log: Vec::new(),
-    }));
+        ..MockState::default()
+    };
 
-    info!("Starting mock server on http://{}:{}", host, port);
+    Ok((state, swagger_state))
+}
 
-    HttpServer::new(move || {
-        App::new()
-            .wrap(Logger::default())
-            .app_data(state.clone())
-            .app_data(swagger_state.clone())
-            .service(web::resource("/{tail:.*}").route(web::route().to(handle_request)))
-            .default_service(web::route().to(|req: actix_web::HttpRequest| {
-                error!("Unhandled request: {} {}", req.method(), req.path());
-                async move {
-                    actix_web::HttpResponse::NotFound().json(serde_json::json!({
-                        "error": "Route not found",
-                        "path": req.path(),
-                        "method": req.method().as_str()
-                    }))
+/// Name and value of each `{param}` segment of `template` in `path`, in
+/// order.
+pub fn extract_path_params(template: &str, path: &str) -> Vec<(String, String)> {
+    let template_segments = template.split('/').filter(|s| !s.is_empty());
+    let path_segments = path.split('/').filter(|s| !s.is_empty());
+    template_segments
+        .zip(path_segments)
+        .filter_map(|(template_seg, path_seg)| {
+            let name = template_seg.strip_prefix('{')?.strip_suffix('}')?;
+            Some((name.to_string(), path_seg.to_string()))
+        })
+        .collect()
+}
+
+/// Loads the spec at `source`, translating Swagger 2.0 documents to OpenAPI 3.
+pub async fn fetch_swagger(source: &SpecSource) -> Result<Value, MockServerError> {
+    if let SpecSource::Url(url) = source {
+        let response = reqwest::get(url).await?;
+        let yaml = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|content_type| {
+                if content_type.contains("yaml") {
+                    Some(true)
+                } else if content_type.contains("json") {
+                    Some(false)
+                } else {
+                    None
                 }
-            }))
-    })
-    .bind(format!("{}:{}", host, port))?
-    .run()
-    .await?;
+            })
+            .or_else(|| is_yaml_path(url.split(['?', '#']).next().unwrap_or(url)));
+        let spec = parse_spec(&response.text().await?, yaml)?;
+        return Ok(upgrade_swagger2(spec, url));
+    }
 
-    Ok(())
+    let files = source.files();
+    let mut specs = Vec::with_capacity(files.len());
+    for file in files {
+        let content = std::fs::read_to_string(&file)?;
+        let spec = parse_spec(&content, is_yaml_path(&file.to_string_lossy()))?;
+        let spec = upgrade_swagger2(spec, &file.to_string_lossy());
+        specs.push((file, spec));
+    }
+    match specs.len() {
+        0 => Err(MockServerError::Config(format!(
+            "{}: no spec files found",
+            source
+        ))),
+        1 => Ok(specs.remove(0).1),
+        count => {
+            info!("Merging {} spec files", count);
+            Ok(merge_specs(specs))
+        }
+    }
 }
 
-fn validate_path_params(path: &str, req_path: &str) -> bool {
-    let path_segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
-    let req_segments: Vec<&str> = req_path.split('/').filter(|s| !s.is_empty()).collect();
-
-    if path_segments.len() != req_segments.len() {
-        return false;
+/// Whether a spec path names a YAML or a JSON file, judging by extension.
+fn is_yaml_path(path: &str) -> Option<bool> {
+    match Path::new(path).extension()?.to_str()? {
+        "yaml" | "yml" => Some(true),
+        "json" => Some(false),
+        _ => None,
     }
+}
 
-    path_segments
-        .iter()
-        .zip(req_segments.iter())
-        .all(|(path_seg, req_seg)| path_seg.starts_with('{') || path_seg == req_seg)
+/// Parses a spec as YAML or JSON, trying JSON first and then YAML when the
+/// format is unknown.
+fn parse_spec(content: &str, yaml: Option<bool>) -> Result<Value, MockServerError> {
+    match yaml {
+        Some(true) => Ok(serde_yaml::from_str(content)?),
+        Some(false) => Ok(serde_json::from_str(content)?),
+        None => serde_json::from_str(content)
+            .or_else(|json_error| serde_yaml::from_str(content).map_err(|_| json_error.into())),
+    }
 }
 
-pub async fn fetch_swagger(url: &str) -> Result<Value, MockServerError> {
-    if url.starts_with("http") {
-        Ok(reqwest::get(url).await?.json().await?)
-    } else {
-        Ok(serde_json::from_str(&std::fs::read_to_string(url)?)?)
+fn upgrade_swagger2(spec: Value, source: &str) -> Value {
+    if swagger2::is_swagger2(&spec) {
+        info!("Translating Swagger 2.0 spec {} to OpenAPI 3", source);
+        return swagger2::upgrade(&spec);
     }
+    spec
 }
@@ -0,0 +1,107 @@
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+
+use crate::{
+    config::RouteTable,
+    fetch_swagger,
+    swagger::{find_case_duplicates, find_refs, process_swagger_paths, SpecSource, SwaggerState},
+};
+
+/// Loads the spec at `source` and prints the problems spit would hit
+/// serving it, failing when there is any.
+pub async fn validate_spec(source: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let spec_source = SpecSource::from(source);
+    let swagger = fetch_swagger(&spec_source).await?;
+    let routes = process_swagger_paths(&swagger);
+    let problems = lint_spec(&swagger, &routes, &spec_source);
+
+    if problems.is_empty() {
+        println!("{}: no problems found in {} routes", source, routes.len());
+        return Ok(());
+    }
+
+    println!("{}: {} problem(s) found", source, problems.len());
+    for problem in &problems {
+        println!("  - {}", problem);
+    }
+    Err(format!("{} is not usable as is", source).into())
+}
+
+/// Describes every unresolvable `$ref`, invalid pattern, operation without
+/// responses, response without `application/json` content and route
+/// conflicting with another in the spec.
+pub fn lint_spec(swagger: &Value, routes: &RouteTable, source: &SpecSource) -> Vec<String> {
+    let (swagger_state, mut problems) = SwaggerState::load(swagger, source);
+
+    for (location, target) in find_refs(swagger) {
+        if swagger_state.resolve_ref(&target).is_none() {
+            problems.push(format!("unresolvable $ref `{}` at {}", target, location));
+        }
+    }
+
+    for (path, handlers) in routes {
+        for (method, operation) in handlers {
+            let responses = operation.get("responses").and_then(Value::as_object);
+            let Some(responses) = responses.filter(|responses| !responses.is_empty()) else {
+                problems.push(format!("{} {} documents no responses", method, path));
+                continue;
+            };
+
+            for (status, response) in responses {
+                let content = swagger_state
+                    .resolve_object(response)
+                    .and_then(|response| response.get("content"))
+                    .and_then(Value::as_object);
+                if let Some(content) = content.filter(|content| !content.is_empty()) {
+                    if !content.contains_key("application/json") {
+                        problems.push(format!(
+                            "{} {} response {} has no application/json content, \
+                             so spit answers it with a default body",
+                            method, path, status
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    for duplicates in find_case_duplicates(routes) {
+        problems.push(format!(
+            "paths differ only by case: {}",
+            duplicates.join(", ")
+        ));
+    }
+    for duplicates in find_param_duplicates(routes) {
+        problems.push(format!(
+            "paths differ only by parameter names, so only the first is served: {}",
+            duplicates.join(", ")
+        ));
+    }
+
+    problems
+}
+
+/// Groups of route paths that are equal once their `{param}` names are
+/// ignored, e.g. `/users/{id}` and `/users/{userId}`.
+fn find_param_duplicates(routes: &RouteTable) -> Vec<Vec<&str>> {
+    let mut by_shape: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    for path in routes.keys() {
+        let shape: Vec<&str> = path
+            .split('/')
+            .map(|segment| {
+                if segment.starts_with('{') {
+                    "{}"
+                } else {
+                    segment
+                }
+            })
+            .collect();
+        by_shape.entry(shape.join("/")).or_default().push(path);
+    }
+
+    by_shape
+        .into_values()
+        .filter(|paths| paths.len() > 1)
+        .collect()
+}
@@ -0,0 +1,150 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    pin::Pin,
+    sync::Mutex,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use actix_web::{
+    body::{BodySize, BoxBody, MessageBody},
+    web,
+};
+use log::error;
+use tokio::{sync::mpsc, task::JoinHandle};
+
+use crate::config::RequestLog;
+
+/// How often buffered entries are flushed to the file.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Appends request log entries to a JSON Lines file from a background task,
+/// so requests never wait on the disk. Entries are flushed every second and
+/// on `close`.
+pub struct RequestLogFile {
+    /// `None` asks the writer to flush and stop.
+    sender: mpsc::UnboundedSender<Option<RequestLog>>,
+    writer: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl RequestLogFile {
+    /// Opens `path` for appending and starts the writer task.
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let writer = tokio::spawn(write_entries(receiver, BufWriter::new(file)));
+        Ok(Self {
+            sender,
+            writer: Mutex::new(Some(writer)),
+        })
+    }
+
+    pub fn append(&self, entry: RequestLog) {
+        if self.sender.send(Some(entry)).is_err() {
+            error!("Request log file writer has stopped, dropping entry");
+        }
+    }
+
+    /// Writes the entries still queued and stops the writer.
+    pub async fn close(&self) {
+        let _ = self.sender.send(None);
+        let writer = self.writer.lock().ok().and_then(|mut writer| writer.take());
+        if let Some(writer) = writer {
+            if let Err(e) = writer.await {
+                error!("Request log file writer failed: {}", e);
+            }
+        }
+    }
+}
+
+/// A streamed response body that keeps a copy of the chunks passing
+/// through, and appends its log entry with them once the stream ends, fails
+/// or is dropped by a disconnecting client.
+pub struct LoggedBody {
+    body: BoxBody,
+    captured: Vec<u8>,
+    entry: Option<RequestLog>,
+    log_file: web::Data<RequestLogFile>,
+}
+
+impl LoggedBody {
+    pub fn new(body: BoxBody, entry: RequestLog, log_file: web::Data<RequestLogFile>) -> Self {
+        Self {
+            body,
+            captured: Vec::new(),
+            entry: Some(entry),
+            log_file,
+        }
+    }
+
+    fn append(&mut self) {
+        if let Some(mut entry) = self.entry.take() {
+            entry.response_size = self.captured.len() as u64;
+            entry.response_body = Some(String::from_utf8_lossy(&self.captured).into_owned());
+            self.log_file.append(entry);
+        }
+    }
+}
+
+impl MessageBody for LoggedBody {
+    type Error = <BoxBody as MessageBody>::Error;
+
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<web::Bytes, Self::Error>>> {
+        let this = self.get_mut();
+        let next = Pin::new(&mut this.body).poll_next(cx);
+        match &next {
+            Poll::Ready(Some(Ok(chunk))) => this.captured.extend_from_slice(chunk),
+            Poll::Ready(_) => this.append(),
+            Poll::Pending => {}
+        }
+        next
+    }
+}
+
+impl Drop for LoggedBody {
+    fn drop(&mut self) {
+        self.append();
+    }
+}
+
+async fn write_entries(
+    mut receiver: mpsc::UnboundedReceiver<Option<RequestLog>>,
+    mut file: BufWriter<File>,
+) {
+    let mut flush = tokio::time::interval(FLUSH_INTERVAL);
+    loop {
+        tokio::select! {
+            entry = receiver.recv() => match entry {
+                Some(Some(entry)) => {
+                    let written = serde_json::to_writer(&mut file, &entry)
+                        .map_err(std::io::Error::from)
+                        .and_then(|()| file.write_all(b"\n"));
+                    if let Err(e) = written {
+                        error!("Failed to write request log file: {}", e);
+                    }
+                }
+                _ => break,
+            },
+            _ = flush.tick() => {
+                if let Err(e) = file.flush() {
+                    error!("Failed to flush request log file: {}", e);
+                }
+            }
+        }
+    }
+    if let Err(e) = file.flush() {
+        error!("Failed to flush request log file: {}", e);
+    }
+}
@@ -1,34 +1,97 @@
 use clap::Parser;
 
-use spit::{cli::{Cli, Commands}, load_config, start_server};
+use spit::{
+    cli::{apply_seed, Cli, Commands, ServerArgs},
+    config_schema::mock_config_schema,
+    export::export_fixtures,
+    lint::validate_spec,
+    load_config,
+    record::record_upstream,
+    replay::replay_requests,
+    routes::print_routes,
+    start_server,
+    swagger::SpecSource,
+    watch::Watch,
+    Listener,
+};
 
 #[actix_web::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+async fn main() {
     let cli = Cli::parse();
 
+    if let Err(e) = run(&cli).await {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn run(cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
     match &cli.command {
-        Commands::Scan {
-            url,
-            port,
-            host,
-            delay,
-            config: config_path,
-        } => {
-            let config = load_config(config_path)?;
-            start_server(url, host, *port, *delay, config).await?;
+        Commands::Scan { url, server } => {
+            run_server(SpecSource::from(url.as_str()), server).await?
         }
-        Commands::File {
-            path,
+        Commands::File { path, server } => {
+            run_server(SpecSource::Files(path.clone()), server).await?
+        }
+        Commands::ConfigSchema => {
+            println!("{}", serde_json::to_string_pretty(&mock_config_schema())?);
+        }
+        Commands::Validate { spec } => validate_spec(spec).await?,
+        Commands::Routes { spec, format } => print_routes(spec, *format).await?,
+        Commands::Replay { log, target } => replay_requests(log, target).await?,
+        Commands::Record {
+            url,
+            out,
             port,
             host,
-            delay,
-            config: config_path,
+        } => record_upstream(url, host, *port, out).await?,
+        Commands::Export {
+            spec,
+            format,
+            out,
+            base_url,
+            config,
+            seed,
         } => {
-            let path = path.to_str().ok_or("Invalid path")?;
-            let config = load_config(config_path)?;
-            start_server(path, host, *port, *delay, config).await?;
+            let mut config = load_config(config)?;
+            apply_seed(seed, &mut config)?;
+            export_fixtures(spec, &config, *format, base_url, out).await?;
         }
     }
 
     Ok(())
 }
+
+async fn run_server(
+    source: SpecSource,
+    server: &ServerArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut config = load_config(&server.config)?;
+    server.apply_to(&mut config)?;
+    let listener = match (&server.unix_socket, &server.tls_cert, &server.tls_key) {
+        (Some(path), _, _) => Listener::Unix(path),
+        (None, Some(cert), Some(key)) => Listener::Tls {
+            host: &server.host,
+            port: server.port,
+            cert,
+            key,
+        },
+        _ => Listener::Tcp {
+            host: &server.host,
+            port: server.port,
+            http2: server.http2,
+        },
+    };
+    let watch = server.watch.then(|| {
+        let args = server.clone();
+        Watch {
+            config_path: server.config.clone(),
+            load_config: Box::new(move || {
+                let mut config = load_config(&args.config)?;
+                args.apply_to(&mut config)?;
+                Ok(config)
+            }),
+        }
+    });
+    start_server(source, listener, server.delay, config, watch).await
+}
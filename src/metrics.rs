@@ -0,0 +1,104 @@
+use std::time::Duration;
+
+use actix_web::{web, HttpResponse};
+use log::error;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder,
+};
+
+/// Path the metrics are served at when `metrics` is on.
+pub const METRICS_PATH: &str = "/metrics";
+
+/// Route label of requests no route matched.
+pub const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Method label of requests whose method is not a standard one, which
+/// clients choose freely and would otherwise each add a series.
+const OTHER_METHOD: &str = "other";
+
+const STANDARD_METHODS: [&str; 9] = [
+    "GET", "HEAD", "POST", "PUT", "DELETE", "CONNECT", "OPTIONS", "TRACE", "PATCH",
+];
+
+/// Upper bounds of the latency histogram buckets, in seconds.
+const LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Request counters and latency histograms, labelled by method and route
+/// template (not the concrete path, which would add a series per id).
+pub struct Metrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    latencies: HistogramVec,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        let requests = IntCounterVec::new(
+            Opts::new(
+                "spit_requests_total",
+                "Requests answered, by route and status.",
+            ),
+            &["method", "path", "status"],
+        )
+        .expect("valid counter options");
+        let latencies = HistogramVec::new(
+            HistogramOpts::new(
+                "spit_request_duration_seconds",
+                "Time to answer requests, by route.",
+            )
+            .buckets(LATENCY_BUCKETS.to_vec()),
+            &["method", "path"],
+        )
+        .expect("valid histogram options");
+
+        let registry = Registry::new();
+        registry
+            .register(Box::new(requests.clone()))
+            .expect("metric registered once");
+        registry
+            .register(Box::new(latencies.clone()))
+            .expect("metric registered once");
+        Self {
+            registry,
+            requests,
+            latencies,
+        }
+    }
+}
+
+impl Metrics {
+    pub fn observe(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        let method = method_label(method);
+        self.requests
+            .with_label_values(&[method, route, &status.to_string()])
+            .inc();
+        self.latencies
+            .with_label_values(&[method, route])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// The metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut out) {
+            error!("Failed to encode metrics: {}", e);
+        }
+        String::from_utf8(out).unwrap_or_default()
+    }
+}
+
+pub async fn metrics_endpoint(metrics: web::Data<Metrics>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(TextEncoder::new().format_type())
+        .body(metrics.render())
+}
+
+fn method_label(method: &str) -> &str {
+    if STANDARD_METHODS.contains(&method) {
+        method
+    } else {
+        OTHER_METHOD
+    }
+}
@@ -0,0 +1,146 @@
+use actix_web::{http::StatusCode, web, HttpRequest, HttpResponse};
+use futures_util::stream;
+use log::{debug, error};
+use reqwest::{redirect::Policy, Client, Url};
+use serde_json::{json, Value};
+
+use crate::MockServerError;
+
+/// Name of the operation extension forwarding a route to the `proxy`
+/// upstream even though the spec describes it.
+pub const PASSTHROUGH_EXTENSION: &str = "x-spit-passthrough";
+
+/// Headers that describe a single connection and are not forwarded either
+/// way.
+const HOP_BY_HOP_HEADERS: [&str; 10] = [
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+    "host",
+    "content-length",
+];
+
+/// Forwards requests spit does not mock to a real backend.
+pub struct Proxy {
+    client: Client,
+    base_url: Url,
+    uncompressed: bool,
+}
+
+impl Proxy {
+    pub fn new(base_url: &str) -> Result<Self, MockServerError> {
+        let base_url = parse_base_url(base_url).map_err(MockServerError::Config)?;
+        let client = Client::builder()
+            .redirect(Policy::none())
+            .build()
+            .map_err(|e| MockServerError::Config(format!("proxy client: {}", e)))?;
+        Ok(Self {
+            client,
+            base_url,
+            uncompressed: false,
+        })
+    }
+
+    /// Drops the client's `Accept-Encoding` so the upstream answers with
+    /// bodies spit can read: the proxy client does not decompress.
+    pub fn uncompressed(mut self) -> Self {
+        self.uncompressed = true;
+        self
+    }
+
+    /// Sends the request to the upstream and streams its response back,
+    /// answering 502 when the upstream cannot be reached.
+    pub async fn forward(&self, req: &HttpRequest, body: &Option<web::Bytes>) -> HttpResponse {
+        let url = self.upstream_url(req);
+        debug!("Forwarding {} {} to {}", req.method(), req.path(), url);
+
+        let method = match reqwest::Method::from_bytes(req.method().as_str().as_bytes()) {
+            Ok(method) => method,
+            Err(_) => return HttpResponse::MethodNotAllowed().finish(),
+        };
+        let mut upstream = self.client.request(method, url.clone());
+        for (name, value) in req.headers() {
+            if is_hop_by_hop(name.as_str())
+                || (self.uncompressed && name == actix_web::http::header::ACCEPT_ENCODING)
+            {
+                continue;
+            }
+            upstream = upstream.header(name.as_str(), value.as_bytes());
+        }
+        if let Some(body) = body {
+            upstream = upstream.body(body.clone());
+        }
+
+        let upstream = match upstream.send().await {
+            Ok(response) => response,
+            Err(e) => {
+                error!("Failed to forward {} to {}: {}", req.path(), url, e);
+                return HttpResponse::BadGateway().json(json!({
+                    "error": "Bad gateway",
+                    "details": format!("Upstream {} did not answer: {}", url, e)
+                }));
+            }
+        };
+
+        let status =
+            StatusCode::from_u16(upstream.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+        let mut response = HttpResponse::build(status);
+        for (name, value) in upstream.headers() {
+            if !is_hop_by_hop(name.as_str()) {
+                response.append_header((name.as_str(), value.as_bytes()));
+            }
+        }
+
+        let body = stream::unfold(Some(upstream), |upstream| async move {
+            let mut upstream = upstream?;
+            match upstream.chunk().await {
+                Ok(Some(chunk)) => Some((Ok(chunk), Some(upstream))),
+                Ok(None) => None,
+                Err(e) => Some((Err(e), None)),
+            }
+        });
+        response.streaming(body)
+    }
+
+    /// The upstream URL for `req`: its path appended to the base URL's path,
+    /// with the same query string.
+    fn upstream_url(&self, req: &HttpRequest) -> Url {
+        let mut url = self.base_url.clone();
+        let path = format!(
+            "{}{}",
+            self.base_url.path().trim_end_matches('/'),
+            req.path()
+        );
+        url.set_path(&path);
+        url.set_query(Some(req.query_string()).filter(|query| !query.is_empty()));
+        url
+    }
+}
+
+/// Checks that `base_url` is an absolute http(s) URL.
+pub fn parse_base_url(base_url: &str) -> Result<Url, String> {
+    let url = Url::parse(base_url).map_err(|e| format!("proxy `{}`: {}", base_url, e))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("proxy `{}` must be an http(s) URL", base_url));
+    }
+    Ok(url)
+}
+
+/// Whether the operation is marked to be forwarded upstream.
+pub fn is_passthrough(operation: &Value) -> bool {
+    operation
+        .get(PASSTHROUGH_EXTENSION)
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+}
+
+fn is_hop_by_hop(name: &str) -> bool {
+    HOP_BY_HOP_HEADERS
+        .iter()
+        .any(|hop| name.eq_ignore_ascii_case(hop))
+}
@@ -0,0 +1,160 @@
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// `rate_limit` settings: `requests` per `window_secs`, refilled gradually,
+/// counted separately for each value of `per`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    pub requests: u32,
+    #[serde(default = "default_window_secs")]
+    pub window_secs: u64,
+    #[serde(default)]
+    pub per: RateLimitKey,
+}
+
+/// What requests share a bucket.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitKey {
+    /// Each client IP.
+    #[default]
+    Client,
+    /// Each route template, across clients.
+    Route,
+    /// Each client IP on each route template.
+    ClientRoute,
+}
+
+fn default_window_secs() -> u64 {
+    60
+}
+
+impl RateLimitConfig {
+    /// The bucket a request from `client` to `route` draws from. A limit
+    /// set by a `routes` override (`routed`) is counted apart from the
+    /// global one and from other routes, even per client.
+    pub fn bucket_key(&self, client: &str, route: &str, routed: bool) -> String {
+        match self.per {
+            RateLimitKey::Client if routed => format!("{} {}", client, route),
+            RateLimitKey::Client => client.to_string(),
+            RateLimitKey::Route => route.to_string(),
+            RateLimitKey::ClientRoute => format!("{} {}", client, route),
+        }
+    }
+
+    fn window(&self) -> Duration {
+        Duration::from_secs(self.window_secs.max(1))
+    }
+}
+
+/// Token bucket holding up to `requests` tokens and refilling the whole
+/// amount over one window.
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+    capacity: f64,
+    window: Duration,
+}
+
+impl TokenBucket {
+    pub fn new(limit: &RateLimitConfig) -> Self {
+        Self {
+            tokens: limit.requests as f64,
+            updated: Instant::now(),
+            capacity: limit.requests as f64,
+            window: limit.window(),
+        }
+    }
+
+    /// Takes a token, or returns how long until one is available.
+    pub fn take(&mut self) -> Result<(), Duration> {
+        self.refill(Instant::now());
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+        let per_second = self.per_second();
+        if per_second <= 0.0 {
+            return Err(self.window);
+        }
+        Err(Duration::from_secs_f64((1.0 - self.tokens) / per_second))
+    }
+
+    /// Whether the bucket has refilled completely by now, and so is no
+    /// different from a new one.
+    pub fn is_full(&self) -> bool {
+        let elapsed = Instant::now().duration_since(self.updated).as_secs_f64();
+        self.tokens + elapsed * self.per_second() >= self.capacity
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let refilled = now.duration_since(self.updated).as_secs_f64() * self.per_second();
+        self.tokens = (self.tokens + refilled).min(self.capacity);
+        self.updated = now;
+    }
+
+    fn per_second(&self) -> f64 {
+        self.capacity / self.window.as_secs_f64()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limit(requests: u32, window_secs: u64, per: RateLimitKey) -> RateLimitConfig {
+        RateLimitConfig {
+            requests,
+            window_secs,
+            per,
+        }
+    }
+
+    #[test]
+    fn keys_buckets_by_client_and_route() {
+        let by_client = limit(1, 60, RateLimitKey::Client);
+        assert_eq!(
+            by_client.bucket_key("10.0.0.1", "/users", false),
+            "10.0.0.1"
+        );
+        assert_eq!(
+            by_client.bucket_key("10.0.0.1", "/users", true),
+            "10.0.0.1 /users"
+        );
+        let by_route = limit(1, 60, RateLimitKey::Route);
+        assert_eq!(by_route.bucket_key("10.0.0.1", "/users", false), "/users");
+        let by_both = limit(1, 60, RateLimitKey::ClientRoute);
+        assert_eq!(
+            by_both.bucket_key("10.0.0.1", "/users", false),
+            "10.0.0.1 /users"
+        );
+    }
+
+    #[test]
+    fn refuses_once_empty_until_refilled() {
+        let mut bucket = TokenBucket::new(&limit(3, 60, RateLimitKey::Client));
+        assert!(bucket.is_full());
+        for _ in 0..3 {
+            assert_eq!(bucket.take(), Ok(()));
+        }
+        assert!(!bucket.is_full());
+
+        let retry_after = bucket.take().unwrap_err();
+        assert!(
+            retry_after > Duration::from_secs(19) && retry_after <= Duration::from_secs(20),
+            "{:?}",
+            retry_after
+        );
+
+        let updated = bucket.updated;
+        bucket.refill(updated + Duration::from_secs(20));
+        assert_eq!(bucket.take(), Ok(()));
+        assert!(bucket.take().is_err());
+
+        bucket.refill(updated + Duration::from_secs(600));
+        assert_eq!(bucket.tokens, 3.0);
+    }
+}
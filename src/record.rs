@@ -0,0 +1,176 @@
+use std::{path::Path, sync::Mutex};
+
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{ServiceFactory, ServiceRequest, ServiceResponse},
+    middleware::Logger,
+    web, App, HttpRequest, HttpResponse, HttpServer,
+};
+use chrono::Utc;
+use log::{error, info};
+use serde_json::{json, Map, Value};
+
+use crate::{config::RequestLog, proxy::Proxy, request::media_type_essence, MockServerError};
+
+/// Collects the exchanges proxied by `spit record`.
+#[derive(Default)]
+pub struct Recorder {
+    entries: Mutex<Vec<RequestLog>>,
+}
+
+impl Recorder {
+    pub fn record(&self, entry: RequestLog) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.push(entry);
+        }
+    }
+
+    pub fn entries(&self) -> Vec<RequestLog> {
+        self.entries
+            .lock()
+            .map(|entries| entries.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// Proxies every request to `upstream`, and once the server stops writes
+/// the recorded responses to `out` as an OpenAPI document `spit file` can
+/// serve.
+pub async fn record_upstream(
+    upstream: &str,
+    host: &str,
+    port: u16,
+    out: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
+
+    let proxy = web::Data::new(Proxy::new(upstream)?.uncompressed());
+    let recorder = web::Data::new(Recorder::default());
+
+    let app_recorder = recorder.clone();
+    info!(
+        "Recording {} through http://{}:{}, stop with Ctrl-C",
+        upstream, host, port
+    );
+    HttpServer::new(move || recording_app(proxy.clone(), app_recorder.clone()))
+        .bind(format!("{}:{}", host, port))?
+        .run()
+        .await?;
+
+    let entries = recorder.entries();
+    let spec = fixtures_spec(upstream, &entries);
+    std::fs::write(out, serde_json::to_vec_pretty(&spec)?).map_err(MockServerError::from)?;
+    info!(
+        "Wrote {} recorded requests to {}",
+        entries.len(),
+        out.display()
+    );
+
+    Ok(())
+}
+
+/// An app forwarding every request through `proxy` and keeping each
+/// exchange in `recorder`.
+pub fn recording_app(
+    proxy: web::Data<Proxy>,
+    recorder: web::Data<Recorder>,
+) -> App<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = actix_web::Error,
+        InitError = (),
+    >,
+> {
+    App::new()
+        .wrap(Logger::default())
+        .app_data(proxy)
+        .app_data(recorder)
+        .default_service(web::route().to(record_exchange))
+}
+
+async fn record_exchange(
+    req: HttpRequest,
+    body: Option<web::Bytes>,
+    proxy: web::Data<Proxy>,
+    recorder: web::Data<Recorder>,
+) -> HttpResponse {
+    let (response, response_body) = proxy.forward(&req, &body).await.into_parts();
+    let response_body = match response_body.try_into_bytes() {
+        Ok(bytes) => bytes,
+        Err(body) => match actix_web::body::to_bytes(body).await {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                error!("Failed to buffer upstream response: {}", e);
+                return HttpResponse::BadGateway().finish();
+            }
+        },
+    };
+
+    recorder.record(RequestLog {
+        timestamp: Utc::now(),
+        method: req.method().to_string(),
+        path: req.path().to_string(),
+        query: Some(req.query_string())
+            .filter(|query| !query.is_empty())
+            .map(String::from),
+        headers: req
+            .headers()
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or_default().to_string()))
+            .collect(),
+        body: body
+            .as_ref()
+            .filter(|bytes| !bytes.is_empty())
+            .map(|bytes| String::from_utf8_lossy(bytes).into_owned()),
+        response_status: response.status().as_u16(),
+        response_size: response_body.len() as u64,
+        peer_addr: req.peer_addr().map(|addr| addr.ip().to_string()),
+        response_body: Some(String::from_utf8_lossy(&response_body).into_owned()),
+        response_content_type: response
+            .headers()
+            .get(actix_web::http::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(String::from),
+    });
+
+    response.set_body(BoxBody::new(response_body))
+}
+
+/// An OpenAPI document with one operation per recorded method and path,
+/// answering the last response recorded for it as its example, under the
+/// media type the upstream answered with (`application/json` when it sent
+/// none). Bodies that are not JSON become string examples.
+pub fn fixtures_spec(upstream: &str, entries: &[RequestLog]) -> Value {
+    let mut paths: Map<String, Value> = Map::new();
+    for entry in entries {
+        let body = entry.response_body.as_deref().unwrap_or_default();
+        let mut response = json!({ "description": "Recorded response" });
+        if !body.is_empty() {
+            let media_type = entry
+                .response_content_type
+                .as_deref()
+                .map(media_type_essence)
+                .unwrap_or_else(|| "application/json".to_string());
+            let example = serde_json::from_str(body).unwrap_or_else(|_| json!(body));
+            let mut content = Map::new();
+            content.insert(media_type, json!({ "example": example }));
+            response["content"] = Value::Object(content);
+        }
+
+        let path_item = paths.entry(entry.path.clone()).or_insert_with(|| json!({}));
+        path_item[entry.method.to_lowercase()] = json!({
+            "responses": { entry.response_status.to_string(): response }
+        });
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": format!("Recorded from {}", upstream),
+            "version": "1.0.0"
+        },
+        "paths": paths
+    })
+}
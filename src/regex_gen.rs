@@ -0,0 +1,148 @@
+use std::ops::RangeInclusive;
+
+use rand::{rngs::StdRng, Rng};
+use regex::Regex;
+use regex_syntax::hir::{Class, ClassUnicode, ClassUnicodeRange, Hir, HirKind};
+
+/// Extra repetitions allowed past the minimum of an unbounded `*`, `+` or
+/// `{n,}`, and the most past the minimum for wide bounded ones.
+const MAX_EXTRA_REPEATS: u32 = 8;
+
+/// Strings generated before settling for one outside the length bounds.
+const ATTEMPTS: usize = 16;
+
+/// Generates a string matching `regex`, preferring one whose length (in
+/// characters) is within `bounds`. `None` when the pattern cannot be parsed
+/// or nothing generated matches, e.g. because of anchors in the middle of
+/// the pattern.
+pub fn generate_matching(
+    regex: &Regex,
+    bounds: RangeInclusive<usize>,
+    rng: &mut StdRng,
+) -> Option<String> {
+    let hir = regex_syntax::parse(regex.as_str()).ok()?;
+
+    let mut fallback = None;
+    for _ in 0..ATTEMPTS {
+        let mut candidate = String::new();
+        render(&hir, rng, &mut candidate);
+        if !regex.is_match(&candidate) {
+            continue;
+        }
+        if bounds.contains(&candidate.chars().count()) {
+            return Some(candidate);
+        }
+        fallback.get_or_insert(candidate);
+    }
+    fallback
+}
+
+fn render(hir: &Hir, rng: &mut StdRng, out: &mut String) {
+    match hir.kind() {
+        HirKind::Empty | HirKind::Look(_) => {}
+        HirKind::Literal(literal) => out.push_str(&String::from_utf8_lossy(&literal.0)),
+        HirKind::Class(Class::Unicode(class)) => out.extend(pick_char(class, rng)),
+        HirKind::Class(Class::Bytes(class)) => {
+            let ranges: Vec<ClassUnicodeRange> = class
+                .iter()
+                .filter(|range| range.start().is_ascii())
+                .map(|range| {
+                    ClassUnicodeRange::new(range.start() as char, range.end().min(0x7f) as char)
+                })
+                .collect();
+            out.extend(pick_char(&ClassUnicode::new(ranges), rng));
+        }
+        HirKind::Repetition(repetition) => {
+            let max = repetition
+                .max
+                .unwrap_or(u32::MAX)
+                .min(repetition.min.saturating_add(MAX_EXTRA_REPEATS));
+            for _ in 0..rng.gen_range(repetition.min..=max) {
+                render(&repetition.sub, rng, out);
+            }
+        }
+        HirKind::Capture(capture) => render(&capture.sub, rng, out),
+        HirKind::Concat(parts) => {
+            for part in parts {
+                render(part, rng, out);
+            }
+        }
+        HirKind::Alternation(branches) => {
+            render(&branches[rng.gen_range(0..branches.len())], rng, out)
+        }
+    }
+}
+
+/// A random character of `class`, from its printable ASCII part when it
+/// has one (so `\d` gives `0-9` and `.` no control characters).
+fn pick_char(class: &ClassUnicode, rng: &mut StdRng) -> Option<char> {
+    let mut printable = class.clone();
+    printable.intersect(&ClassUnicode::new([ClassUnicodeRange::new(' ', '~')]));
+    let class = if printable.ranges().is_empty() {
+        class
+    } else {
+        &printable
+    };
+
+    let size = |range: &ClassUnicodeRange| range.end() as u32 - range.start() as u32 + 1;
+    let total: u32 = class.iter().map(size).sum();
+    if total == 0 {
+        return None;
+    }
+    let mut index = rng.gen_range(0..total);
+    for range in class.iter() {
+        if index < size(range) {
+            return char::from_u32(range.start() as u32 + index).or(Some(range.start()));
+        }
+        index -= size(range);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn generated_strings_match_their_pattern() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for pattern in [
+            r"^[A-Z]{3}-\d{4}$",
+            r"^ORD-[0-9]{6}$",
+            r"^(cat|dog|bird)s?$",
+            r"^\w+@example\.(com|org)$",
+            r"^[a-f0-9]{8}(-[a-f0-9]{4}){3}-[a-f0-9]{12}$",
+            r"^.+$",
+        ] {
+            let regex = Regex::new(pattern).unwrap();
+            for _ in 0..50 {
+                let value = generate_matching(&regex, 0..=usize::MAX, &mut rng).unwrap();
+                assert!(
+                    regex.is_match(&value),
+                    "{:?} does not match {}",
+                    value,
+                    pattern
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn prefers_strings_within_the_length_bounds() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let regex = Regex::new(r"^[a-z]{1,20}$").unwrap();
+        for _ in 0..50 {
+            let value = generate_matching(&regex, 2..=8, &mut rng).unwrap();
+            assert!((2..=8).contains(&value.len()), "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn unsatisfiable_patterns_generate_nothing() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let regex = Regex::new("a^b").unwrap();
+        assert_eq!(generate_matching(&regex, 0..=usize::MAX, &mut rng), None);
+    }
+}
@@ -0,0 +1,73 @@
+use std::path::Path;
+
+use log::info;
+use reqwest::Method;
+
+use crate::{config::RequestLog, MockServerError};
+
+/// Headers describing the original connection rather than the request.
+const SKIPPED_HEADERS: [&str; 4] = ["host", "content-length", "connection", "transfer-encoding"];
+
+/// Reads `RequestLog` entries (a JSON array or one object per line) from `log`.
+pub fn read_request_log(log: &Path) -> Result<Vec<RequestLog>, MockServerError> {
+    let content = std::fs::read_to_string(log)?;
+
+    if content.trim_start().starts_with('[') {
+        return Ok(serde_json::from_str(&content)?);
+    }
+
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).map_err(MockServerError::from))
+        .collect()
+}
+
+/// Re-issues every logged request against `target` in order, printing the
+/// status received next to the one originally logged.
+pub async fn replay_requests(log: &Path, target: &str) -> Result<(), MockServerError> {
+    let entries = read_request_log(log)?;
+    info!("Replaying {} requests against {}", entries.len(), target);
+
+    let client = reqwest::Client::new();
+    let base = target.trim_end_matches('/');
+    let mut mismatches = 0;
+
+    for entry in &entries {
+        let method = Method::from_bytes(entry.method.as_bytes())
+            .map_err(|e| MockServerError::Config(format!("invalid method in log: {}", e)))?;
+        let url = match &entry.query {
+            Some(query) => format!("{}{}?{}", base, entry.path, query),
+            None => format!("{}{}", base, entry.path),
+        };
+
+        let mut request = client.request(method, &url);
+        for (name, value) in &entry.headers {
+            if !SKIPPED_HEADERS.contains(&name.to_lowercase().as_str()) {
+                request = request.header(name, value);
+            }
+        }
+        if let Some(body) = &entry.body {
+            request = request.body(body.clone());
+        }
+
+        let status = request.send().await?.status().as_u16();
+        let marker = if status == entry.response_status {
+            ""
+        } else {
+            mismatches += 1;
+            "  (differs)"
+        };
+        println!(
+            "{} {} -> {} (logged {}){}",
+            entry.method, entry.path, status, entry.response_status, marker
+        );
+    }
+
+    println!(
+        "Replayed {} requests, {} with a different status",
+        entries.len(),
+        mismatches
+    );
+    Ok(())
+}
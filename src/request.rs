@@ -1,69 +1,285 @@
-use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web::{
+    body::{BodySize, BodyStream, BoxBody, MessageBody},
+    http::StatusCode,
+    web, HttpRequest, HttpResponse, HttpResponseBuilder,
+};
 use chrono::Utc;
-use fake::Fake;
 use log::{debug, error};
-use regex::Regex;
+use rand::{rngs::StdRng, Rng};
 use serde_json::{json, Value};
 use std::{
-    collections::{HashMap, HashSet},
-    sync::Mutex,
+    collections::HashMap,
+    io::Write,
+    net::{Ipv4Addr, Ipv6Addr},
+    sync::{Arc, Mutex, RwLock, RwLockReadGuard},
 };
 
 use crate::{
-    config::{MockConfig, MockFieldConfig, MockState, RequestLog},
-    swagger::SwaggerState,
-    validate_path_params,
+    config::{
+        random_uuid, ChaosConfig, Delay, ExampleStrategy, MockConfig, MockState, PaginationConfig,
+        RequestLog, RouteHandlers, Sequences,
+    },
+    extract_path_params,
+    generator::{schema_complexity, MockGenerator},
+    har::{HarExchange, HarPlayback, HarRecorder},
+    jobs::{job_id, AsyncJobSpec},
+    log_file::{LoggedBody, RequestLogFile},
+    metrics::{Metrics, UNMATCHED_ROUTE},
+    proxy::{is_passthrough, Proxy},
+    rate_limit::TokenBucket,
+    store::{self, entity_id, Resource, ID_FIELD},
+    swagger::{
+        discriminator_choices, lower_bound, swagger_snapshot, tuple_items, upper_bound,
+        SharedSwagger, SwaggerState,
+    },
+    xml::{prefers_xml, to_xml, XML_CONTENT_TYPE},
+    ValidationGate, VirtualHosts,
 };
 
+const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+const FORM_CONTENT_TYPE: &str = "application/x-www-form-urlencoded";
+const MULTIPART_CONTENT_TYPE: &str = "multipart/form-data";
+
+/// Query parameter and header asking for one of the documented responses.
+const STATUS_QUERY_PARAM: &str = "__spit_status";
+const STATUS_HEADER: &str = "x-spit-status";
+
+/// Schema complexity from which `offload_generation` moves generation off
+/// the worker thread.
+const DEFAULT_OFFLOAD_THRESHOLD: usize = 500;
+
+/// Keys of an object checked against `patternProperties` and
+/// `additionalProperties`; those past it are accepted unchecked, bounding
+/// the cost of validating huge objects.
+const MAX_VALIDATED_KEYS: usize = 10_000;
+
+/// Size of the pieces a body is sent in when `body_delay` is set.
+const BODY_CHUNK_SIZE: usize = 1024;
+
+/// Relative error allowed when checking `multipleOf`, since decimal
+/// multiples such as 0.01 are not exact in floating point.
+const MULTIPLE_OF_TOLERANCE: f64 = 1e-9;
+
 pub struct RequestHandler {
     req: HttpRequest,
     path: String,
-    state: web::Data<Mutex<MockState>>,
-    swagger_state: web::Data<SwaggerState>,
+    state: web::Data<RwLock<MockState>>,
+    shared_swagger: web::Data<SharedSwagger>,
+    /// The spec the request is answered from, taken again under the guard
+    /// the route is matched with, so a reload in between cannot pair the
+    /// old spec with the new routes.
+    swagger_state: Arc<SwaggerState>,
+    /// `strict_formats`: validate the string `format`s of requests.
+    strict_formats: bool,
 }
 
 impl RequestHandler {
     pub fn new(
         req: HttpRequest,
         path: web::Path<String>,
-        state: web::Data<Mutex<MockState>>,
-        swagger_state: web::Data<SwaggerState>,
+        state: web::Data<RwLock<MockState>>,
+        shared_swagger: web::Data<SharedSwagger>,
     ) -> Self {
+        let strict_formats = state
+            .read()
+            .map(|state| state.config.strict_formats.unwrap_or(false))
+            .unwrap_or(false);
         Self {
             req,
             path: format!("/{}", path.as_str()),
             state,
-            swagger_state,
+            swagger_state: swagger_snapshot(&shared_swagger),
+            shared_swagger,
+            strict_formats,
         }
     }
 
-    pub async fn handle_request(&self, body: Option<web::Bytes>) -> HttpResponse {
+    pub async fn handle_request(mut self, body: Option<web::Bytes>) -> HttpResponse {
         debug!("Received request: {} {}", self.req.method(), self.path);
-
-        let mut state_guard = match self.acquire_state_lock() {
-            Ok(guard) => guard,
+        let started = (Utc::now(), std::time::Instant::now());
+
+        let current;
+        let route_result = match self.acquire_state_lock() {
+            Ok(state_guard) if state_guard.reloading => {
+                debug!("Spec reload in progress, deferring {}", self.path);
+                return HttpResponse::ServiceUnavailable()
+                    .insert_header((actix_web::http::header::RETRY_AFTER, "1"))
+                    .json(json!({
+                        "error": "Service unavailable",
+                        "details": "Mock spec is being reloaded"
+                    }));
+            }
+            Ok(state_guard) => {
+                // Reloads swap the spec only while `reloading` is set, so
+                // under this guard it matches the routes.
+                current = (
+                    swagger_snapshot(&self.shared_swagger),
+                    state_guard.config.strict_formats.unwrap_or(false),
+                );
+                self.find_matching_route(&state_guard)
+                    .map(|(route_path, handlers)| {
+                        (
+                            route_path.clone(),
+                            handlers.clone(),
+                            state_guard.config.for_route(
+                                self.req.method().as_str(),
+                                route_path,
+                                &self.path,
+                            ),
+                        )
+                    })
+            }
             Err(response) => return response,
         };
+        (self.swagger_state, self.strict_formats) = current;
+
+        let body_delay = route_result
+            .as_ref()
+            .ok()
+            .and_then(|(_, _, config)| config.body_delay);
 
-        let route_result = self.find_matching_route(&state_guard);
+        let played_back = match self.req.app_data::<web::Data<HarPlayback>>() {
+            Some(playback) => playback.respond(&self.req).await,
+            None => None,
+        };
 
-        let response = match route_result {
-            Ok((route_path, handlers)) => {
-                let config = state_guard.config.clone();
+        let template = route_result
+            .as_ref()
+            .ok()
+            .map(|(route_path, _, _)| route_path.clone());
 
-                self.process_route(route_path, handlers, &body, &config)
-                    .await
+        let proxy = self.req.app_data::<web::Data<Proxy>>();
+        let response = match (played_back, route_result) {
+            (Some(response), _) => {
+                debug!("Played back recorded response for {}", self.path);
+                response
+            }
+            (None, Ok((route_path, handlers, config))) => {
+                let passthrough = handlers.iter().any(|(m, operation)| {
+                    m == self.req.method().as_str() && is_passthrough(operation)
+                });
+                match (self.check_rate_limit(&route_path, &config), proxy) {
+                    (Some(limited), _) => limited,
+                    (None, Some(proxy)) if passthrough => proxy.forward(&self.req, &body).await,
+                    (None, _) => {
+                        self.process_route(&route_path, &handlers, &body, &config)
+                            .await
+                    }
+                }
+            }
+            (None, Err(response)) => {
+                if let Ok(state_guard) = self.acquire_state_lock() {
+                    state_guard.record_unmatched(&self.path);
+                }
+                match proxy {
+                    Some(proxy) => proxy.forward(&self.req, &body).await,
+                    None => response,
+                }
+            }
+        };
+
+        if let Ok(state_guard) = self.acquire_state_lock() {
+            self.log_request(&state_guard, &body, &response);
+        }
+        if let Some(metrics) = self.req.app_data::<web::Data<Metrics>>() {
+            metrics.observe(
+                self.req.method().as_str(),
+                template.as_deref().unwrap_or(UNMATCHED_ROUTE),
+                response.status().as_u16(),
+                started.1.elapsed(),
+            );
+        }
+
+        let response = match self.req.app_data::<web::Data<RequestLogFile>>() {
+            Some(log_file) => self.append_log_file(log_file, &body, response),
+            None => response,
+        };
+
+        let response = match self.req.app_data::<web::Data<HarRecorder>>() {
+            Some(har) => self.record_har(har, started, &body, response).await,
+            None => response,
+        };
+
+        match body_delay {
+            Some(delay) => delay_body(response, std::time::Duration::from_millis(delay)).await,
+            None => response,
+        }
+    }
+
+    /// Answers 429 with `Retry-After` when the request's `rate_limit` bucket
+    /// is empty.
+    fn check_rate_limit(&self, route_path: &str, config: &MockConfig) -> Option<HttpResponse> {
+        let limit = config.rate_limit.as_ref()?;
+        let client = self
+            .req
+            .peer_addr()
+            .map(|addr| addr.ip().to_string())
+            .unwrap_or_default();
+        let state = self.acquire_state_lock().ok()?;
+        let routed = state.config.rate_limit.as_ref() != Some(limit);
+        let key = limit.bucket_key(&client, route_path, routed);
+
+        let mut buckets = state.rate_limits.lock().ok()?;
+        if !buckets.contains_key(&key) {
+            // Full buckets are as good as new ones; dropping them keeps one
+            // bucket per client ever seen from piling up.
+            buckets.retain(|_, bucket| !bucket.is_full());
+        }
+        let wait = buckets
+            .entry(key)
+            .or_insert_with(|| TokenBucket::new(limit))
+            .take()
+            .err()?;
+
+        debug!("Rate limit exceeded for {}", self.path);
+        let retry_after = wait.as_secs_f64().ceil().max(1.0) as u64;
+        Some(
+            HttpResponse::TooManyRequests()
+                .insert_header((
+                    actix_web::http::header::RETRY_AFTER,
+                    retry_after.to_string(),
+                ))
+                .json(json!({
+                    "error": "Too many requests",
+                    "details": format!(
+                        "Rate limit of {} requests per {}s exceeded",
+                        limit.requests, limit.window_secs
+                    )
+                })),
+        )
+    }
+
+    /// Buffers the response body to add the exchange to the HAR recording.
+    async fn record_har(
+        &self,
+        har: &HarRecorder,
+        started: (chrono::DateTime<Utc>, std::time::Instant),
+        body: &Option<web::Bytes>,
+        response: HttpResponse,
+    ) -> HttpResponse {
+        let (response, response_body) = response.into_parts();
+        let response_body = match actix_web::body::to_bytes(response_body).await {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                error!("Failed to buffer response body: {}", e);
+                return HttpResponse::InternalServerError().finish();
             }
-            Err(response) => response,
         };
 
-        self.log_request(&mut state_guard, response.status().as_u16());
+        har.record(HarExchange {
+            req: &self.req,
+            request_body: body.as_deref(),
+            response: &response,
+            response_body: &response_body,
+            started: started.0,
+            elapsed: started.1.elapsed(),
+        });
 
-        response
+        response.set_body(BoxBody::new(response_body))
     }
 
-    fn acquire_state_lock(&self) -> Result<std::sync::MutexGuard<'_, MockState>, HttpResponse> {
-        self.state.lock().map_err(|e| {
+    fn acquire_state_lock(&self) -> Result<RwLockReadGuard<'_, MockState>, HttpResponse> {
+        self.state.read().map_err(|e| {
             error!("Failed to acquire state lock: {}", e);
             HttpResponse::InternalServerError().json(json!({
                 "error": "Internal server error",
@@ -75,15 +291,11 @@ impl RequestHandler {
     fn find_matching_route<'a>(
         &self,
         state: &'a MockState,
-    ) -> Result<(&'a String, &'a Vec<(String, Value)>), HttpResponse> {
-        let matching_route = state.routes.iter().find(|(route_path, _)| {
-            let matches = validate_path_params(route_path, &self.path);
-            debug!(
-                "Checking route '{}' against '{}': {}",
-                route_path, self.path, matches
-            );
-            matches
-        });
+    ) -> Result<(&'a String, &'a RouteHandlers), HttpResponse> {
+        let matching_route = state
+            .router
+            .find(&self.path)
+            .and_then(|route_path| state.routes.get_key_value(route_path));
 
         matching_route.ok_or_else(|| {
             error!("No matching route found for {}", self.path);
@@ -98,7 +310,7 @@ impl RequestHandler {
     async fn process_route(
         &self,
         route_path: &str,
-        handlers: &Vec<(String, Value)>,
+        handlers: &[(String, Value)],
         body: &Option<web::Bytes>,
         config: &MockConfig,
     ) -> HttpResponse {
@@ -106,7 +318,21 @@ impl RequestHandler {
         let method = self.req.method().as_str();
 
         match handlers.iter().find(|(m, _)| m == method) {
-            Some((_, route_schema)) => self.handle_matched_route(route_schema, body, config).await,
+            Some((_, route_schema)) => {
+                self.handle_matched_route(route_path, route_schema, body, config)
+                    .await
+            }
+            None if method == "OPTIONS" => {
+                let mut allowed: Vec<&str> = handlers.iter().map(|(m, _)| m.as_str()).collect();
+                allowed.push("OPTIONS");
+                debug!(
+                    "Answering OPTIONS on {} with Allow: {:?}",
+                    route_path, allowed
+                );
+                HttpResponse::NoContent()
+                    .insert_header((actix_web::http::header::ALLOW, allowed.join(", ")))
+                    .finish()
+            }
             None => {
                 error!(
                     "No handler found for method {} on route {}",
@@ -124,28 +350,403 @@ impl RequestHandler {
 
     async fn handle_matched_route(
         &self,
+        route_path: &str,
         route_schema: &Value,
         body: &Option<web::Bytes>,
         config: &MockConfig,
     ) -> HttpResponse {
         debug!("Found matching method handler for {}", self.req.method());
 
-        if let Some(parameters) = route_schema.get("parameters") {
-            if let Err(error_response) = self.validate_headers(parameters) {
-                return error_response;
+        if let Err(error_response) = self.validate_request(route_path, route_schema, body, config) {
+            self.record_validation_failure(body);
+            return error_response;
+        }
+
+        if let Some(chaos) = &config.chaos {
+            let (spike, failure) = self.roll_chaos(chaos, config.seed);
+            if spike {
+                debug!("Chaos: adding a {}ms latency spike", chaos.spike_ms);
+                tokio::time::sleep(std::time::Duration::from_millis(chaos.spike_ms)).await;
+            }
+            if let Some(status) = failure {
+                debug!("Chaos: failing {} with {}", self.path, status);
+                return HttpResponse::build(status).json(json!({
+                    "error": status.canonical_reason().unwrap_or("Error"),
+                    "details": "Failure injected by chaos mode"
+                }));
             }
         }
 
-        if let Err(error_response) = self.validate_request_body(body, route_schema) {
-            return error_response;
+        if let Some(latency) = &config.latency {
+            let delay = self
+                .with_rng(config.seed, |rng| latency.sample(rng))
+                .unwrap_or_else(|| latency.mean());
+            debug!("Applying sampled delay of {}ms", delay.as_millis());
+            tokio::time::sleep(delay).await;
+        } else if let Some(delay) = config.ttfb_delay.map(Delay::Fixed).or(config.delay) {
+            let delay = self
+                .with_rng(config.seed, |rng| delay.sample(rng))
+                .unwrap_or_else(|| delay.base());
+            debug!("Applying configured delay of {}ms", delay.as_millis());
+            tokio::time::sleep(delay).await;
+        }
+
+        if let Some(spec) = AsyncJobSpec::from_operation(route_schema) {
+            return self.start_async_job(&spec, config.seed);
+        }
+
+        if self.req.method() == actix_web::http::Method::GET {
+            if let Some(response) = self.async_job_status(route_path, route_schema, config) {
+                return response;
+            }
+        }
+
+        if config.stateful.unwrap_or(false) {
+            if let Some(response) = self
+                .stateful_response(route_path, route_schema, body, config)
+                .await
+            {
+                return response;
+            }
         }
 
-        if let Some(delay) = config.delay {
-            debug!("Applying configured delay of {}ms", delay);
-            tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
+        self.generate_response(route_path, route_schema, config)
+            .await
+    }
+
+    /// Answers create, read, update and delete requests from the `stateful`
+    /// store, or `None` for requests left to generation, such as a collection
+    /// GET whose response is not an array or a write without a JSON object.
+    async fn stateful_response(
+        &self,
+        route_path: &str,
+        route_schema: &Value,
+        body: &Option<web::Bytes>,
+        config: &MockConfig,
+    ) -> Option<HttpResponse> {
+        let request_body = body
+            .as_ref()
+            .and_then(|body| serde_json::from_slice::<Value>(body).ok())
+            .and_then(|body| match body {
+                Value::Object(fields) => Some(fields),
+                _ => None,
+            });
+        let not_found = || {
+            HttpResponse::NotFound().json(json!({
+                "error": "Entity not found",
+                "path": self.path
+            }))
+        };
+        let schema = self.success_schema(route_schema);
+        let shown = |entity: &Value| {
+            let mut entity = entity.clone();
+            if let Some(schema) = &schema {
+                self.strip_write_only(&mut entity, schema);
+            }
+            entity
+        };
+        let id_mismatch = |body_id: &str| {
+            HttpResponse::BadRequest().json(json!({
+                "error": "Entity id does not match the path",
+                "id": body_id,
+                "path": self.path
+            }))
+        };
+
+        match (
+            Resource::of(route_path, &self.path),
+            self.req.method().as_str(),
+        ) {
+            (Resource::Collection(collection), "POST") => {
+                let fields = request_body?;
+                let client_id = fields.get(ID_FIELD).is_some_and(|id| !id.is_null());
+                let mut entity = match &schema {
+                    Some(schema) => self.generate_value(route_path, schema, config).await,
+                    None => json!({}),
+                };
+                if !entity.is_object() {
+                    entity = json!({});
+                }
+                entity.as_object_mut()?.extend(fields);
+                // An optional id may have been left out: generate one of the
+                // declared type, so integer ids stay integers.
+                let id_schema = schema
+                    .as_ref()
+                    .and_then(|schema| self.swagger_state.resolve_object(schema))
+                    .and_then(|schema| schema.get("properties"))
+                    .and_then(|properties| properties.get(ID_FIELD));
+                if let (None, Some(id_schema)) = (entity.get(ID_FIELD), id_schema) {
+                    entity[ID_FIELD] = self.generate_value(route_path, id_schema, config).await;
+                }
+
+                let state = self.acquire_state_lock().ok()?;
+                let mut collections = state.collections.lock().ok()?;
+                let entities = collections.entry(collection.to_string()).or_default();
+                if !client_id {
+                    entity[ID_FIELD] = store::free_id(entities, entity.get(ID_FIELD), || {
+                        state
+                            .with_rng(config.seed, random_uuid)
+                            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+                    });
+                }
+                let id = entity_id(&entity)?;
+                if client_id && entities.contains_key(&id) {
+                    return Some(HttpResponse::Conflict().json(json!({
+                        "error": "Entity already exists",
+                        "id": id,
+                        "path": self.path
+                    })));
+                }
+                entities.insert(id.clone(), entity.clone());
+                debug!("Stored {} in {}", id, collection);
+                Some(
+                    HttpResponse::Created()
+                        .insert_header((
+                            actix_web::http::header::LOCATION,
+                            format!("{}/{}", collection, id),
+                        ))
+                        .json(shown(&entity)),
+                )
+            }
+            (Resource::Collection(collection), "GET") => {
+                if !self.is_array_schema(schema.as_ref()?) {
+                    return None;
+                }
+                let state = self.acquire_state_lock().ok()?;
+                let collections = state.collections.lock().ok()?;
+                Some(HttpResponse::Ok().json(shown(&json!(store::list(&collections, collection)))))
+            }
+            (Resource::Item(collection, id), method) => {
+                let state = self.acquire_state_lock().ok()?;
+                let mut collections = state.collections.lock().ok()?;
+                let entities = collections.entry(collection.to_string()).or_default();
+
+                match method {
+                    "GET" => Some(match entities.get(id) {
+                        Some(entity) => HttpResponse::Ok().json(shown(entity)),
+                        None => not_found(),
+                    }),
+                    "PUT" => {
+                        let mut entity = Value::Object(request_body?);
+                        if let Some(body_id) = entity_id(&entity).filter(|body_id| body_id != id) {
+                            return Some(id_mismatch(&body_id));
+                        }
+                        if entity_id(&entity).is_none() {
+                            entity[ID_FIELD] = entities
+                                .get(id)
+                                .and_then(|stored| stored.get(ID_FIELD))
+                                .cloned()
+                                .unwrap_or_else(|| json!(id));
+                        }
+                        entities.insert(id.to_string(), entity.clone());
+                        Some(HttpResponse::Ok().json(shown(&entity)))
+                    }
+                    "PATCH" => {
+                        let fields = request_body?;
+                        let body_id = fields.get(ID_FIELD).and_then(store::id_key);
+                        if let Some(body_id) = body_id.filter(|body_id| body_id != id) {
+                            return Some(id_mismatch(&body_id));
+                        }
+                        Some(match entities.get_mut(id) {
+                            Some(Value::Object(entity)) => {
+                                entity.extend(fields);
+                                HttpResponse::Ok().json(shown(&Value::Object(entity.clone())))
+                            }
+                            _ => not_found(),
+                        })
+                    }
+                    "DELETE" => Some(match entities.remove(id) {
+                        Some(_) => {
+                            debug!("Deleted {} from {}", id, collection);
+                            HttpResponse::NoContent().finish()
+                        }
+                        None => not_found(),
+                    }),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Removes the properties `schema` marks `writeOnly` from `value`, at
+    /// any depth: they are accepted in requests but never answered with.
+    fn strip_write_only(&self, value: &mut Value, schema: &Value) {
+        let Some(schema) = self.swagger_state.resolve_object(schema) else {
+            return;
+        };
+        match value {
+            Value::Object(object) => {
+                let properties = schema.get("properties").and_then(Value::as_object);
+                for (key, property) in properties.into_iter().flatten() {
+                    let write_only = self
+                        .swagger_state
+                        .resolve_object(property)
+                        .into_iter()
+                        .chain([property])
+                        .any(|schema| schema.get("writeOnly") == Some(&Value::Bool(true)));
+                    if write_only {
+                        object.remove(key);
+                    } else if let Some(child) = object.get_mut(key) {
+                        self.strip_write_only(child, property);
+                    }
+                }
+            }
+            Value::Array(items) => {
+                if let Some(item_schema) = schema.get("items") {
+                    for item in items {
+                        self.strip_write_only(item, item_schema);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// JSON schema of the response the operation answers with by default.
+    fn success_schema(&self, operation: &Value) -> Option<Value> {
+        let response = operation
+            .get("responses")?
+            .get(default_status(operation).to_string())?;
+        self.swagger_state
+            .resolve_object(response)?
+            .pointer("/content/application~1json/schema")
+            .cloned()
+    }
+
+    /// Draws whether this request gets a latency spike and which status it
+    /// fails with, if any, from the shared chaos generator.
+    fn roll_chaos(&self, chaos: &ChaosConfig, seed: Option<u64>) -> (bool, Option<StatusCode>) {
+        self.with_rng(seed, |rng| {
+            let spike = rng.gen_bool(chaos.spike_rate.clamp(0.0, 1.0));
+            let failure = rng
+                .gen_bool(chaos.failure_rate.clamp(0.0, 1.0))
+                .then(|| {
+                    chaos
+                        .statuses
+                        .get(rng.gen_range(0..chaos.statuses.len().max(1)))
+                })
+                .flatten()
+                .and_then(|status| StatusCode::from_u16(*status).ok());
+            (spike, failure)
+        })
+        .unwrap_or((false, None))
+    }
+
+    /// Runs `draw` with the shared generator behind `chaos`, `latency`, delay
+    /// jitter and random ids, seeding it from `seed` on first use.
+    fn with_rng<T>(&self, seed: Option<u64>, draw: impl FnOnce(&mut StdRng) -> T) -> Option<T> {
+        self.acquire_state_lock().ok()?.with_rng(seed, draw)
+    }
+
+    /// Registers a job and answers 202 with its status route in `Location`.
+    fn start_async_job(&self, spec: &AsyncJobSpec, seed: Option<u64>) -> HttpResponse {
+        let id = self
+            .with_rng(seed, random_uuid)
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        let (job, location) = spec.start(&id);
+
+        match self.acquire_state_lock() {
+            Ok(state) => {
+                if let Ok(mut jobs) = state.jobs.lock() {
+                    jobs.retain(|_, job| !job.is_expired());
+                    jobs.insert(id.clone(), job);
+                }
+            }
+            Err(response) => return response,
+        }
+
+        debug!("Started async job {} polled at {}", id, location);
+        HttpResponse::Accepted()
+            .insert_header((actix_web::http::header::LOCATION, location))
+            .json(json!({ "id": id, "status": "pending" }))
+    }
+
+    /// Answers a poll of an `x-spit-async` status route with the job's
+    /// current status, or `None` when `route_path` is not a status route.
+    fn async_job_status(
+        &self,
+        route_path: &str,
+        route_schema: &Value,
+        config: &MockConfig,
+    ) -> Option<HttpResponse> {
+        let job = {
+            let state = self.acquire_state_lock().ok()?;
+            if !state.status_routes.contains(route_path) {
+                return None;
+            }
+            let id = job_id(route_path, &self.path)?;
+            let jobs = state.jobs.lock().ok()?;
+            jobs.get(id).cloned().map(|job| (id.to_string(), job))
+        };
+
+        let (id, job) = match job {
+            Some(job) => job,
+            None => {
+                return Some(HttpResponse::NotFound().json(json!({
+                    "error": "Job not found",
+                    "path": self.path
+                })))
+            }
+        };
+
+        let mut body = route_schema
+            .pointer("/responses/200/content/application~1json/schema")
+            .map(|schema| {
+                MockGenerator::new(&self.swagger_state, config)
+                    .seeded_for(&self.seed_key())
+                    .counting(self.sequences())
+                    .generate_mock_value(schema, None)
+            })
+            .filter(Value::is_object)
+            .unwrap_or_else(|| json!({}));
+        body["id"] = json!(id);
+        body["status"] = json!(job.status());
+
+        Some(HttpResponse::Ok().json(body))
+    }
+
+    fn validate_request(
+        &self,
+        route_path: &str,
+        route_schema: &Value,
+        body: &Option<web::Bytes>,
+        config: &MockConfig,
+    ) -> Result<(), HttpResponse> {
+        if let Some(parameters) = route_schema.get("parameters").and_then(Value::as_array) {
+            let parameters = Value::Array(
+                parameters
+                    .iter()
+                    .filter_map(|param| self.swagger_state.resolve_object(param))
+                    .cloned()
+                    .collect(),
+            );
+            self.validate_path_values(route_path, &parameters)?;
+            self.validate_headers(&parameters)?;
+            self.validate_query_params(&parameters)?;
+            self.validate_scalar_parameters(&parameters, config)?;
         }
 
-        self.generate_response(route_schema, config)
+        self.validate_request_body(body, route_schema, config)
+    }
+
+    /// Counts a request rejected by validation towards
+    /// `fail_on_validation_error`, if that gate is enabled.
+    fn record_validation_failure(&self, body: &Option<web::Bytes>) {
+        let gate = match self.req.app_data::<web::Data<ValidationGate>>() {
+            Some(gate) => gate,
+            None => return,
+        };
+
+        error!(
+            "Request failed validation: {} {} body={}",
+            self.req.method(),
+            self.path,
+            body.as_ref()
+                .map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+                .unwrap_or_default()
+        );
+        gate.record_failure();
     }
 
     fn validate_headers(&self, parameters: &Value) -> Result<(), HttpResponse> {
@@ -181,238 +782,1055 @@ impl RequestHandler {
         Ok(())
     }
 
-    fn validate_request_body(
+    /// Checks each `{param}` value of the request path against the schema of
+    /// its `in: path` parameter, e.g. that an integer id got digits.
+    fn validate_path_values(
         &self,
-        body: &Option<web::Bytes>,
-        schema: &Value,
+        route_path: &str,
+        parameters: &Value,
     ) -> Result<(), HttpResponse> {
-        let request_body = match schema.get("requestBody") {
-            Some(body) => body,
-            None => return Ok(()),
-        };
-
-        let body_schema = match request_body
-            .get("content")
-            .and_then(|content| content.get("application/json"))
-            .and_then(|json| json.get("schema"))
-        {
-            Some(schema) => schema,
-            None => return Ok(()),
-        };
+        let invalid_parameters: Vec<String> = extract_path_params(route_path, &self.path)
+            .into_iter()
+            .filter(|(name, raw)| {
+                let schema = parameters
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .find_map(|param| {
+                        let is_path = param.get("in").and_then(Value::as_str) == Some("path");
+                        let named =
+                            param.get("name").and_then(Value::as_str) == Some(name.as_str());
+                        (is_path && named).then(|| param.get("schema")).flatten()
+                    });
+                schema.is_some_and(|schema| {
+                    !self.coerce_scalar(raw, schema).is_some_and(|value| {
+                        self.matches_schema(&value, schema, Direction::Request)
+                    })
+                })
+            })
+            .map(|(name, _)| name)
+            .collect();
 
-        if body.is_none()
-            && request_body
-                .get("required")
-                .and_then(|required| required.as_bool())
-                .unwrap_or(false)
-        {
-            return Err(HttpResponse::BadRequest().json(json!({
-                "error": "Missing required request body"
-            })));
+        if invalid_parameters.is_empty() {
+            return Ok(());
         }
+        debug!("Invalid path parameters: {:?}", invalid_parameters);
+        Err(HttpResponse::BadRequest().json(json!({
+            "error": "Invalid path parameters",
+            "invalid_parameters": invalid_parameters
+        })))
+    }
 
-        if let Some(body_bytes) = body {
-            let body_value = match serde_json::from_slice::<Value>(body_bytes) {
-                Ok(value) => value,
-                Err(e) => {
-                    return Err(HttpResponse::BadRequest().json(json!({
-                        "error": "Invalid JSON in request body",
-                        "details": e.to_string()
-                    })));
-                }
+    /// Checks that required query parameters are present and that every
+    /// query value given fits its parameter's `schema`.
+    fn validate_query_params(&self, parameters: &Value) -> Result<(), HttpResponse> {
+        let query: Vec<(String, String)> =
+            serde_urlencoded::from_str(self.req.query_string()).unwrap_or_default();
+
+        let mut missing_parameters = Vec::new();
+        let mut invalid_parameters = Vec::new();
+        for param in parameters.as_array().into_iter().flatten() {
+            if param.get("in").and_then(Value::as_str) != Some("query") {
+                continue;
+            }
+            let name = match param.get("name").and_then(Value::as_str) {
+                Some(name) => name,
+                None => continue,
             };
 
-            self.validate_against_schema(&body_value, body_schema)?;
-        }
-
-        Ok(())
-    }
-
-    fn validate_against_schema(&self, value: &Value, schema: &Value) -> Result<(), HttpResponse> {
-        if let Some(ref_path) = schema.get("$ref").and_then(Value::as_str) {
-            if let Some(resolved_schema) = self.swagger_state.resolve_ref(ref_path) {
-                return self.validate_against_schema(value, &resolved_schema);
+            let raw = query
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.as_str());
+            match (raw, param.get("schema")) {
+                (None, _) if param.get("required") == Some(&json!(true)) => {
+                    missing_parameters.push(name)
+                }
+                (Some(raw), Some(schema)) => {
+                    let valid = self.coerce_scalar(raw, schema).is_some_and(|value| {
+                        self.matches_schema(&value, schema, Direction::Request)
+                    });
+                    if !valid {
+                        invalid_parameters.push(name);
+                    }
+                }
+                _ => {}
             }
         }
 
-        match schema.get("type").and_then(Value::as_str) {
-            Some("object") => self.validate_object(value, schema),
-            Some("array") => self.validate_array(value, schema),
-            Some("string") => self.validate_string(value, schema),
-            Some("number") | Some("integer") => self.validate_number(value, schema),
-            Some("boolean") => self.validate_boolean(value),
-            _ => Ok(()),
+        if !missing_parameters.is_empty() {
+            debug!(
+                "Missing required query parameters: {:?}",
+                missing_parameters
+            );
+            return Err(HttpResponse::BadRequest().json(json!({
+                "error": "Missing required query parameters",
+                "missing_parameters": missing_parameters
+            })));
         }
-    }
-
-    fn validate_object(&self, value: &Value, schema: &Value) -> Result<(), HttpResponse> {
-        if !value.is_object() {
+        if !invalid_parameters.is_empty() {
+            debug!("Invalid query parameters: {:?}", invalid_parameters);
             return Err(HttpResponse::BadRequest().json(json!({
-                "error": "Expected object type"
+                "error": "Invalid query parameters",
+                "invalid_parameters": invalid_parameters
             })));
         }
 
-        let obj = value.as_object().unwrap();
-
-        if let Some(required) = schema.get("required").and_then(Value::as_array) {
-            let missing_fields: Vec<String> = required
-                .iter()
-                .filter_map(Value::as_str)
-                .filter(|&field| !obj.contains_key(field))
-                .map(String::from)
-                .collect();
+        Ok(())
+    }
 
-            if !missing_fields.is_empty() {
-                return Err(HttpResponse::BadRequest().json(json!({
-                    "error": "Missing required fields",
-                    "fields": missing_fields
-                })));
+    /// Validates header parameters that declare a `schema`, coercing their
+    /// string values to the schema's scalar type first.
+    fn validate_scalar_parameters(
+        &self,
+        parameters: &Value,
+        config: &MockConfig,
+    ) -> Result<(), HttpResponse> {
+        for param in parameters.as_array().into_iter().flatten() {
+            if param.get("in").and_then(Value::as_str) != Some("header") {
+                continue;
             }
-        }
+            let (name, schema) = match (
+                param.get("name").and_then(Value::as_str),
+                param.get("schema"),
+            ) {
+                (Some(name), Some(schema)) => (name, schema),
+                _ => continue,
+            };
 
-        if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
-            for (prop_name, prop_schema) in properties {
-                if let Some(prop_value) = obj.get(prop_name) {
-                    self.validate_against_schema(prop_value, prop_schema)?;
-                }
+            let raw = self
+                .req
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok());
+            if let Some(raw) = raw {
+                self.validate_coerced(name, raw, schema, config)?;
             }
         }
 
         Ok(())
     }
 
-    fn validate_array(&self, value: &Value, schema: &Value) -> Result<(), HttpResponse> {
-        if !value.is_array() {
-            return Err(HttpResponse::BadRequest().json(json!({
-                "error": "Expected array type"
-            })));
-        }
+    fn validate_coerced(
+        &self,
+        name: &str,
+        raw: &str,
+        schema: &Value,
+        config: &MockConfig,
+    ) -> Result<(), HttpResponse> {
+        let value = self.coerce_scalar(raw, schema).ok_or_else(|| {
+            HttpResponse::BadRequest().json(json!({
+                "error": "Invalid parameter value",
+                "parameter": name,
+                "value": raw
+            }))
+        })?;
 
-        let arr = value.as_array().unwrap();
+        self.validate_against_schema(&value, schema, config)
+    }
 
-        if let Some(min_items) = schema.get("minItems").and_then(Value::as_u64) {
-            if (arr.len() as u64) < min_items {
-                return Err(HttpResponse::BadRequest().json(json!({
-                    "error": "Array too short",
-                    "minItems": min_items,
-                    "actual": arr.len()
-                })));
+    /// Interprets a string-encoded value per the schema's type: numbers and
+    /// booleans are parsed, arrays split on commas. `None` when it can't be.
+    fn coerce_scalar(&self, raw: &str, schema: &Value) -> Option<Value> {
+        if let Some(ref_path) = schema.get("$ref").and_then(Value::as_str) {
+            if let Some(resolved_schema) = self.swagger_state.resolve_ref(ref_path) {
+                return self.coerce_scalar(raw, &resolved_schema);
             }
         }
 
-        if let Some(max_items) = schema.get("maxItems").and_then(Value::as_u64) {
-            if (arr.len() as u64) > max_items {
-                return Err(HttpResponse::BadRequest().json(json!({
-                    "error": "Array too long",
-                    "maxItems": max_items,
-                    "actual": arr.len()
-                })));
-            }
-        }
+        let types: Vec<&str> = match schema.get("type") {
+            Some(Value::String(type_name)) => vec![type_name.as_str()],
+            Some(Value::Array(types)) => types.iter().filter_map(Value::as_str).collect(),
+            _ => vec!["string"],
+        };
 
-        if let Some(items_schema) = schema.get("items") {
-            for item in arr {
-                self.validate_against_schema(item, items_schema)?;
+        types.into_iter().find_map(|type_name| match type_name {
+            "integer" => raw.trim().parse::<i64>().ok().map(Value::from),
+            "number" => raw
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|n| n.is_finite())
+                .map(Value::from),
+            "boolean" => match raw.trim() {
+                "true" => Some(json!(true)),
+                "false" => Some(json!(false)),
+                _ => None,
+            },
+            "null" if raw.is_empty() => Some(Value::Null),
+            "array" => {
+                let items = schema.get("items").cloned().unwrap_or(json!({}));
+                raw.split(',')
+                    .filter(|item| !item.is_empty())
+                    .map(|item| self.coerce_scalar(item, &items))
+                    .collect::<Option<Vec<_>>>()
+                    .map(Value::Array)
             }
-        }
-
-        Ok(())
+            "string" => Some(Value::String(raw.to_string())),
+            _ => None,
+        })
     }
 
-    fn validate_string(&self, value: &Value, schema: &Value) -> Result<(), HttpResponse> {
-        if !value.is_string() {
-            return Err(HttpResponse::BadRequest().json(json!({
-                "error": "Expected string type"
-            })));
+    /// Checks the fields of an `application/x-www-form-urlencoded` or
+    /// `multipart/form-data` body, coercing each value per its property schema.
+    fn validate_form_fields(
+        &self,
+        fields: Vec<(String, String)>,
+        schema: &Value,
+        config: &MockConfig,
+    ) -> Result<(), HttpResponse> {
+        let schema = match schema.get("$ref").and_then(Value::as_str) {
+            Some(ref_path) => self
+                .swagger_state
+                .resolve_ref(ref_path)
+                .unwrap_or_else(|| schema.clone()),
+            None => schema.clone(),
+        };
+        let properties = schema.get("properties").and_then(Value::as_object);
+
+        let mut form = serde_json::Map::new();
+        for (name, raw) in &fields {
+            let value = match properties.and_then(|props| props.get(name)) {
+                Some(prop_schema) => self.coerce_scalar(raw, prop_schema).ok_or_else(|| {
+                    HttpResponse::BadRequest().json(json!({
+                        "error": "Invalid form field value",
+                        "field": name,
+                        "value": raw
+                    }))
+                })?,
+                None => Value::String(raw.clone()),
+            };
+            form.insert(name.clone(), value);
         }
 
-        let s = value.as_str().unwrap();
+        self.validate_against_schema(&Value::Object(form), &schema, config)
+    }
 
-        if let Some(min_length) = schema.get("minLength").and_then(Value::as_u64) {
-            if (s.len() as u64) < min_length {
-                return Err(HttpResponse::BadRequest().json(json!({
-                    "error": "String too short",
-                    "minLength": min_length,
-                    "actual": s.len()
-                })));
-            }
-        }
+    fn validate_request_body(
+        &self,
+        body: &Option<web::Bytes>,
+        schema: &Value,
+        config: &MockConfig,
+    ) -> Result<(), HttpResponse> {
+        let request_body = match schema
+            .get("requestBody")
+            .and_then(|body| self.swagger_state.resolve_object(body))
+        {
+            Some(body) => body,
+            None => return Ok(()),
+        };
 
-        if let Some(max_length) = schema.get("maxLength").and_then(Value::as_u64) {
-            if (s.len() as u64) > max_length {
-                return Err(HttpResponse::BadRequest().json(json!({
-                    "error": "String too long",
-                    "maxLength": max_length,
-                    "actual": s.len()
-                })));
+        let body = body.as_ref().filter(|bytes| !bytes.is_empty());
+
+        if body.is_none()
+            && request_body
+                .get("required")
+                .and_then(|required| required.as_bool())
+                .unwrap_or(false)
+        {
+            return Err(HttpResponse::BadRequest().json(json!({
+                "error": "Missing required request body"
+            })));
+        }
+
+        let (Some(body_bytes), Some(content)) = (
+            body,
+            request_body
+                .get("content")
+                .and_then(Value::as_object)
+                .filter(|content| !content.is_empty()),
+        ) else {
+            return Ok(());
+        };
+
+        let content_type = self
+            .req
+            .headers()
+            .get(actix_web::http::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok());
+        let media_type = match content_type {
+            Some(content_type) => match find_media_type(content, content_type) {
+                Some(media_type) => media_type,
+                None => {
+                    return Err(HttpResponse::UnsupportedMediaType().json(json!({
+                        "error": "Unsupported media type",
+                        "content_type": content_type,
+                        "supported": content.keys().collect::<Vec<_>>()
+                    })))
+                }
+            },
+            // Bodies sent without a Content-Type were always read as JSON.
+            None => match content.get("application/json") {
+                Some(media_type) => media_type,
+                None => return Ok(()),
+            },
+        };
+        let Some(body_schema) = media_type.get("schema") else {
+            return Ok(());
+        };
+
+        let essence = content_type
+            .map(media_type_essence)
+            .unwrap_or_else(|| "application/json".to_string());
+        match essence.as_str() {
+            FORM_CONTENT_TYPE => {
+                let fields = serde_urlencoded::from_bytes(body_bytes).map_err(|e| {
+                    HttpResponse::BadRequest().json(json!({
+                        "error": "Invalid form body",
+                        "details": e.to_string()
+                    }))
+                })?;
+                self.validate_form_fields(fields, body_schema, config)
+            }
+            MULTIPART_CONTENT_TYPE => {
+                let fields = content_type
+                    .and_then(|content_type| media_type_param(content_type, "boundary"))
+                    .and_then(|boundary| multipart_fields(body_bytes, boundary))
+                    .ok_or_else(|| {
+                        HttpResponse::BadRequest().json(json!({
+                            "error": "Invalid multipart body",
+                            "details": "Parts are not delimited by the Content-Type boundary"
+                        }))
+                    })?;
+                self.validate_form_fields(fields, body_schema, config)
+            }
+            essence if essence == "application/json" || essence.ends_with("+json") => {
+                let body_value = serde_json::from_slice::<Value>(body_bytes).map_err(|e| {
+                    HttpResponse::BadRequest().json(json!({
+                        "error": "Invalid JSON in request body",
+                        "details": e.to_string()
+                    }))
+                })?;
+                self.validate_against_schema(&body_value, body_schema, config)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks `value` against `schema`, answering with the first violation or,
+    /// with `aggregate_errors`, with all of them in an `errors` array.
+    fn validate_against_schema(
+        &self,
+        value: &Value,
+        schema: &Value,
+        config: &MockConfig,
+    ) -> Result<(), HttpResponse> {
+        let mut violations = Vec::new();
+        self.check_schema(value, schema, "", &mut violations, Direction::Request);
+
+        if violations.is_empty() {
+            return Ok(());
+        }
+
+        if !config.aggregate_errors.unwrap_or(false) {
+            let first = violations.swap_remove(0);
+            return Err(HttpResponse::build(first.status).json(first.details));
+        }
+
+        let status = violations
+            .iter()
+            .map(|violation| violation.status)
+            .max()
+            .unwrap_or(StatusCode::BAD_REQUEST);
+        let errors: Vec<Value> = violations.into_iter().map(Violation::into_error).collect();
+
+        Err(HttpResponse::build(status).json(json!({
+            "error": "Request validation failed",
+            "errors": errors
+        })))
+    }
+
+    fn matches_schema(&self, value: &Value, schema: &Value, direction: Direction) -> bool {
+        let mut violations = Vec::new();
+        self.check_schema(value, schema, "", &mut violations, direction);
+        violations.is_empty()
+    }
+
+    fn check_schema(
+        &self,
+        value: &Value,
+        schema: &Value,
+        pointer: &str,
+        violations: &mut Vec<Violation>,
+        direction: Direction,
+    ) {
+        if let Some(ref_path) = schema.get("$ref").and_then(Value::as_str) {
+            if let Some(resolved_schema) = self.swagger_state.resolve_ref(ref_path) {
+                return self.check_schema(value, &resolved_schema, pointer, violations, direction);
             }
         }
 
-        if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
-            let regex = Regex::new(pattern).map_err(|_| {
-                HttpResponse::InternalServerError().json(json!({
-                    "error": "Invalid pattern in schema"
-                }))
-            })?;
+        // OpenAPI 3.0 spells `type: [..., "null"]` as `nullable: true`.
+        if value.is_null() && schema.get("nullable").and_then(Value::as_bool) == Some(true) {
+            return;
+        }
 
-            if !regex.is_match(s) {
-                return Err(HttpResponse::BadRequest().json(json!({
-                    "error": "String does not match pattern",
-                    "pattern": pattern
-                })));
+        if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+            if !allowed.contains(value) {
+                violations.push(Violation::new(
+                    pointer,
+                    json!({
+                        "error": "Value not allowed",
+                        "allowed": allowed,
+                        "actual": value
+                    }),
+                ));
             }
         }
 
-        Ok(())
+        if let Some(parts) = schema.get("allOf").and_then(Value::as_array) {
+            for part in parts {
+                self.check_schema(value, part, pointer, violations, direction);
+            }
+        }
+        if let Some(variants) = schema.get("anyOf").and_then(Value::as_array) {
+            if !variants
+                .iter()
+                .any(|variant| self.matches_schema(value, variant, direction))
+            {
+                violations.push(Violation::new(
+                    pointer,
+                    json!({ "error": "Value does not match any schema in anyOf" }),
+                ));
+            }
+        }
+        if let Some(variants) = schema.get("oneOf").and_then(Value::as_array) {
+            self.check_one_of(value, schema, variants, pointer, violations, direction);
+        }
+
+        match schema.get("type") {
+            Some(Value::String(type_name)) => {
+                self.check_type(value, schema, type_name, pointer, violations, direction)
+            }
+            Some(Value::Array(types)) => {
+                let matches_any = types.iter().filter_map(Value::as_str).any(|type_name| {
+                    let mut type_violations = Vec::new();
+                    self.check_type(
+                        value,
+                        schema,
+                        type_name,
+                        pointer,
+                        &mut type_violations,
+                        direction,
+                    );
+                    type_violations.is_empty()
+                });
+
+                if !matches_any {
+                    violations.push(Violation::new(
+                        pointer,
+                        json!({
+                            "error": "Value does not match any of the allowed types",
+                            "types": types
+                        }),
+                    ));
+                }
+            }
+            _ => {}
+        }
     }
 
-    fn validate_number(&self, value: &Value, schema: &Value) -> Result<(), HttpResponse> {
-        if !value.is_number() {
-            return Err(HttpResponse::BadRequest().json(json!({
-                "error": "Expected numeric type"
-            })));
+    /// A discriminated `oneOf` validates against the variant the
+    /// discriminator value selects; otherwise exactly one variant must match.
+    fn check_one_of(
+        &self,
+        value: &Value,
+        schema: &Value,
+        variants: &[Value],
+        pointer: &str,
+        violations: &mut Vec<Violation>,
+        direction: Direction,
+    ) {
+        let property = schema
+            .pointer("/discriminator/propertyName")
+            .and_then(Value::as_str);
+        let tag = property.and_then(|property| value.get(property));
+        let choices = schema
+            .as_object()
+            .map(|schema| discriminator_choices(schema, variants))
+            .unwrap_or_default();
+        if let (Some(property), Some(tag), false) = (property, tag, choices.is_empty()) {
+            match choices
+                .iter()
+                .find(|(choice, _)| tag.as_str() == Some(choice.as_str()))
+            {
+                Some((_, variant)) => {
+                    self.check_schema(value, variant, pointer, violations, direction)
+                }
+                None => violations.push(Violation::new(
+                    &child_pointer(pointer, property),
+                    json!({
+                        "error": "Unknown discriminator value",
+                        "allowed": choices.iter().map(|(choice, _)| choice).collect::<Vec<_>>(),
+                        "actual": tag
+                    }),
+                )),
+            }
+            return;
         }
 
-        let num = value.as_f64().unwrap();
+        let matching = variants
+            .iter()
+            .filter(|variant| self.matches_schema(value, variant, direction))
+            .count();
+        if matching != 1 {
+            violations.push(Violation::new(
+                pointer,
+                json!({
+                    "error": "Value must match exactly one schema in oneOf",
+                    "matching": matching
+                }),
+            ));
+        }
+    }
+
+    /// Whether a property schema (or the schema it references) is `readOnly`
+    /// in requests or `writeOnly` in responses.
+    fn is_exempt(&self, prop_schema: &Value, direction: Direction) -> bool {
+        self.swagger_state
+            .resolve_object(prop_schema)
+            .into_iter()
+            .chain([prop_schema])
+            .any(|schema| {
+                schema
+                    .get(direction.exempting_keyword())
+                    .and_then(Value::as_bool)
+                    == Some(true)
+            })
+    }
 
-        if let Some(minimum) = schema.get("minimum").and_then(Value::as_f64) {
-            if num < minimum {
-                return Err(HttpResponse::BadRequest().json(json!({
-                    "error": "Number too small",
-                    "minimum": minimum,
-                    "actual": num
-                })));
+    fn check_type(
+        &self,
+        value: &Value,
+        schema: &Value,
+        type_name: &str,
+        pointer: &str,
+        violations: &mut Vec<Violation>,
+        direction: Direction,
+    ) {
+        match type_name {
+            "object" => self.check_object(value, schema, pointer, violations, direction),
+            "array" => self.check_array(value, schema, pointer, violations, direction),
+            "string" => self.check_string(value, schema, pointer, violations),
+            "integer" if value.as_f64().is_some_and(|n| n.fract() != 0.0) => violations.push(
+                Violation::new(pointer, json!({ "error": "Expected integer type" })),
+            ),
+            "number" | "integer" => self.check_number(value, schema, pointer, violations),
+            "boolean" if !value.is_boolean() => violations.push(Violation::new(
+                pointer,
+                json!({ "error": "Expected boolean type" }),
+            )),
+            "null" if !value.is_null() => {
+                violations.push(Violation::new(pointer, json!({ "error": "Expected null" })))
             }
+            _ => {}
         }
+    }
 
-        if let Some(maximum) = schema.get("maximum").and_then(Value::as_f64) {
-            if num > maximum {
-                return Err(HttpResponse::BadRequest().json(json!({
-                    "error": "Number too large",
-                    "maximum": maximum,
-                    "actual": num
-                })));
+    fn check_object(
+        &self,
+        value: &Value,
+        schema: &Value,
+        pointer: &str,
+        violations: &mut Vec<Violation>,
+        direction: Direction,
+    ) {
+        let obj = match value.as_object() {
+            Some(obj) => obj,
+            None => {
+                return violations.push(Violation::new(
+                    pointer,
+                    json!({ "error": "Expected object type" }),
+                ))
             }
+        };
+
+        // Checked before any per-property work so oversized objects cost no
+        // more than counting their keys.
+        let max_properties = schema.get("maxProperties").and_then(Value::as_u64);
+        if let Some(max) = max_properties.filter(|&max| obj.len() as u64 > max) {
+            return violations.push(Violation::new(
+                pointer,
+                json!({
+                    "error": "Too many properties",
+                    "maxProperties": max,
+                    "actual": obj.len()
+                }),
+            ));
+        }
+        if obj.len() > MAX_VALIDATED_KEYS {
+            debug!(
+                "Validating only the first {} of {} keys at {}",
+                MAX_VALIDATED_KEYS,
+                obj.len(),
+                pointer
+            );
         }
 
-        Ok(())
+        let properties = schema.get("properties").and_then(Value::as_object);
+        let mut null_fields: Vec<&str> = Vec::new();
+        if let Some(required) = schema.get("required").and_then(Value::as_array) {
+            let missing_fields: Vec<String> = required
+                .iter()
+                .filter_map(Value::as_str)
+                .filter(|&field| !obj.contains_key(field))
+                .filter(|&field| {
+                    !properties
+                        .and_then(|properties| properties.get(field))
+                        .is_some_and(|prop_schema| self.is_exempt(prop_schema, direction))
+                })
+                .map(String::from)
+                .collect();
+
+            if !missing_fields.is_empty() {
+                violations.push(Violation::new(
+                    pointer,
+                    json!({
+                        "error": "Missing required fields",
+                        "fields": missing_fields
+                    }),
+                ));
+            }
+
+            // A required field sent as `null` is as good as missing unless
+            // its schema allows null.
+            null_fields = required
+                .iter()
+                .filter_map(Value::as_str)
+                .filter(|&field| obj.get(field).is_some_and(Value::is_null))
+                .filter(|&field| {
+                    properties
+                        .and_then(|properties| properties.get(field))
+                        .is_some_and(|prop_schema| {
+                            !self.matches_schema(&Value::Null, prop_schema, direction)
+                        })
+                })
+                .collect();
+            if !null_fields.is_empty() {
+                violations.push(Violation::new(
+                    pointer,
+                    json!({
+                        "error": "Required fields are null",
+                        "fields": null_fields
+                    }),
+                ));
+            }
+        }
+
+        if let Some(properties) = properties {
+            for (prop_name, prop_schema) in properties {
+                if null_fields.contains(&prop_name.as_str()) {
+                    continue;
+                }
+                if let Some(prop_value) = obj.get(prop_name) {
+                    let prop_pointer = child_pointer(pointer, prop_name);
+                    self.check_schema(
+                        prop_value,
+                        prop_schema,
+                        &prop_pointer,
+                        violations,
+                        direction,
+                    );
+                }
+            }
+        }
+
+        if let Some(patterns) = schema.get("patternProperties").and_then(Value::as_object) {
+            for (pattern, prop_schema) in patterns {
+                let regex = match self.swagger_state.regex(pattern) {
+                    Ok(regex) => regex,
+                    Err(_) => {
+                        violations.push(Violation::schema_error(
+                            pointer,
+                            json!({
+                                "error": "Invalid patternProperties pattern in schema",
+                                "pattern": pattern
+                            }),
+                        ));
+                        continue;
+                    }
+                };
+
+                for (prop_name, prop_value) in obj.iter().take(MAX_VALIDATED_KEYS) {
+                    if regex.is_match(prop_name) {
+                        let prop_pointer = child_pointer(pointer, prop_name);
+                        self.check_schema(
+                            prop_value,
+                            prop_schema,
+                            &prop_pointer,
+                            violations,
+                            direction,
+                        );
+                    }
+                }
+            }
+        }
+
+        match schema.get("additionalProperties") {
+            Some(Value::Bool(false)) => {
+                let unexpected: Vec<&String> = obj
+                    .keys()
+                    .take(MAX_VALIDATED_KEYS)
+                    .filter(|key| !self.is_declared_property(schema, key))
+                    .collect();
+                if !unexpected.is_empty() {
+                    violations.push(Violation::new(
+                        pointer,
+                        json!({
+                            "error": "Unexpected properties",
+                            "fields": unexpected
+                        }),
+                    ));
+                }
+            }
+            Some(extra_schema @ Value::Object(_)) => {
+                for (prop_name, prop_value) in obj.iter().take(MAX_VALIDATED_KEYS) {
+                    if !self.is_declared_property(schema, prop_name) {
+                        let prop_pointer = child_pointer(pointer, prop_name);
+                        self.check_schema(
+                            prop_value,
+                            extra_schema,
+                            &prop_pointer,
+                            violations,
+                            direction,
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
     }
 
-    fn validate_boolean(&self, value: &Value) -> Result<(), HttpResponse> {
-        if !value.is_boolean() {
-            return Err(HttpResponse::BadRequest().json(json!({
-                "error": "Expected boolean type"
-            })));
+    /// Whether `key` is covered by the object schema's `properties` or
+    /// `patternProperties`, i.e. is not subject to `additionalProperties`.
+    fn is_declared_property(&self, schema: &Value, key: &str) -> bool {
+        let in_properties = schema
+            .get("properties")
+            .and_then(Value::as_object)
+            .is_some_and(|properties| properties.contains_key(key));
+        let matches_pattern = schema
+            .get("patternProperties")
+            .and_then(Value::as_object)
+            .is_some_and(|patterns| {
+                patterns.keys().any(|pattern| {
+                    self.swagger_state
+                        .regex(pattern)
+                        .is_ok_and(|regex| regex.is_match(key))
+                })
+            });
+        in_properties || matches_pattern
+    }
+
+    fn check_array(
+        &self,
+        value: &Value,
+        schema: &Value,
+        pointer: &str,
+        violations: &mut Vec<Violation>,
+        direction: Direction,
+    ) {
+        let arr = match value.as_array() {
+            Some(arr) => arr,
+            None => {
+                return violations.push(Violation::new(
+                    pointer,
+                    json!({ "error": "Expected array type" }),
+                ))
+            }
+        };
+
+        if let Some(min_items) = schema.get("minItems").and_then(Value::as_u64) {
+            if (arr.len() as u64) < min_items {
+                violations.push(Violation::new(
+                    pointer,
+                    json!({
+                        "error": "Array too short",
+                        "minItems": min_items,
+                        "actual": arr.len()
+                    }),
+                ));
+            }
+        }
+
+        if let Some(max_items) = schema.get("maxItems").and_then(Value::as_u64) {
+            if (arr.len() as u64) > max_items {
+                violations.push(Violation::new(
+                    pointer,
+                    json!({
+                        "error": "Array too long",
+                        "maxItems": max_items,
+                        "actual": arr.len()
+                    }),
+                ));
+            }
+        }
+
+        let (prefix_items, rest_items) = schema.as_object().map(tuple_items).unwrap_or((&[], None));
+
+        for (index, (item, item_schema)) in arr.iter().zip(prefix_items).enumerate() {
+            let item_pointer = format!("{}/{}", pointer, index);
+            self.check_schema(item, item_schema, &item_pointer, violations, direction);
+        }
+
+        match rest_items {
+            Some(Value::Bool(false)) if arr.len() > prefix_items.len() => {
+                violations.push(Violation::new(
+                    pointer,
+                    json!({
+                        "error": "Unexpected items after tuple positions",
+                        "maxItems": prefix_items.len(),
+                        "actual": arr.len()
+                    }),
+                ));
+            }
+            Some(items_schema) if items_schema.is_object() => {
+                for (index, item) in arr.iter().enumerate().skip(prefix_items.len()) {
+                    let item_pointer = format!("{}/{}", pointer, index);
+                    self.check_schema(item, items_schema, &item_pointer, violations, direction);
+                }
+            }
+            _ => {}
+        }
+
+        if let Some(contains_schema) = schema.get("contains") {
+            self.check_contains(arr, schema, contains_schema, pointer, violations, direction);
         }
-        Ok(())
     }
 
-    fn generate_response(&self, schema: &Value, config: &MockConfig) -> HttpResponse {
-        let status_code = config.status_code.unwrap_or(200).try_into().unwrap_or(200);
-        let mut response_builder = HttpResponse::build(
-            actix_web::http::StatusCode::from_u16(status_code)
-                .unwrap_or(actix_web::http::StatusCode::OK),
-        );
+    /// Enforces `contains`: between `minContains` (default 1) and
+    /// `maxContains` items must match the subschema.
+    fn check_contains(
+        &self,
+        arr: &[Value],
+        schema: &Value,
+        contains_schema: &Value,
+        pointer: &str,
+        violations: &mut Vec<Violation>,
+        direction: Direction,
+    ) {
+        let matching = arr
+            .iter()
+            .filter(|item| self.matches_schema(item, contains_schema, direction))
+            .count() as u64;
+
+        let min_contains = schema
+            .get("minContains")
+            .and_then(Value::as_u64)
+            .unwrap_or(1);
+        if matching < min_contains {
+            violations.push(Violation::new(
+                pointer,
+                json!({
+                    "error": "Too few items match contains",
+                    "minContains": min_contains,
+                    "actual": matching
+                }),
+            ));
+        }
+
+        if let Some(max_contains) = schema.get("maxContains").and_then(Value::as_u64) {
+            if matching > max_contains {
+                violations.push(Violation::new(
+                    pointer,
+                    json!({
+                        "error": "Too many items match contains",
+                        "maxContains": max_contains,
+                        "actual": matching
+                    }),
+                ));
+            }
+        }
+    }
+
+    fn check_string(
+        &self,
+        value: &Value,
+        schema: &Value,
+        pointer: &str,
+        violations: &mut Vec<Violation>,
+    ) {
+        let s = match value.as_str() {
+            Some(s) => s,
+            None => {
+                return violations.push(Violation::new(
+                    pointer,
+                    json!({ "error": "Expected string type" }),
+                ))
+            }
+        };
+
+        // Lengths count characters (code points), not bytes.
+        let length = s.chars().count();
+        if let Some(min_length) = schema.get("minLength").and_then(Value::as_u64) {
+            if (length as u64) < min_length {
+                violations.push(Violation::new(
+                    pointer,
+                    json!({
+                        "error": "String too short",
+                        "minLength": min_length,
+                        "actual": length
+                    }),
+                ));
+            }
+        }
+
+        if let Some(max_length) = schema.get("maxLength").and_then(Value::as_u64) {
+            if (length as u64) > max_length {
+                violations.push(Violation::new(
+                    pointer,
+                    json!({
+                        "error": "String too long",
+                        "maxLength": max_length,
+                        "actual": length
+                    }),
+                ));
+            }
+        }
+
+        let format = schema
+            .get("format")
+            .and_then(Value::as_str)
+            .filter(|_| self.strict_formats);
+        let format_ok = match format {
+            Some("uuid") => uuid::Uuid::parse_str(s).is_ok(),
+            Some("date-time") => chrono::DateTime::parse_from_rfc3339(s).is_ok(),
+            Some("date") => chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").is_ok(),
+            Some("email") => s
+                .split_once('@')
+                .is_some_and(|(local, domain)| !local.is_empty() && domain.contains('.')),
+            Some("ipv4") => s.parse::<Ipv4Addr>().is_ok(),
+            Some("ipv6") => s.parse::<Ipv6Addr>().is_ok(),
+            Some("uri" | "url") => reqwest::Url::parse(s).is_ok(),
+            Some("hostname") => is_hostname(s),
+            _ => true,
+        };
+        if !format_ok {
+            violations.push(Violation::new(
+                pointer,
+                json!({
+                    "error": "String does not match format",
+                    "format": format
+                }),
+            ));
+        }
+
+        if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+            match self.swagger_state.regex(pattern) {
+                Ok(regex) if !regex.is_match(s) => violations.push(Violation::new(
+                    pointer,
+                    json!({
+                        "error": "String does not match pattern",
+                        "pattern": pattern
+                    }),
+                )),
+                Ok(_) => {}
+                Err(_) => violations.push(Violation::schema_error(
+                    pointer,
+                    json!({ "error": "Invalid pattern in schema" }),
+                )),
+            }
+        }
+    }
+
+    fn check_number(
+        &self,
+        value: &Value,
+        schema: &Value,
+        pointer: &str,
+        violations: &mut Vec<Violation>,
+    ) {
+        let num = match value.as_f64() {
+            Some(num) => num,
+            None => {
+                return violations.push(Violation::new(
+                    pointer,
+                    json!({ "error": "Expected numeric type" }),
+                ))
+            }
+        };
+
+        let Some(schema) = schema.as_object() else {
+            return;
+        };
+
+        if let Some((minimum, exclusive)) = lower_bound(schema) {
+            if num < minimum || (exclusive && num == minimum) {
+                let keyword = if exclusive {
+                    "exclusiveMinimum"
+                } else {
+                    "minimum"
+                };
+                violations.push(Violation::new(
+                    pointer,
+                    json!({
+                        "error": "Number too small",
+                        keyword: minimum,
+                        "actual": num
+                    }),
+                ));
+            }
+        }
+
+        if let Some((maximum, exclusive)) = upper_bound(schema) {
+            if num > maximum || (exclusive && num == maximum) {
+                let keyword = if exclusive {
+                    "exclusiveMaximum"
+                } else {
+                    "maximum"
+                };
+                violations.push(Violation::new(
+                    pointer,
+                    json!({
+                        "error": "Number too large",
+                        keyword: maximum,
+                        "actual": num
+                    }),
+                ));
+            }
+        }
+
+        if let Some(multiple_of) = schema
+            .get("multipleOf")
+            .and_then(Value::as_f64)
+            .filter(|m| *m > 0.0)
+        {
+            let quotient = num / multiple_of;
+            let error = (quotient - quotient.round()).abs();
+            if error > MULTIPLE_OF_TOLERANCE * quotient.abs().max(1.0) {
+                violations.push(Violation::new(
+                    pointer,
+                    json!({
+                        "error": "Number is not a multiple of multipleOf",
+                        "multipleOf": multiple_of,
+                        "actual": num
+                    }),
+                ));
+            }
+        }
+    }
+
+    async fn generate_response(
+        &self,
+        route_path: &str,
+        schema: &Value,
+        config: &MockConfig,
+    ) -> HttpResponse {
+        let status = match self.requested_status(schema) {
+            Some(Ok(status)) => status,
+            Some(Err(response)) => return response,
+            None => config.status_code.unwrap_or_else(|| default_status(schema)),
+        };
+        let status = actix_web::http::StatusCode::from_u16(status)
+            .unwrap_or(actix_web::http::StatusCode::OK);
+        let status_code = status.as_u16();
+        let mut response_builder = HttpResponse::build(status);
+
+        if config.clock_offset.is_some() {
+            response_builder.insert_header((
+                actix_web::http::header::DATE,
+                config.now().format("%a, %d %b %Y %H:%M:%S GMT").to_string(),
+            ));
+        }
 
         if let Some(headers) = &config.headers {
             for (key, value) in headers {
@@ -420,178 +1838,559 @@ impl RequestHandler {
             }
         }
 
-        let response_schema = schema
+        let response_content = schema
             .get("responses")
-            .and_then(|responses| responses.get(&status_code.to_string()))
-            .and_then(|response| response.get("content"))
-            .and_then(|content| content.get("application/json"))
-            .and_then(|json_content| json_content.get("schema"));
+            .and_then(|responses| responses.get(status_code.to_string()))
+            .and_then(|response| self.swagger_state.resolve_object(response))
+            .and_then(|response| response.get("content"));
+
+        let json_content = response_content.and_then(|content| content.get("application/json"));
+        let xml_content = response_content.and_then(|content| content.get(XML_CONTENT_TYPE));
+        let xml = self
+            .req
+            .headers()
+            .get(actix_web::http::header::ACCEPT)
+            .and_then(|accept| accept.to_str().ok())
+            .is_some_and(prefers_xml)
+            && (xml_content.is_some()
+                || (config.xml_responses.unwrap_or(false) && json_content.is_some()));
+        let json_content = if xml {
+            xml_content.or(json_content)
+        } else {
+            json_content
+        };
+
+        let strategy = config.example_strategy.or_else(|| {
+            config
+                .prefer_examples
+                .unwrap_or(true)
+                .then_some(ExampleStrategy::First)
+        });
+        if let Some(strategy) = strategy {
+            let cursor_key = format!("{} {} {}", self.req.method(), route_path, status_code);
+            if let Some(example) = json_content.and_then(|content| {
+                self.select_example(content, strategy, &cursor_key, config.seed)
+            }) {
+                let schema = json_content
+                    .and_then(|content| content.get("schema"))
+                    .unwrap_or(&Value::Null);
+                return self.body_response(&mut response_builder, example, xml.then_some(schema));
+            }
+        }
+
+        let response_schema = json_content.and_then(|json_content| json_content.get("schema"));
+
+        if let Some(pagination) = &config.pagination {
+            if let Some(link) = self.pagination_link(pagination, response_schema) {
+                response_builder.insert_header((actix_web::http::header::LINK, link));
+            }
+        }
 
         if let Some(schema) = response_schema {
-            if let Some(ref_path) = schema.get("$ref").and_then(Value::as_str) {
-                if let Some(resolved_schema) = self.swagger_state.resolve_ref(ref_path) {
-                    return response_builder.json(self.generate_mock_value(
-                        &resolved_schema,
-                        config.fields.as_ref(),
-                        None,
-                    ));
+            let mut body = self
+                .mock_body(route_path, schema, status_code, config)
+                .await;
+            if let Err(response) = self.check_generated(&body, schema, config) {
+                return response;
+            }
+            if status_code == 201 && self.req.method() == actix_web::http::Method::POST {
+                if let Some(location) = self.created_location(route_path, schema, &mut body, config)
+                {
+                    response_builder.insert_header((actix_web::http::header::LOCATION, location));
                 }
             }
-            return response_builder.json(self.generate_mock_value(
-                schema,
-                config.fields.as_ref(),
-                None,
-            ));
+            return self.body_response(&mut response_builder, body, xml.then_some(schema));
+        }
+
+        let ndjson_schema = response_content
+            .and_then(|content| content.get(NDJSON_CONTENT_TYPE))
+            .and_then(|ndjson_content| ndjson_content.get("schema"));
+
+        if let Some(schema) = ndjson_schema {
+            let value = self
+                .mock_body(route_path, schema, status_code, config)
+                .await;
+            return response_builder
+                .content_type(NDJSON_CONTENT_TYPE)
+                .streaming(ndjson_stream(value));
         }
 
-        response_builder.json(json!({
-            "success": false,
-            "message": "Schema not found",
-            "data": null
-        }))
+        debug!(
+            "No response schema for status {}, using default body",
+            status_code
+        );
+        response_builder.json(self.default_body(status_code, config))
     }
 
-    fn generate_mock_value(
+    /// Sends `body` as JSON, or as XML shaped by `xml_schema` when given.
+    fn body_response(
         &self,
-        schema: &Value,
-        field_config: Option<&MockFieldConfig>,
-        field_name: Option<&str>,
-    ) -> Value {
-        if let Some(config) = field_config {
-            if let Some(name) = field_name {
-                if let Some(pattern) = config.patterns.get(name) {
-                    return pattern.generate_value();
-                }
-            }
+        response_builder: &mut HttpResponseBuilder,
+        body: Value,
+        xml_schema: Option<&Value>,
+    ) -> HttpResponse {
+        match xml_schema {
+            Some(schema) => response_builder.content_type(XML_CONTENT_TYPE).body(to_xml(
+                &self.swagger_state,
+                &body,
+                schema,
+            )),
+            None => response_builder.json(body),
         }
+    }
 
-        match schema {
-            Value::Object(map) => {
-                if let Some(ref_path) = map.get("$ref").and_then(Value::as_str) {
-                    if let Some(resolved_schema) = self.swagger_state.resolve_ref(ref_path) {
-                        return self.generate_mock_value(
-                            &resolved_schema,
-                            field_config,
-                            field_name,
-                        );
-                    }
-                }
+    /// The status asked for with `__spit_status` (or else `X-Spit-Status`),
+    /// which must be one the operation documents.
+    fn requested_status(&self, operation: &Value) -> Option<Result<u16, HttpResponse>> {
+        let query: Vec<(String, String)> =
+            serde_urlencoded::from_str(self.req.query_string()).unwrap_or_default();
+        let requested = query
+            .into_iter()
+            .find(|(name, _)| name == STATUS_QUERY_PARAM)
+            .map(|(_, value)| value)
+            .or_else(|| {
+                self.req
+                    .headers()
+                    .get(STATUS_HEADER)
+                    .and_then(|value| value.to_str().ok())
+                    .map(String::from)
+            })?;
 
-                let type_val = map.get("type").and_then(Value::as_str).unwrap_or("object");
-                match type_val {
-                    "string" => self.generate_mock_string(map),
-                    "integer" | "number" => self.generate_mock_number(map, type_val),
-                    "boolean" => json!(rand::random::<bool>()),
-                    "array" => self.generate_mock_array(map, field_config, field_name),
-                    "object" => self.generate_mock_object(map, field_config),
-                    _ => json!(null),
-                }
+        let documented: Vec<&String> = operation
+            .get("responses")
+            .and_then(Value::as_object)
+            .map(|responses| responses.keys().collect())
+            .unwrap_or_default();
+        let requested = requested.trim();
+        match requested.parse::<u16>() {
+            Ok(status) if documented.iter().any(|d| d.as_str() == requested) => Some(Ok(status)),
+            _ => {
+                debug!("Requested status {} is not documented", requested);
+                Some(Err(HttpResponse::BadRequest().json(json!({
+                    "error": "Undocumented status",
+                    "details": format!("Status {} is not documented for this operation", requested),
+                    "documented_statuses": documented
+                }))))
             }
-            _ => json!(null),
         }
     }
 
-    fn generate_mock_string(&self, schema: &serde_json::Map<String, Value>) -> Value {
-        use fake::faker::company::raw::*;
-        use fake::faker::internet::raw::*;
-        use fake::faker::lorem::raw::*;
-        use fake::faker::name::raw::*;
-        use fake::locales::EN;
-        use fake::Fake;
+    /// Builds an RFC 8288 `Link` header with `next` and (past the first page)
+    /// `prev` URLs, for array responses or requests already paging through
+    /// `limit`/`offset`.
+    fn pagination_link(
+        &self,
+        pagination: &PaginationConfig,
+        response_schema: Option<&Value>,
+    ) -> Option<String> {
+        let query: Vec<(&str, &str)> = self
+            .req
+            .query_string()
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| pair.split_once('=').unwrap_or((pair, "")))
+            .collect();
+        let param = |name: &str| {
+            query
+                .iter()
+                .find(|(key, _)| *key == name)
+                .and_then(|(_, value)| value.parse::<u64>().ok())
+        };
+
+        let limit = param(&pagination.limit_param);
+        let offset = param(&pagination.offset_param);
+        let is_collection = response_schema.is_some_and(|schema| self.is_array_schema(schema));
+        if !is_collection && limit.is_none() && offset.is_none() {
+            return None;
+        }
 
-        if let Some(format) = schema.get("format").and_then(Value::as_str) {
-            match format {
-                "date-time" => json!(chrono::Utc::now().to_rfc3339()),
-                "email" => json!(FreeEmail(EN).fake::<String>()),
-                "uuid" => json!(uuid::Uuid::new_v4().to_string()),
-                "name" => json!(Name(EN).fake::<String>()),
-                "username" => json!(Username(EN).fake::<String>()),
-                "company" => json!(CompanyName(EN).fake::<String>()),
-                _ => json!(Sentence(EN, 3..10).fake::<String>()),
+        let limit = limit.unwrap_or(pagination.default_limit).max(1);
+        let offset = offset.unwrap_or(0);
+        let base_url = match &pagination.base_url {
+            Some(base_url) => base_url.trim_end_matches('/').to_string(),
+            None => {
+                let info = self.req.connection_info();
+                format!("{}://{}", info.scheme(), info.host())
             }
-        } else if let Some(enum_values) = schema.get("enum").and_then(Value::as_array) {
-            if !enum_values.is_empty() {
-                let index = (0..enum_values.len()).fake::<usize>();
-                enum_values[index].clone()
-            } else {
-                json!(Sentence(EN, 3..10).fake::<String>())
+        };
+        let other_params: String = query
+            .iter()
+            .filter(|(key, _)| *key != pagination.limit_param && *key != pagination.offset_param)
+            .map(|(key, value)| format!("{}={}&", key, value))
+            .collect();
+        let page_url = |page_offset: u64| {
+            format!(
+                "<{}{}?{}{}={}&{}={}>",
+                base_url,
+                self.path,
+                other_params,
+                pagination.limit_param,
+                limit,
+                pagination.offset_param,
+                page_offset
+            )
+        };
+
+        let mut links = vec![format!("{}; rel=\"next\"", page_url(offset + limit))];
+        if offset > 0 {
+            links.push(format!(
+                "{}; rel=\"prev\"",
+                page_url(offset.saturating_sub(limit))
+            ));
+        }
+        Some(links.join(", "))
+    }
+
+    fn is_array_schema(&self, schema: &Value) -> bool {
+        if let Some(ref_path) = schema.get("$ref").and_then(Value::as_str) {
+            return self
+                .swagger_state
+                .resolve_ref(ref_path)
+                .is_some_and(|resolved| self.is_array_schema(&resolved));
+        }
+        schema.get("type").and_then(Value::as_str) == Some("array")
+    }
+
+    /// Makes sure a created resource carries its `readOnly` id (preferring a
+    /// property named `id`) and, when the spec has an item route (`route_path`
+    /// followed by one `{param}` segment), returns its URL for that id.
+    fn created_location(
+        &self,
+        route_path: &str,
+        schema: &Value,
+        body: &mut Value,
+        config: &MockConfig,
+    ) -> Option<String> {
+        let resolved = match schema.get("$ref").and_then(Value::as_str) {
+            Some(ref_path) => self.swagger_state.resolve_ref(ref_path)?,
+            None => schema.clone(),
+        };
+        let (id_name, id_schema) = resolved
+            .get("properties")
+            .and_then(Value::as_object)?
+            .iter()
+            .filter(|(_, prop)| prop.get("readOnly").and_then(Value::as_bool) == Some(true))
+            .max_by_key(|(name, _)| name.as_str() == "id")?;
+
+        let body = body.as_object_mut()?;
+        let id = match body.get(id_name).filter(|id| !id.is_null()) {
+            Some(id) => id.clone(),
+            None => {
+                let id = MockGenerator::new(&self.swagger_state, config)
+                    .seeded_for(&self.seed_key())
+                    .counting(self.sequences())
+                    .generate_mock_value(id_schema, Some(id_name));
+                body.insert(id_name.clone(), id.clone());
+                id
             }
-        } else {
-            json!(Sentence(EN, 3..10).fake::<String>())
+        };
+        let id = match id {
+            Value::String(id) => id,
+            other => other.to_string(),
+        };
+
+        let state = self.acquire_state_lock().ok()?;
+        let has_item_route = state.routes.keys().any(|path| {
+            path.strip_prefix(route_path.trim_end_matches('/'))
+                .and_then(|rest| rest.strip_prefix("/{"))
+                .is_some_and(|rest| rest.ends_with('}') && !rest.contains('/'))
+        });
+
+        has_item_route.then(|| format!("{}/{}", self.path.trim_end_matches('/'), id))
+    }
+
+    /// With `strict_responses`, refuses to send a generated body that breaks
+    /// its own schema, e.g. because a field pattern produced the wrong type
+    /// for a required field.
+    fn check_generated(
+        &self,
+        body: &Value,
+        schema: &Value,
+        config: &MockConfig,
+    ) -> Result<(), HttpResponse> {
+        if !config.strict_responses.unwrap_or(false) {
+            return Ok(());
+        }
+
+        let mut violations = Vec::new();
+        self.check_schema(body, schema, "", &mut violations, Direction::Response);
+        if violations.is_empty() {
+            return Ok(());
         }
+
+        let errors: Vec<Value> = violations.into_iter().map(Violation::into_error).collect();
+        for violation in &errors {
+            error!(
+                "Generated response for {} {} violates its schema: {}",
+                self.req.method(),
+                self.path,
+                violation
+            );
+        }
+
+        Err(HttpResponse::InternalServerError().json(json!({
+            "error": "Generated response violates its schema",
+            "errors": errors
+        })))
     }
 
-    fn generate_mock_number(
+    /// Picks one of the named `examples` of a media type per `strategy`,
+    /// falling back to its single `example`.
+    fn select_example(
         &self,
-        schema: &serde_json::Map<String, Value>,
-        type_val: &str,
-    ) -> Value {
-        let min = schema
-            .get("minimum")
-            .and_then(|v| v.as_f64())
-            .unwrap_or(0.0);
-        let max = schema
-            .get("maximum")
-            .and_then(|v| v.as_f64())
-            .unwrap_or(100.0);
-
-        if type_val == "integer" {
-            json!((min as i64..=max as i64).fake::<i64>())
-        } else {
-            json!((min + (max - min) * rand::random::<f64>()).round() / 100.0)
+        content: &Value,
+        strategy: ExampleStrategy,
+        cursor_key: &str,
+        seed: Option<u64>,
+    ) -> Option<Value> {
+        let examples: Vec<&Value> = content
+            .get("examples")
+            .and_then(Value::as_object)
+            .map(|examples| {
+                examples
+                    .values()
+                    .filter_map(|example| example.get("value"))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        if examples.is_empty() {
+            return content.get("example").cloned();
         }
+
+        let index = match strategy {
+            ExampleStrategy::First => 0,
+            ExampleStrategy::Random => {
+                self.with_rng(seed, |rng| rng.gen_range(0..examples.len()))?
+            }
+            ExampleStrategy::RoundRobin => {
+                let state = self.acquire_state_lock().ok()?;
+                let mut cursors = state.example_cursors.lock().ok()?;
+                let cursor = cursors.entry(cursor_key.to_string()).or_insert(0);
+                let index = *cursor % examples.len();
+                *cursor = cursor.wrapping_add(1);
+                index
+            }
+        };
+
+        Some(examples[index].clone())
     }
 
-    fn generate_mock_array(
+    /// Generates the body for `schema`, reusing the one previously generated
+    /// for this method, path and status when `cache_responses` is enabled,
+    /// or for these path parameter values with `cache_by_path_params`.
+    async fn mock_body(
         &self,
-        schema: &serde_json::Map<String, Value>,
-        field_config: Option<&MockFieldConfig>,
-        field_name: Option<&str>,
+        route_path: &str,
+        schema: &Value,
+        status_code: u16,
+        config: &MockConfig,
     ) -> Value {
-        let min_items = schema.get("minItems").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
-        let max_items = schema.get("maxItems").and_then(|v| v.as_u64()).unwrap_or(5) as usize;
-        let count = (min_items..=max_items).fake::<usize>();
-
-        if let Some(items) = schema.get("items") {
-            json!((0..count)
-                .map(|_| self.generate_mock_value(items, field_config, field_name))
-                .collect::<Vec<_>>())
-        } else {
-            json!([])
+        if config.cache_responses.unwrap_or(false) {
+            let key = (
+                self.req.method().to_string(),
+                self.path.clone(),
+                status_code,
+            );
+            return self
+                .cached_value(
+                    |state| &state.response_cache,
+                    key,
+                    route_path,
+                    schema,
+                    config,
+                )
+                .await;
         }
+
+        let params: Vec<String> = extract_path_params(route_path, &self.path)
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect();
+        if config.cache_by_path_params.unwrap_or(false) && !params.is_empty() {
+            let key = (
+                self.req.method().to_string(),
+                route_path.to_string(),
+                params,
+                status_code,
+            );
+            return self
+                .cached_value(|state| &state.entity_cache, key, route_path, schema, config)
+                .await;
+        }
+
+        self.generate_value(route_path, schema, config).await
     }
 
-    fn generate_mock_object(
+    /// Looks `key` up in the cache `cache` selects from the state, generating
+    /// and storing the value on a miss.
+    async fn cached_value<K: std::hash::Hash + Eq + std::fmt::Debug>(
         &self,
-        schema: &serde_json::Map<String, Value>,
-        field_config: Option<&MockFieldConfig>,
+        cache: fn(&MockState) -> &Mutex<HashMap<K, Value>>,
+        key: K,
+        route_path: &str,
+        schema: &Value,
+        config: &MockConfig,
     ) -> Value {
-        let mut mock = serde_json::Map::new();
+        if let Ok(state) = self.acquire_state_lock() {
+            if let Some(cached) = cache(&state).lock().ok().and_then(|c| c.get(&key).cloned()) {
+                debug!("Serving cached body for {:?}", key);
+                return cached;
+            }
+        }
 
-        let props = match schema.get("properties").and_then(Value::as_object) {
-            Some(props) => props,
-            None => return Value::Object(mock),
+        let value = self.generate_value(route_path, schema, config).await;
+        if let Ok(state) = self.acquire_state_lock() {
+            if let Ok(mut cache) = cache(&state).lock() {
+                cache.insert(key, value.clone());
+            }
+        }
+        value
+    }
+
+    /// Generates a value for `schema`, on the blocking thread pool when
+    /// `offload_generation` is on and the schema is complex enough to stall
+    /// the worker.
+    async fn generate_value(&self, route_path: &str, schema: &Value, config: &MockConfig) -> Value {
+        let threshold = config
+            .offload_threshold
+            .unwrap_or(DEFAULT_OFFLOAD_THRESHOLD);
+        let complexity = match config.offload_generation {
+            Some(true) => schema_complexity(&self.swagger_state, schema),
+            _ => 0,
         };
+        let seed_key = self.seed_key();
+        let echoed = self.echoed_params(route_path, config);
+        if !config.offload_generation.unwrap_or(false) || complexity < threshold {
+            return MockGenerator::new(&self.swagger_state, config)
+                .seeded_for(&seed_key)
+                .echoing(echoed)
+                .counting(self.sequences())
+                .generate_mock_value(schema, None);
+        }
+        debug!(
+            "Offloading generation for {} (complexity {})",
+            self.path, complexity
+        );
 
-        let required_fields: HashSet<_> = schema
-            .get("required")
-            .and_then(Value::as_array)
-            .map(|req| req.iter().filter_map(|v| v.as_str()).collect())
-            .unwrap_or_default();
+        let swagger_state = self.swagger_state.clone();
+        let owned_config = config.clone();
+        let owned_schema = schema.clone();
+        let owned_key = seed_key.clone();
+        let owned_echoed = echoed.clone();
+        let sequences = self.sequences();
+        let offloaded = web::block(move || {
+            MockGenerator::new(&swagger_state, &owned_config)
+                .seeded_for(&owned_key)
+                .echoing(owned_echoed)
+                .counting(sequences)
+                .generate_mock_value(&owned_schema, None)
+        })
+        .await;
+
+        match offloaded {
+            Ok(value) => value,
+            Err(e) => {
+                error!("Offloaded generation failed, generating inline: {}", e);
+                MockGenerator::new(&self.swagger_state, config)
+                    .seeded_for(&seed_key)
+                    .echoing(echoed)
+                    .counting(self.sequences())
+                    .generate_mock_value(schema, None)
+            }
+        }
+    }
 
-        for (key, prop_schema) in props {
-            if required_fields.contains(key.as_str()) || required_fields.is_empty() {
-                mock.insert(
-                    key.clone(),
-                    self.generate_mock_value(prop_schema, field_config, Some(key)),
-                );
+    /// With `echo_params`, the query and path parameter values of the
+    /// request by name, path parameters winning over query ones.
+    fn echoed_params(&self, route_path: &str, config: &MockConfig) -> HashMap<String, String> {
+        if !config.echo_params.unwrap_or(false) {
+            return HashMap::new();
+        }
+        let query: Vec<(String, String)> =
+            serde_urlencoded::from_str(self.req.query_string()).unwrap_or_default();
+        query
+            .into_iter()
+            .filter(|(name, _)| name != STATUS_QUERY_PARAM)
+            .chain(extract_path_params(route_path, &self.path))
+            .collect()
+    }
+
+    /// The server's `sequence` counters, shared by its generators.
+    fn sequences(&self) -> Sequences {
+        self.acquire_state_lock()
+            .map(|state| state.sequences.clone())
+            .unwrap_or_default()
+    }
+
+    /// What a `seed` is combined with for this request's generated data.
+    fn seed_key(&self) -> String {
+        format!(
+            "{} {}?{}",
+            self.req.method(),
+            self.path,
+            self.req.query_string()
+        )
+    }
+
+    fn default_body(&self, status_code: u16, config: &MockConfig) -> Value {
+        let status_class = format!("{}xx", status_code / 100);
+
+        config
+            .default_bodies
+            .as_ref()
+            .and_then(|bodies| bodies.get(&status_class))
+            .cloned()
+            .unwrap_or_else(|| {
+                json!({
+                    "success": (200..300).contains(&status_code),
+                    "message": "Schema not found",
+                    "data": null
+                })
+            })
+    }
+
+    fn log_request(&self, state: &MockState, body: &Option<web::Bytes>, response: &HttpResponse) {
+        let entry = self.log_entry(body, response);
+
+        if let Some(file) = &state.access_log {
+            let line = self.combined_log_line(&entry, response.body().size());
+            if let Err(e) = writeln!(file.as_ref(), "{}", line) {
+                error!("Failed to write access log: {}", e);
             }
         }
 
-        Value::Object(mock)
+        if let Ok(mut request_log) = state.request_log.lock() {
+            request_log.push(entry);
+        }
     }
 
-    fn log_request(&self, state: &mut MockState, status: u16) {
+    /// Appends the exchange, bodies included, to the `log_file`. Streamed
+    /// bodies are passed on as they come and logged once they end.
+    fn append_log_file(
+        &self,
+        log_file: &web::Data<RequestLogFile>,
+        body: &Option<web::Bytes>,
+        response: HttpResponse,
+    ) -> HttpResponse {
+        let entry = self.log_entry(body, &response);
+        let (response, response_body) = response.into_parts();
+        match response_body.try_into_bytes() {
+            Ok(response_body) => {
+                let mut entry = entry;
+                entry.response_body = Some(String::from_utf8_lossy(&response_body).into_owned());
+                log_file.append(entry);
+                response.set_body(BoxBody::new(response_body))
+            }
+            Err(stream) => response.set_body(BoxBody::new(LoggedBody::new(
+                stream,
+                entry,
+                log_file.clone(),
+            ))),
+        }
+    }
+
+    fn log_entry(&self, body: &Option<web::Bytes>, response: &HttpResponse) -> RequestLog {
         let headers: HashMap<String, String> = self
             .req
             .headers()
@@ -599,23 +2398,327 @@ impl RequestHandler {
             .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or_default().to_string()))
             .collect();
 
-        state.request_log.push(RequestLog {
+        let response_size = match response.body().size() {
+            BodySize::Sized(size) => size,
+            _ => 0,
+        };
+
+        RequestLog {
             timestamp: Utc::now(),
             method: self.req.method().to_string(),
             path: self.path.clone(),
+            query: Some(self.req.query_string())
+                .filter(|query| !query.is_empty())
+                .map(String::from),
             headers,
-            response_status: status,
+            body: body
+                .as_ref()
+                .filter(|bytes| !bytes.is_empty())
+                .map(|bytes| String::from_utf8_lossy(bytes).into_owned()),
+            response_status: response.status().as_u16(),
+            response_size,
+            peer_addr: self.req.peer_addr().map(|addr| addr.ip().to_string()),
+            response_body: None,
+            response_content_type: response
+                .headers()
+                .get(actix_web::http::header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .map(String::from),
+        }
+    }
+
+    /// `entry` in the Apache combined log format. The request line is the
+    /// raw (still percent-encoded) target, and quoted fields are escaped, so
+    /// a request cannot break or forge lines. Bodies of unknown size, such
+    /// as streams, are logged as `-`.
+    fn combined_log_line(&self, entry: &RequestLog, size: BodySize) -> String {
+        let header = |name: &str| {
+            self.req
+                .headers()
+                .get(name)
+                .map_or_else(|| "-".to_string(), |v| escape_log_field(v.as_bytes()))
+        };
+        let target = self
+            .req
+            .uri()
+            .path_and_query()
+            .map_or("/", |target| target.as_str());
+        let request_line = format!("{} {} {:?}", entry.method, target, self.req.version());
+        let size = match size {
+            BodySize::Sized(size) => size.to_string(),
+            _ => "-".to_string(),
+        };
+
+        format!(
+            "{} - - [{}] \"{}\" {} {} \"{}\" \"{}\"",
+            entry.peer_addr.as_deref().unwrap_or("-"),
+            entry.timestamp.format("%d/%b/%Y:%H:%M:%S %z"),
+            escape_log_field(request_line.as_bytes()),
+            entry.response_status,
+            size,
+            header("referer"),
+            header("user-agent"),
+        )
+    }
+}
+
+/// `value` for a quoted access log field, escaped as Apache does: `"` and
+/// `\` get a backslash, and control and non-ASCII bytes become `\xhh`.
+fn escape_log_field(value: &[u8]) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for &byte in value {
+        match byte {
+            b'"' => escaped.push_str("\\\""),
+            b'\\' => escaped.push_str("\\\\"),
+            b'\n' => escaped.push_str("\\n"),
+            b'\r' => escaped.push_str("\\r"),
+            b'\t' => escaped.push_str("\\t"),
+            b' '..=b'~' => escaped.push(char::from(byte)),
+            _ => escaped.push_str(&format!("\\x{:02x}", byte)),
+        }
+    }
+    escaped
+}
+
+/// Status answered when none is configured: 200, then 201, then the only
+/// status an operation documents.
+fn default_status(operation: &Value) -> u16 {
+    let responses = operation.get("responses");
+    let documents = |status: &str| responses.and_then(|r| r.get(status)).is_some();
+
+    if documents("200") {
+        return 200;
+    }
+    if documents("201") {
+        return 201;
+    }
+    only_documented_status(responses).unwrap_or(200)
+}
+
+/// The status of an operation documenting exactly one response besides
+/// `default`, such as a `204` delete or a recorded `404`.
+fn only_documented_status(responses: Option<&Value>) -> Option<u16> {
+    let mut statuses = responses?
+        .as_object()?
+        .keys()
+        .filter(|status| status.as_str() != "default");
+    let status = statuses.next()?.parse().ok()?;
+    statuses.next().is_none().then_some(status)
+}
+
+/// Re-sends the body of `response` in `BODY_CHUNK_SIZE` chunks, each one
+/// preceded by `delay`, so the headers arrive well before the body.
+async fn delay_body(response: HttpResponse, delay: std::time::Duration) -> HttpResponse {
+    let (response, body) = response.into_parts();
+    let bytes = match actix_web::body::to_bytes(body).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            error!("Failed to buffer response body: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    let chunks: Vec<web::Bytes> = bytes
+        .chunks(BODY_CHUNK_SIZE)
+        .map(web::Bytes::copy_from_slice)
+        .collect();
+    let stream = futures_util::stream::unfold(chunks.into_iter(), move |mut chunks| async move {
+        let chunk = chunks.next()?;
+        tokio::time::sleep(delay).await;
+        Some((Ok::<_, actix_web::Error>(chunk), chunks))
+    });
+
+    response.set_body(BoxBody::new(BodyStream::new(stream)))
+}
+
+/// Which way a validated value travels. `readOnly` properties need not be
+/// sent in requests, nor `writeOnly` ones in responses, even when required.
+#[derive(Clone, Copy)]
+enum Direction {
+    Request,
+    Response,
+}
+
+impl Direction {
+    /// The schema keyword exempting a property from `required` this way.
+    fn exempting_keyword(self) -> &'static str {
+        match self {
+            Direction::Request => "readOnly",
+            Direction::Response => "writeOnly",
+        }
+    }
+}
+
+/// A request value breaking its schema, located by a JSON pointer into it.
+struct Violation {
+    pointer: String,
+    status: StatusCode,
+    details: Value,
+}
+
+impl Violation {
+    fn new(pointer: &str, details: Value) -> Self {
+        Self {
+            pointer: pointer.to_string(),
+            status: StatusCode::BAD_REQUEST,
+            details,
+        }
+    }
+
+    /// A problem with the schema itself rather than the request.
+    fn schema_error(pointer: &str, details: Value) -> Self {
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            ..Self::new(pointer, details)
+        }
+    }
+
+    /// The details as an `errors` entry: `path` and `message` first, then
+    /// the remaining fields.
+    fn into_error(self) -> Value {
+        let mut error = serde_json::Map::new();
+        error.insert("path".to_string(), json!(self.pointer));
+        if let Value::Object(mut details) = self.details {
+            if let Some(message) = details.shift_remove("error") {
+                error.insert("message".to_string(), message);
+            }
+            error.extend(details);
+        }
+        Value::Object(error)
+    }
+}
+
+fn child_pointer(pointer: &str, key: &str) -> String {
+    format!("{}/{}", pointer, key.replace('~', "~0").replace('/', "~1"))
+}
+
+/// Media type of a `Content-Type` value without its parameters, lowercased.
+pub(crate) fn media_type_essence(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+}
+
+/// Value of the `name` parameter of a `Content-Type` value.
+fn media_type_param<'a>(content_type: &'a str, name: &str) -> Option<&'a str> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then(|| value.trim().trim_matches('"'))
+    })
+}
+
+/// The `content` entry for a request's `Content-Type`: the exact media type,
+/// else `application/json` for `+json` types, else a `type/*` range, else
+/// `*/*`.
+fn find_media_type<'a>(
+    content: &'a serde_json::Map<String, Value>,
+    content_type: &str,
+) -> Option<&'a Value> {
+    let essence = media_type_essence(content_type);
+    let json = if essence.ends_with("+json") {
+        "application/json"
+    } else {
+        essence.as_str()
+    };
+    let range = format!("{}/*", essence.split('/').next().unwrap_or_default());
+    let media_type = [essence.as_str(), json, range.as_str(), "*/*"]
+        .into_iter()
+        .find_map(|wanted| {
+            content
+                .iter()
+                .find(|(media_type, _)| media_type_essence(media_type) == wanted)
+                .map(|(_, media_type)| media_type)
         });
+    media_type
+}
+
+/// Name and content of each part of a `multipart/form-data` body, with file
+/// contents decoded lossily, or `None` when it is not delimited by
+/// `boundary`.
+fn multipart_fields(body: &[u8], boundary: &str) -> Option<Vec<(String, String)>> {
+    let body = String::from_utf8_lossy(body);
+    let delimiter = format!("--{}", boundary);
+    let mut parts = body.split(delimiter.as_str());
+    parts.next()?;
+
+    let mut fields = Vec::new();
+    for part in parts {
+        if part.starts_with("--") {
+            return Some(fields);
+        }
+        let (headers, content) = part.strip_prefix("\r\n")?.split_once("\r\n\r\n")?;
+        let name = headers.split("\r\n").find_map(|header| {
+            let (key, value) = header.split_once(':')?;
+            if !key.trim().eq_ignore_ascii_case("content-disposition") {
+                return None;
+            }
+            media_type_param(value, "name")
+        })?;
+        let content = content.strip_suffix("\r\n").unwrap_or(content);
+        fields.push((name.to_string(), content.to_string()));
     }
+    None
+}
+
+/// RFC 1123 host name: dot-separated labels of letters, digits and inner
+/// hyphens, each at most 63 characters, 253 in all.
+fn is_hostname(s: &str) -> bool {
+    s.len() <= 253
+        && s.trim_end_matches('.').split('.').all(|label| {
+            (1..=63).contains(&label.len())
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+        })
+}
+
+/// Streams each element of an array (or a lone value) as its own JSON line.
+fn ndjson_stream(
+    value: Value,
+) -> impl futures_util::Stream<Item = Result<web::Bytes, actix_web::Error>> {
+    let items = match value {
+        Value::Array(items) => items,
+        other => vec![other],
+    };
+
+    futures_util::stream::iter(items.into_iter().map(|item| {
+        let mut line = serde_json::to_vec(&item)?;
+        line.push(b'\n');
+        Ok(web::Bytes::from(line))
+    }))
 }
 
 pub async fn handle_request(
     req: HttpRequest,
     path: web::Path<String>,
     body: Option<web::Bytes>,
-    state: web::Data<Mutex<MockState>>,
-    swagger_state: web::Data<SwaggerState>,
+    state: web::Data<RwLock<MockState>>,
+    swagger_state: web::Data<SharedSwagger>,
+    virtual_hosts: web::Data<VirtualHosts>,
 ) -> HttpResponse {
-    let handler = RequestHandler::new(req, path, state, swagger_state);
+    // HTTP/2 requests carry the host as `:authority`, which ends up in the
+    // URI rather than in a `Host` header.
+    let host = match req.uri().authority() {
+        Some(authority) => Some(authority.as_str()),
+        None => req
+            .headers()
+            .get(actix_web::http::header::HOST)
+            .and_then(|host| host.to_str().ok()),
+    };
+    let host_service = host.and_then(|host| virtual_hosts.get(host));
+
+    let handler = match host_service {
+        Some((host_state, host_swagger)) => {
+            RequestHandler::new(req, path, host_state.clone(), host_swagger.clone())
+        }
+        None => RequestHandler::new(req, path, state, swagger_state),
+    };
     handler.handle_request(body).await
 }
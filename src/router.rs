@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+
+use crate::config::RouteTable;
+
+/// Route templates indexed by path segment, so a request path is matched by
+/// walking its segments instead of comparing it with every route. Literal
+/// segments take precedence over `{param}` segments.
+#[derive(Default, Clone, Debug)]
+pub struct RouteTree {
+    root: Node,
+    case_insensitive: bool,
+}
+
+#[derive(Default, Clone, Debug)]
+struct Node {
+    literals: HashMap<String, Node>,
+    param: Option<Box<Node>>,
+    /// Template of the route ending at this node.
+    route: Option<String>,
+}
+
+impl RouteTree {
+    pub fn new(routes: &RouteTable, case_insensitive: bool) -> Self {
+        let mut tree = Self {
+            root: Node::default(),
+            case_insensitive,
+        };
+        for template in routes.keys() {
+            let mut node = &mut tree.root;
+            for segment in segments(template) {
+                node = if segment.starts_with('{') {
+                    node.param.get_or_insert_with(Default::default)
+                } else {
+                    node.literals
+                        .entry(fold_case(segment, case_insensitive))
+                        .or_default()
+                };
+            }
+            node.route.get_or_insert_with(|| template.clone());
+        }
+        tree
+    }
+
+    /// The template of the route matching `path`, if any.
+    pub fn find(&self, path: &str) -> Option<&str> {
+        let path: Vec<String> = segments(path)
+            .map(|s| fold_case(s, self.case_insensitive))
+            .collect();
+        self.root.find(&path)
+    }
+}
+
+impl Node {
+    fn find(&self, path: &[String]) -> Option<&str> {
+        let Some((segment, rest)) = path.split_first() else {
+            return self.route.as_deref();
+        };
+        self.literals
+            .get(segment)
+            .and_then(|node| node.find(rest))
+            .or_else(|| self.param.as_ref().and_then(|node| node.find(rest)))
+    }
+}
+
+/// The linear scan `RouteTree` replaced: the first route, in table order,
+/// that `validate_path_params` accepts. Kept as the reference the tree is
+/// tested and benchmarked against.
+#[doc(hidden)]
+pub fn scan_routes<'a>(
+    routes: &'a RouteTable,
+    path: &str,
+    case_insensitive: bool,
+) -> Option<&'a str> {
+    routes
+        .keys()
+        .find(|route_path| validate_path_params(route_path, path, case_insensitive))
+        .map(String::as_str)
+}
+
+fn validate_path_params(path: &str, req_path: &str, case_insensitive: bool) -> bool {
+    let path_segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let req_segments: Vec<&str> = req_path.split('/').filter(|s| !s.is_empty()).collect();
+
+    if path_segments.len() != req_segments.len() {
+        return false;
+    }
+
+    path_segments
+        .iter()
+        .zip(req_segments.iter())
+        .all(|(path_seg, req_seg)| {
+            path_seg.starts_with('{')
+                || path_seg == req_seg
+                || (case_insensitive && path_seg.eq_ignore_ascii_case(req_seg))
+        })
+}
+
+fn fold_case(segment: &str, case_insensitive: bool) -> String {
+    if case_insensitive {
+        segment.to_ascii_lowercase()
+    } else {
+        segment.to_string()
+    }
+}
+
+fn segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|s| !s.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(templates: &[&str]) -> RouteTable {
+        templates
+            .iter()
+            .map(|template| (template.to_string(), Vec::new()))
+            .collect()
+    }
+
+    #[test]
+    fn matches_like_a_linear_scan() {
+        let routes = table(&[
+            "/",
+            "/users",
+            "/users/me",
+            "/users/{id}",
+            "/users/{id}/friends",
+            "/users/{id}/friends/{friend}",
+            "/orders/{order}/items/{item}",
+            "/orders/{order}/items/latest",
+            "/Health",
+        ]);
+        let paths = [
+            "/",
+            "/users",
+            "/users/",
+            "/users/me",
+            "/users/42",
+            "/users/42/friends",
+            "/users/me/friends",
+            "/users/me/friends/7",
+            "/users/42/enemies",
+            "/orders/1/items/latest",
+            "/orders/1/items/9",
+            "/orders/1/items",
+            "/health",
+            "/Health",
+            "/nope",
+            "//users//42",
+        ];
+        for case_insensitive in [false, true] {
+            let tree = RouteTree::new(&routes, case_insensitive);
+            for path in paths {
+                assert_eq!(
+                    tree.find(path),
+                    scan_routes(&routes, path, case_insensitive),
+                    "{} (case insensitive: {})",
+                    path,
+                    case_insensitive
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn matches_like_a_linear_scan_over_400_routes() {
+        let mut templates = Vec::new();
+        for n in 0..100 {
+            templates.push(format!("/resource{}", n));
+            templates.push(format!("/resource{}/{{id}}", n));
+            templates.push(format!("/resource{}/{{id}}/items", n));
+            templates.push(format!("/resource{}/{{id}}/items/{{item}}", n));
+        }
+        templates.push("/resource7/me/items".to_string());
+        let routes = table(&templates.iter().map(String::as_str).collect::<Vec<_>>());
+
+        let tree = RouteTree::new(&routes, false);
+        for n in 0..101 {
+            for path in [
+                format!("/resource{}", n),
+                format!("/resource{}/42", n),
+                format!("/resource{}/me/items", n),
+                format!("/resource{}/42/items/7", n),
+                format!("/resource{}/42/other", n),
+            ] {
+                assert_eq!(
+                    tree.find(&path),
+                    scan_routes(&routes, &path, false),
+                    "{}",
+                    path
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn literal_segments_win_over_params_at_any_depth() {
+        let routes = table(&["/{kind}/latest", "/users/{id}"]);
+        let tree = RouteTree::new(&routes, false);
+        assert_eq!(tree.find("/users/latest"), Some("/users/{id}"));
+        assert_eq!(tree.find("/orders/latest"), Some("/{kind}/latest"));
+    }
+}
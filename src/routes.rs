@@ -0,0 +1,103 @@
+use serde_json::{json, Value};
+
+use crate::{
+    config::RouteTable,
+    fetch_swagger,
+    swagger::{process_swagger_paths, SpecSource},
+};
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+pub enum RoutesFormat {
+    /// Aligned columns for reading
+    Table,
+    /// JSON array with one object per route
+    Json,
+}
+
+/// Prints the routes spit finds in the spec at `source`, with their
+/// methods, documented statuses and path parameter names.
+pub async fn print_routes(
+    source: &str,
+    format: RoutesFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let swagger = fetch_swagger(&SpecSource::from(source)).await?;
+    let routes = process_swagger_paths(&swagger);
+
+    match format {
+        RoutesFormat::Json => println!("{}", serde_json::to_string_pretty(&routes_json(&routes))?),
+        RoutesFormat::Table => print!("{}", routes_table(&routes)),
+    }
+    Ok(())
+}
+
+pub fn routes_json(routes: &RouteTable) -> Value {
+    routes
+        .iter()
+        .map(|(path, handlers)| {
+            let methods: Vec<Value> = handlers
+                .iter()
+                .map(|(method, operation)| {
+                    json!({ "method": method, "statuses": statuses(operation) })
+                })
+                .collect();
+            json!({
+                "path": path,
+                "params": path_params(path),
+                "methods": methods
+            })
+        })
+        .collect()
+}
+
+fn routes_table(routes: &RouteTable) -> String {
+    let mut rows = vec![[
+        "PATH".to_string(),
+        "METHOD".to_string(),
+        "STATUSES".to_string(),
+        "PARAMS".to_string(),
+    ]];
+    for (path, handlers) in routes {
+        for (method, operation) in handlers {
+            rows.push([
+                path.clone(),
+                method.clone(),
+                statuses(operation).join(","),
+                path_params(path).join(","),
+            ]);
+        }
+    }
+
+    let mut widths = [0; 4];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let mut table = String::new();
+    for row in &rows {
+        let line: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        table.push_str(line.join("  ").trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+/// Response codes the operation documents, `default` included.
+fn statuses(operation: &Value) -> Vec<String> {
+    operation
+        .get("responses")
+        .and_then(Value::as_object)
+        .map(|responses| responses.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Names of the `{param}` segments of a route template.
+fn path_params(path: &str) -> Vec<&str> {
+    path.split('/')
+        .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+        .collect()
+}
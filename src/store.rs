@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+
+use serde_json::{json, Value};
+
+/// Field holding an entity's id in `stateful` mode.
+pub const ID_FIELD: &str = "id";
+
+/// Entities stored by `stateful` mode, by id within each collection path.
+pub type Collections = HashMap<String, HashMap<String, Value>>;
+
+/// How a request path maps onto a REST resource.
+#[derive(Debug, PartialEq)]
+pub enum Resource<'a> {
+    /// A collection such as `/users`, by its concrete path.
+    Collection(&'a str),
+    /// One entity such as `/users/7`: its collection path and id.
+    Item(&'a str, &'a str),
+}
+
+impl<'a> Resource<'a> {
+    /// Classifies `path` by the route `template` it matched: templates ending
+    /// in a `{param}` segment address an item, any other a collection.
+    pub fn of(template: &str, path: &'a str) -> Self {
+        let path = path.trim_end_matches('/');
+        let item = template
+            .trim_end_matches('/')
+            .rsplit('/')
+            .next()
+            .is_some_and(|segment| segment.starts_with('{') && segment.ends_with('}'));
+
+        match path.rsplit_once('/') {
+            Some((collection, id)) if item && !id.is_empty() => Resource::Item(collection, id),
+            _ => Resource::Collection(path),
+        }
+    }
+}
+
+/// The id of `entity` as it appears in item paths.
+pub fn entity_id(entity: &Value) -> Option<String> {
+    id_key(entity.get(ID_FIELD)?)
+}
+
+/// The key an entity with this id is stored under.
+pub fn id_key(id: &Value) -> Option<String> {
+    match id {
+        Value::String(id) => Some(id.clone()),
+        Value::Null => None,
+        other => Some(other.to_string()),
+    }
+}
+
+/// `generated` as the id of a new entity of `entities`, or in its place the
+/// next free integer (for integer ids) or a UUID from `uuid` when it is
+/// missing or taken.
+pub fn free_id(
+    entities: &HashMap<String, Value>,
+    generated: Option<&Value>,
+    uuid: impl FnOnce() -> String,
+) -> Value {
+    match generated.and_then(|id| Some((id, id_key(id)?))) {
+        Some((id, key)) if !entities.contains_key(&key) => id.clone(),
+        Some((Value::Number(_), _)) => {
+            let last = entities
+                .keys()
+                .filter_map(|key| key.parse::<u64>().ok())
+                .max()
+                .unwrap_or(0);
+            json!(last + 1)
+        }
+        _ => json!(uuid()),
+    }
+}
+
+/// The stored entities of a collection, ordered by id.
+pub fn list(collections: &Collections, collection: &str) -> Vec<Value> {
+    let mut entities: Vec<(&String, &Value)> = collections
+        .get(collection)
+        .map(|entities| entities.iter().collect())
+        .unwrap_or_default();
+    entities.sort_by(
+        |(a, _), (b, _)| match (a.parse::<u64>(), b.parse::<u64>()) {
+            (Ok(a), Ok(b)) => a.cmp(&b),
+            _ => a.cmp(b),
+        },
+    );
+    entities
+        .into_iter()
+        .map(|(_, entity)| entity.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_paths_by_their_template() {
+        assert_eq!(
+            Resource::of("/users", "/users/"),
+            Resource::Collection("/users")
+        );
+        assert_eq!(
+            Resource::of("/users/{id}", "/users/7"),
+            Resource::Item("/users", "7")
+        );
+        assert_eq!(
+            Resource::of("/orgs/{org}/users", "/orgs/acme/users"),
+            Resource::Collection("/orgs/acme/users")
+        );
+    }
+
+    #[test]
+    fn keys_ids_as_they_appear_in_paths() {
+        assert_eq!(entity_id(&json!({ "id": 7 })), Some("7".to_string()));
+        assert_eq!(entity_id(&json!({ "id": "a-b" })), Some("a-b".to_string()));
+        assert_eq!(entity_id(&json!({ "id": null })), None);
+        assert_eq!(entity_id(&json!({ "name": "x" })), None);
+    }
+
+    #[test]
+    fn replaces_missing_or_taken_ids() {
+        let entities: HashMap<String, Value> = [("3", json!({})), ("10", json!({}))]
+            .into_iter()
+            .map(|(id, entity)| (id.to_string(), entity))
+            .collect();
+        let uuid = || "uuid".to_string();
+
+        assert_eq!(free_id(&entities, Some(&json!(4)), uuid), json!(4));
+        assert_eq!(free_id(&entities, Some(&json!(3)), uuid), json!(11));
+        assert_eq!(free_id(&entities, Some(&json!("10")), uuid), json!("uuid"));
+        assert_eq!(free_id(&entities, None, uuid), json!("uuid"));
+    }
+
+    #[test]
+    fn lists_entities_in_id_order() {
+        let mut collections = Collections::new();
+        let users = collections.entry("/users".to_string()).or_default();
+        for id in ["10", "9", "b", "a"] {
+            users.insert(id.to_string(), json!(id));
+        }
+
+        assert_eq!(
+            list(&collections, "/users"),
+            [json!("9"), json!("10"), json!("a"), json!("b")]
+        );
+        assert!(list(&collections, "/orders").is_empty());
+    }
+}
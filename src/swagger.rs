@@ -1,52 +1,573 @@
-use std::collections::HashMap;
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
 
+use log::warn;
+use regex::Regex;
 use serde_json::Value;
 
-use crate::MockServerError;
+use crate::{config::RouteTable, MockServerError};
+
+pub(crate) const HTTP_METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// Longest chain of `$ref`s followed to reach an inline object.
+const MAX_REF_CHAIN: usize = 32;
 
 #[derive(Debug)]
 pub struct SwaggerState {
     pub components: HashMap<String, Value>,
+    /// The whole spec, for refs outside `components/schemas`.
+    pub document: Value,
+    /// Local files referenced by the spec, keyed by their path relative to
+    /// the spec. Their own internal refs are rewritten to `file#/...`.
+    pub external: HashMap<String, Value>,
+    /// Paths of the local files the spec's refs point at, including those
+    /// that failed to load, for `--watch`.
+    pub external_files: Vec<PathBuf>,
+    /// `pattern` and `patternProperties` regexes of every schema, compiled
+    /// once and keyed by their source.
+    pub patterns: HashMap<String, Regex>,
+}
+
+/// The spec a server answers from, swapped as a whole when `--watch`
+/// reloads it. Requests work on the snapshot taken when they arrive.
+pub type SharedSwagger = RwLock<Arc<SwaggerState>>;
+
+/// The current spec of `shared`.
+pub fn swagger_snapshot(shared: &SharedSwagger) -> Arc<SwaggerState> {
+    match shared.read() {
+        Ok(guard) => guard.clone(),
+        Err(poisoned) => poisoned.into_inner().clone(),
+    }
 }
 
 impl SwaggerState {
+    /// Builds the state for `swagger`, loading the local files its refs
+    /// point at when `source` is a file path. Fails on schema patterns that
+    /// are not valid regexes.
+    pub fn from_spec(swagger: &Value, source: &SpecSource) -> Result<Self, MockServerError> {
+        let (state, invalid) = Self::load(swagger, source);
+        if !invalid.is_empty() {
+            return Err(MockServerError::Config(format!(
+                "{}: {}",
+                source,
+                invalid.join("; ")
+            )));
+        }
+        Ok(state)
+    }
+
+    /// Like `from_spec`, but returns the state along with a description of
+    /// each invalid pattern, which it leaves uncompiled.
+    pub fn load(swagger: &Value, source: &SpecSource) -> (Self, Vec<String>) {
+        let mut state = Self {
+            components: component_section(swagger, "schemas"),
+            document: swagger.clone(),
+            external: HashMap::new(),
+            external_files: Vec::new(),
+            patterns: HashMap::new(),
+        };
+        // Refs of a spec split across files are relative to the first one.
+        if let Some(first) = source.files().first() {
+            let base = first.parent().unwrap_or(Path::new(""));
+            state.load_external_refs(base);
+        }
+
+        let mut sources = Vec::new();
+        collect_patterns(&state.document, "", &mut sources);
+        for (file, document) in &state.external {
+            collect_patterns(document, file, &mut sources);
+        }
+        let mut invalid = Vec::new();
+        for (pointer, pattern) in sources {
+            if state.patterns.contains_key(&pattern) {
+                continue;
+            }
+            match Regex::new(&pattern) {
+                Ok(regex) => {
+                    state.patterns.insert(pattern, regex);
+                }
+                Err(e) => invalid.push(format!("invalid pattern at {}: {}", pointer, e)),
+            }
+        }
+        (state, invalid)
+    }
+
+    /// Compiles `patterns` too, such as the `regex` field patterns of the
+    /// config. Invalid ones are left out, to be compiled (and fail) on use.
+    pub fn add_patterns<'p>(&mut self, patterns: impl IntoIterator<Item = &'p str>) {
+        for pattern in patterns {
+            if self.patterns.contains_key(pattern) {
+                continue;
+            }
+            if let Ok(regex) = Regex::new(pattern) {
+                self.patterns.insert(pattern.to_string(), regex);
+            }
+        }
+    }
+
+    /// The compiled regex for `pattern`, compiling it on the spot for
+    /// patterns that are not in the spec.
+    pub fn regex(&self, pattern: &str) -> Result<Cow<'_, Regex>, regex::Error> {
+        match self.patterns.get(pattern) {
+            Some(regex) => Ok(Cow::Borrowed(regex)),
+            None => Regex::new(pattern).map(Cow::Owned),
+        }
+    }
+
     pub fn resolve_ref(&self, ref_path: &str) -> Option<Value> {
-        let schema_name = ref_path.replace("#/components/schemas/", "");
-        self.components.get(&schema_name).cloned()
+        self.lookup(ref_path).cloned()
+    }
+
+    /// Follows `$ref`s until reaching an inline object, e.g. a response,
+    /// parameter or request body. Unresolvable or cyclic refs give `None`.
+    pub fn resolve_object<'a>(&'a self, mut object: &'a Value) -> Option<&'a Value> {
+        for _ in 0..MAX_REF_CHAIN {
+            let Some(ref_path) = object.get("$ref").and_then(Value::as_str) else {
+                return Some(object);
+            };
+            object = self.lookup(ref_path)?;
+        }
+        None
+    }
+
+    /// The value `ref_path` points at: a `#/...` JSON pointer into the spec,
+    /// or `file#/...` into a loaded local file.
+    fn lookup(&self, ref_path: &str) -> Option<&Value> {
+        if let Some(name) = ref_path.strip_prefix("#/components/schemas/") {
+            if let Some(schema) = self.components.get(name) {
+                return Some(schema);
+            }
+        }
+
+        let (file, fragment) = ref_path.split_once('#').unwrap_or((ref_path, ""));
+        let document = match file {
+            "" => &self.document,
+            file => self.external.get(&join_ref_path("", file))?,
+        };
+        match fragment {
+            "" => Some(document),
+            pointer => document.pointer(pointer),
+        }
+    }
+
+    /// Loads every local file referenced from the spec, and the files those
+    /// reference in turn.
+    fn load_external_refs(&mut self, base: &Path) {
+        let mut pending = Vec::new();
+        collect_external_refs(&self.document, &mut pending);
+
+        while let Some(file) = pending.pop() {
+            let path = base.join(&file);
+            if self.external.contains_key(&file) || self.external_files.contains(&path) {
+                continue;
+            }
+            self.external_files.push(path.clone());
+            let mut document = match read_spec_file(&path) {
+                Ok(document) => document,
+                Err(e) => {
+                    warn!("Cannot load referenced file {}: {}", file, e);
+                    continue;
+                }
+            };
+
+            let dir = Path::new(&file)
+                .parent()
+                .map(|dir| dir.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            qualify_refs(&mut document, &file, &dir);
+            collect_external_refs(&document, &mut pending);
+            self.external.insert(file, document);
+        }
     }
 }
 
-pub async fn parse_swagger(url: &str) -> Result<SwaggerState, MockServerError> {
-    let swagger: Value = if url.starts_with("http") {
-        reqwest::get(url).await?.json().await?
+/// Local file parts of the refs in `value`, relative to the spec.
+fn collect_external_refs(value: &Value, files: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            if let Some(ref_path) = map.get("$ref").and_then(Value::as_str) {
+                let file = ref_path.split('#').next().unwrap_or_default();
+                if !file.is_empty() && !file.starts_with("http") {
+                    files.push(join_ref_path("", file));
+                }
+            }
+            for child in map.values() {
+                collect_external_refs(child, files);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                collect_external_refs(item, files);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Rewrites the refs of a file loaded from `file` so they resolve from the
+/// spec: `#/Foo` becomes `file#/Foo` and `other.yaml#/Foo` is taken relative
+/// to `dir`, the file's directory.
+fn qualify_refs(value: &mut Value, file: &str, dir: &str) {
+    match value {
+        Value::Object(map) => {
+            if let Some(Value::String(ref_path)) = map.get_mut("$ref") {
+                *ref_path = match ref_path.split_once('#') {
+                    Some(("", fragment)) => format!("{}#{}", file, fragment),
+                    Some((other, fragment)) if !other.starts_with("http") => {
+                        format!("{}#{}", join_ref_path(dir, other), fragment)
+                    }
+                    None if !ref_path.starts_with("http") => join_ref_path(dir, ref_path),
+                    _ => ref_path.clone(),
+                };
+            }
+            for child in map.values_mut() {
+                qualify_refs(child, file, dir);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                qualify_refs(item, file, dir);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// `file` relative to `dir`, with `./` and `dir/../` steps folded away so
+/// the same file always gets the same key.
+fn join_ref_path(dir: &str, file: &str) -> String {
+    let mut segments: Vec<&str> = dir.split('/').filter(|s| !s.is_empty()).collect();
+    for segment in file.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." if segments.last().is_some_and(|last| *last != "..") => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
+
+/// Reads a spec or referenced file as JSON, or YAML for `.yaml`/`.yml`.
+fn read_spec_file(path: &Path) -> Result<Value, Box<dyn std::error::Error>> {
+    let content = std::fs::read_to_string(path)?;
+    if path
+        .extension()
+        .is_some_and(|ext| ext == "yaml" || ext == "yml")
+    {
+        Ok(serde_yaml::from_str(&content)?)
     } else {
-        serde_json::from_str(&std::fs::read_to_string(url)?)?
-    };
+        Ok(serde_json::from_str(&content)?)
+    }
+}
 
-    let components = swagger
+fn component_section(swagger: &Value, section: &str) -> HashMap<String, Value> {
+    swagger
         .get("components")
-        .and_then(|c| c.get("schemas"))
-        .and_then(|schemas| schemas.as_object())
-        .map(|schemas| {
-            schemas
+        .and_then(|c| c.get(section))
+        .and_then(Value::as_object)
+        .map(|entries| {
+            entries
                 .iter()
                 .map(|(k, v)| (k.clone(), v.clone()))
                 .collect()
         })
+        .unwrap_or_default()
+}
+
+pub async fn parse_swagger(url: &str) -> Result<SwaggerState, MockServerError> {
+    let source = SpecSource::from(url);
+    let swagger = crate::fetch_swagger(&source).await?;
+
+    SwaggerState::from_spec(&swagger, &source)
+}
+
+/// Where a spec is read from.
+#[derive(Debug, Clone)]
+pub enum SpecSource {
+    /// A spec served over HTTP(S).
+    Url(String),
+    /// Spec files and directories of them, merged into one spec.
+    Files(Vec<PathBuf>),
+}
+
+impl From<&str> for SpecSource {
+    /// A URL for `http(s)` sources, else a single file or directory.
+    fn from(source: &str) -> Self {
+        if source.starts_with("http") {
+            SpecSource::Url(source.to_string())
+        } else {
+            SpecSource::Files(vec![PathBuf::from(source)])
+        }
+    }
+}
+
+impl std::fmt::Display for SpecSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpecSource::Url(url) => f.write_str(url),
+            SpecSource::Files(paths) => {
+                let paths: Vec<String> = paths
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect();
+                f.write_str(&paths.join(", "))
+            }
+        }
+    }
+}
+
+impl SpecSource {
+    /// Local files making up the spec: each listed file, and the JSON and
+    /// YAML files of each listed directory. None for URLs.
+    pub fn files(&self) -> Vec<PathBuf> {
+        match self {
+            SpecSource::Url(_) => Vec::new(),
+            SpecSource::Files(paths) => paths
+                .iter()
+                .flat_map(|path| match path.is_dir() {
+                    true => dir_spec_files(path),
+                    false => vec![path.clone()],
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The JSON and YAML files of `dir`, sorted by name.
+fn dir_spec_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|file| {
+            file.extension()
+                .is_some_and(|ext| ext == "json" || ext == "yaml" || ext == "yml")
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// Combines the documents of a spec split across files: the first file's
+/// document with the `paths` and `components` of the others added. On a
+/// collision the first definition is kept.
+pub fn merge_specs(mut specs: Vec<(PathBuf, Value)>) -> Value {
+    if specs.is_empty() {
+        return Value::Null;
+    }
+    let (first_file, mut merged) = specs.remove(0);
+    let mut owners: HashMap<String, PathBuf> = HashMap::new();
+    for path in merged
+        .get("paths")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+        .map(|(path, _)| path)
+    {
+        owners.insert(path.clone(), first_file.clone());
+    }
+
+    for (file, spec) in specs {
+        for (path, item) in spec
+            .get("paths")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+        {
+            if let Some(owner) = owners.get(path) {
+                warn!(
+                    "Path {} in {} is already defined in {}, keeping the first",
+                    path,
+                    file.display(),
+                    owner.display()
+                );
+                continue;
+            }
+            let Some(paths) = object_entry(&mut merged, "paths") else {
+                warn!(
+                    "Paths of {} are not an object, skipping {}",
+                    first_file.display(),
+                    path
+                );
+                continue;
+            };
+            owners.insert(path.clone(), file.clone());
+            paths.insert(path.clone(), item.clone());
+        }
+
+        for (section, entries) in spec
+            .get("components")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+        {
+            for (name, component) in entries.as_object().into_iter().flatten() {
+                match merged.pointer(&format!("/components/{}/{}", section, name)) {
+                    Some(existing) if existing != component => warn!(
+                        "Component {}/{} in {} differs from an earlier file's, keeping the first",
+                        section,
+                        name,
+                        file.display()
+                    ),
+                    Some(_) => {}
+                    None => match object_entry(&mut merged, "components")
+                        .and_then(|components| object_entry_in(components, section))
+                    {
+                        Some(entries) => {
+                            entries.insert(name.clone(), component.clone());
+                        }
+                        None => warn!(
+                            "Components of {} are not an object, skipping {}/{}",
+                            first_file.display(),
+                            section,
+                            name
+                        ),
+                    },
+                }
+            }
+        }
+    }
+    merged
+}
+
+/// The object under `key` of the object `value`, added when missing or
+/// `null`. `None` when either is something else.
+fn object_entry<'a>(
+    value: &'a mut Value,
+    key: &str,
+) -> Option<&'a mut serde_json::Map<String, Value>> {
+    object_entry_in(value.as_object_mut()?, key)
+}
+
+fn object_entry_in<'a>(
+    object: &'a mut serde_json::Map<String, Value>,
+    key: &str,
+) -> Option<&'a mut serde_json::Map<String, Value>> {
+    let entry = object.entry(key).or_insert(Value::Null);
+    if entry.is_null() {
+        *entry = Value::Object(serde_json::Map::new());
+    }
+    entry.as_object_mut()
+}
+
+/// Splits an array schema into its positional (`prefixItems`, or the legacy
+/// array form of `items`) schemas and the schema applying to the remaining
+/// items. A `false` rest schema forbids extra items.
+pub fn tuple_items(schema: &serde_json::Map<String, Value>) -> (&[Value], Option<&Value>) {
+    if let Some(prefix) = schema.get("prefixItems").and_then(Value::as_array) {
+        return (prefix, schema.get("items"));
+    }
+
+    match schema.get("items") {
+        Some(Value::Array(prefix)) => (prefix, schema.get("additionalItems")),
+        items => (&[], items),
+    }
+}
+
+/// Discriminator values of a `oneOf`/`anyOf` union with the variant each
+/// selects: the discriminator's `mapping`, or else the name of every
+/// referenced variant schema.
+pub fn discriminator_choices(
+    schema: &serde_json::Map<String, Value>,
+    variants: &[Value],
+) -> Vec<(String, Value)> {
+    let mapped: Vec<(String, Value)> = schema
+        .get("discriminator")
+        .and_then(|d| d.get("mapping"))
+        .and_then(Value::as_object)
+        .map(|mapping| {
+            mapping
+                .iter()
+                .filter_map(|(value, target)| {
+                    let target = target.as_str()?;
+                    Some((value.clone(), serde_json::json!({ "$ref": target })))
+                })
+                .collect()
+        })
         .unwrap_or_default();
+    if !mapped.is_empty() {
+        return mapped;
+    }
+
+    variants
+        .iter()
+        .filter_map(|variant| {
+            let ref_path = variant.get("$ref").and_then(Value::as_str)?;
+            let name = ref_path.rsplit('/').next()?;
+            Some((name.to_string(), variant.clone()))
+        })
+        .collect()
+}
+
+/// Lower bound of a number schema as (limit, exclusive), from `minimum` and
+/// the boolean (OpenAPI 3.0) or numeric (3.1) form of `exclusiveMinimum`.
+pub fn lower_bound(schema: &serde_json::Map<String, Value>) -> Option<(f64, bool)> {
+    number_bound(
+        schema,
+        "minimum",
+        "exclusiveMinimum",
+        |inclusive, exclusive| inclusive > exclusive,
+    )
+}
+
+/// Upper bound of a number schema as (limit, exclusive), from `maximum` and
+/// `exclusiveMaximum`.
+pub fn upper_bound(schema: &serde_json::Map<String, Value>) -> Option<(f64, bool)> {
+    number_bound(
+        schema,
+        "maximum",
+        "exclusiveMaximum",
+        |inclusive, exclusive| inclusive < exclusive,
+    )
+}
 
-    Ok(SwaggerState { components })
+/// With both an inclusive limit and a numeric exclusive one, the tighter
+/// wins; `inclusive_wins(inclusive, exclusive)` tells which that is.
+fn number_bound(
+    schema: &serde_json::Map<String, Value>,
+    inclusive_key: &str,
+    exclusive_key: &str,
+    inclusive_wins: fn(f64, f64) -> bool,
+) -> Option<(f64, bool)> {
+    let inclusive = schema.get(inclusive_key).and_then(Value::as_f64);
+    match schema.get(exclusive_key) {
+        Some(Value::Bool(exclusive)) => inclusive.map(|limit| (limit, *exclusive)),
+        Some(exclusive) => match (inclusive, exclusive.as_f64()) {
+            (Some(inclusive), Some(exclusive)) if inclusive_wins(inclusive, exclusive) => {
+                Some((inclusive, false))
+            }
+            (_, Some(exclusive)) => Some((exclusive, true)),
+            (inclusive, None) => inclusive.map(|limit| (limit, false)),
+        },
+        None => inclusive.map(|limit| (limit, false)),
+    }
 }
 
-pub fn process_swagger_paths(swagger: &Value) -> HashMap<String, Vec<(String, Value)>> {
-    let mut routes = HashMap::new();
+pub fn process_swagger_paths(swagger: &Value) -> RouteTable {
+    let mut routes = RouteTable::new();
 
     if let Some(paths) = swagger.get("paths").and_then(Value::as_object) {
         for (path, methods) in paths {
             if let Some(method_map) = methods.as_object() {
+                let shared_parameters = methods.get("parameters").and_then(Value::as_array);
                 let path_handlers = method_map
                     .iter()
-                    .map(|(method, definition)| (method.to_uppercase(), definition.clone()))
+                    .filter(|(method, _)| HTTP_METHODS.contains(&method.to_lowercase().as_str()))
+                    .map(|(method, definition)| {
+                        let mut definition = definition.clone();
+                        if let Some(shared) = shared_parameters {
+                            inherit_parameters(swagger, &mut definition, shared);
+                        }
+                        (method.to_uppercase(), definition)
+                    })
                     .collect();
                 routes.insert(path.clone(), path_handlers);
             }
@@ -55,3 +576,191 @@ pub fn process_swagger_paths(swagger: &Value) -> HashMap<String, Vec<(String, Va
 
     routes
 }
+
+/// Adds the parameters declared on the path item to an operation, unless
+/// the operation overrides them (same `name` and `in`, after following
+/// `$ref`s within `swagger`).
+fn inherit_parameters(swagger: &Value, operation: &mut Value, shared: &[Value]) {
+    let key = |param: &Value| parameter_key(swagger, param);
+    let Some(operation) = operation.as_object_mut() else {
+        return;
+    };
+    let parameters = operation
+        .entry("parameters")
+        .or_insert_with(|| Value::Array(Vec::new()));
+    if let Some(parameters) = parameters.as_array_mut() {
+        for param in shared {
+            if !parameters.iter().any(|own| key(own) == key(param)) {
+                parameters.push(param.clone());
+            }
+        }
+    }
+}
+
+/// What tells parameters apart: `name` and `in`, or for a `$ref` that does
+/// not resolve within `swagger` (e.g. into another file), the ref itself.
+fn parameter_key(swagger: &Value, param: &Value) -> (Option<Value>, Option<Value>) {
+    let mut resolved = param;
+    for _ in 0..MAX_REF_CHAIN {
+        let Some(ref_path) = resolved.get("$ref").and_then(Value::as_str) else {
+            break;
+        };
+        match ref_path
+            .strip_prefix('#')
+            .and_then(|pointer| swagger.pointer(pointer))
+        {
+            Some(target) => resolved = target,
+            None => return (None, Some(Value::String(ref_path.to_string()))),
+        }
+    }
+    (resolved.get("name").cloned(), resolved.get("in").cloned())
+}
+
+/// Drops routes whose path doesn't match any `only` glob (when given) or
+/// matches an `exclude` glob. Returns how many were removed.
+pub fn filter_routes(routes: &mut RouteTable, only: &[String], exclude: &[String]) -> usize {
+    let before = routes.len();
+    routes.retain(|path, _| {
+        (only.is_empty() || only.iter().any(|glob| glob_matches(glob, path)))
+            && !exclude.iter().any(|glob| glob_matches(glob, path))
+    });
+    before - routes.len()
+}
+
+/// Matches a path against a glob where `*` stays within one segment, `**`
+/// spans segments and `?` is any single character. A trailing `/**` also
+/// matches the parent path itself.
+pub fn glob_matches(glob: &str, path: &str) -> bool {
+    if let Some(parent) = glob.strip_suffix("/**") {
+        if glob_matches(parent, path) {
+            return true;
+        }
+    }
+    glob_match_bytes(glob.as_bytes(), path.as_bytes())
+}
+
+fn glob_match_bytes(glob: &[u8], path: &[u8]) -> bool {
+    match glob {
+        [] => path.is_empty(),
+        [b'*', b'*', rest @ ..] => {
+            (0..=path.len()).any(|skip| glob_match_bytes(rest, &path[skip..]))
+        }
+        [b'*', rest @ ..] => (0..=path.len())
+            .take_while(|&skip| skip == 0 || path[skip - 1] != b'/')
+            .any(|skip| glob_match_bytes(rest, &path[skip..])),
+        [b'?', rest @ ..] => !path.is_empty() && glob_match_bytes(rest, &path[1..]),
+        [c, rest @ ..] => path.first() == Some(c) && glob_match_bytes(rest, &path[1..]),
+    }
+}
+
+/// Groups of route paths that are equal when compared case-insensitively.
+pub fn find_case_duplicates(routes: &RouteTable) -> Vec<Vec<&str>> {
+    let mut by_lowercase: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    for path in routes.keys() {
+        by_lowercase
+            .entry(path.to_lowercase())
+            .or_default()
+            .push(path);
+    }
+
+    by_lowercase
+        .into_values()
+        .filter(|paths| paths.len() > 1)
+        .collect()
+}
+
+/// Lists `(location, format)` for every schema `format` not in `known`, with
+/// the location as a JSON pointer into the spec.
+pub fn find_unknown_formats(swagger: &Value, known: &[&str]) -> Vec<(String, String)> {
+    let mut unknown = Vec::new();
+    collect_unknown_formats(swagger, "", known, &mut unknown);
+    unknown
+}
+
+/// Lists `(location, target)` for every `$ref` in the spec, skipping example
+/// and default values.
+pub fn find_refs(swagger: &Value) -> Vec<(String, String)> {
+    let mut refs = Vec::new();
+    collect_refs(swagger, "", &mut refs);
+    refs
+}
+
+fn collect_refs(node: &Value, pointer: &str, refs: &mut Vec<(String, String)>) {
+    match node {
+        Value::Object(map) => {
+            if let Some(target) = map.get("$ref").and_then(Value::as_str) {
+                refs.push((format!("{}/$ref", pointer), target.to_string()));
+            }
+            for (key, child) in map {
+                if matches!(key.as_str(), "example" | "default" | "enum") {
+                    continue;
+                }
+                let escaped = key.replace('~', "~0").replace('/', "~1");
+                collect_refs(child, &format!("{}/{}", pointer, escaped), refs);
+            }
+        }
+        Value::Array(items) => {
+            for (index, child) in items.iter().enumerate() {
+                collect_refs(child, &format!("{}/{}", pointer, index), refs);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Pointer and source of each `pattern` and `patternProperties` key under
+/// `node`, skipping example and default values.
+fn collect_patterns(node: &Value, pointer: &str, patterns: &mut Vec<(String, String)>) {
+    match node {
+        Value::Object(map) => {
+            if let Some(pattern) = map.get("pattern").and_then(Value::as_str) {
+                patterns.push((format!("{}/pattern", pointer), pattern.to_string()));
+            }
+            if let Some(properties) = map.get("patternProperties").and_then(Value::as_object) {
+                for pattern in properties.keys() {
+                    patterns.push((format!("{}/patternProperties", pointer), pattern.clone()));
+                }
+            }
+            for (key, child) in map {
+                if matches!(key.as_str(), "example" | "examples" | "default" | "enum") {
+                    continue;
+                }
+                let escaped = key.replace('~', "~0").replace('/', "~1");
+                collect_patterns(child, &format!("{}/{}", pointer, escaped), patterns);
+            }
+        }
+        Value::Array(items) => {
+            for (index, child) in items.iter().enumerate() {
+                collect_patterns(child, &format!("{}/{}", pointer, index), patterns);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn collect_unknown_formats(
+    node: &Value,
+    pointer: &str,
+    known: &[&str],
+    unknown: &mut Vec<(String, String)>,
+) {
+    match node {
+        Value::Object(map) => {
+            if let Some(format) = map.get("format").and_then(Value::as_str) {
+                if map.contains_key("type") && !known.contains(&format) {
+                    unknown.push((format!("{}/format", pointer), format.to_string()));
+                }
+            }
+            for (key, child) in map {
+                let escaped = key.replace('~', "~0").replace('/', "~1");
+                collect_unknown_formats(child, &format!("{}/{}", pointer, escaped), known, unknown);
+            }
+        }
+        Value::Array(items) => {
+            for (index, child) in items.iter().enumerate() {
+                collect_unknown_formats(child, &format!("{}/{}", pointer, index), known, unknown);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,560 @@
+use serde_json::{json, Map, Value};
+
+use crate::swagger::HTTP_METHODS;
+
+/// Parameter keywords that move into `schema` in OpenAPI 3.
+const SCHEMA_KEYWORDS: &[&str] = &[
+    "type",
+    "format",
+    "items",
+    "enum",
+    "default",
+    "minimum",
+    "maximum",
+    "exclusiveMinimum",
+    "exclusiveMaximum",
+    "multipleOf",
+    "minLength",
+    "maxLength",
+    "pattern",
+    "minItems",
+    "maxItems",
+    "uniqueItems",
+];
+
+/// Whether `spec` is a Swagger 2.0 document.
+pub fn is_swagger2(spec: &Value) -> bool {
+    spec.get("swagger")
+        .and_then(Value::as_str)
+        .is_some_and(|version| version.starts_with('2'))
+}
+
+/// Translates a Swagger 2.0 document into the OpenAPI 3.0 shapes the rest
+/// of spit reads: `definitions` become `components/schemas`, body and form
+/// parameters become `requestBody`, and response schemas move under
+/// `content` for each type the operation produces.
+pub fn upgrade(spec: &Value) -> Value {
+    let global_consumes = media_types(spec.get("consumes"));
+    let global_produces = media_types(spec.get("produces"));
+    let shared_parameters = spec.get("parameters").and_then(Value::as_object);
+
+    let mut components = Map::new();
+    if let Some(definitions) = spec.get("definitions") {
+        components.insert("schemas".to_string(), definitions.clone());
+    }
+    if let Some(parameters) = shared_parameters {
+        let parameters: Map<String, Value> = parameters
+            .iter()
+            .filter(|(_, param)| !is_body_param(param))
+            .map(|(name, param)| (name.clone(), upgrade_parameter(param)))
+            .collect();
+        components.insert("parameters".to_string(), Value::Object(parameters));
+    }
+    if let Some(responses) = spec.get("responses").and_then(Value::as_object) {
+        let responses: Map<String, Value> = responses
+            .iter()
+            .map(|(name, response)| (name.clone(), upgrade_response(response, &global_produces)))
+            .collect();
+        components.insert("responses".to_string(), Value::Object(responses));
+    }
+    if let Some(schemes) = spec.get("securityDefinitions") {
+        components.insert("securitySchemes".to_string(), schemes.clone());
+    }
+
+    let mut paths = Map::new();
+    for (path, item) in spec
+        .get("paths")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+    {
+        let Some(item) = item.as_object() else {
+            continue;
+        };
+        let path_parameters = resolve_parameters(
+            item.get("parameters").and_then(Value::as_array),
+            shared_parameters,
+        );
+
+        let mut upgraded = Map::new();
+        for (key, value) in item {
+            if HTTP_METHODS.contains(&key.to_lowercase().as_str()) {
+                let operation = upgrade_operation(
+                    value,
+                    &path_parameters,
+                    shared_parameters,
+                    &global_consumes,
+                    &global_produces,
+                );
+                upgraded.insert(key.clone(), operation);
+            } else if key != "parameters" {
+                upgraded.insert(key.clone(), value.clone());
+            }
+        }
+        let plain: Vec<Value> = path_parameters
+            .iter()
+            .filter(|param| !is_body_param(param) && !is_form_param(param))
+            .map(upgrade_parameter)
+            .collect();
+        if !plain.is_empty() {
+            upgraded.insert("parameters".to_string(), Value::Array(plain));
+        }
+        paths.insert(path.clone(), Value::Object(upgraded));
+    }
+
+    let mut upgraded = json!({
+        "openapi": "3.0.3",
+        "info": spec.get("info").cloned().unwrap_or_else(|| json!({})),
+        "paths": paths,
+        "components": components,
+    });
+    if let Some(host) = spec.get("host").and_then(Value::as_str) {
+        let scheme = spec
+            .pointer("/schemes/0")
+            .and_then(Value::as_str)
+            .unwrap_or("https");
+        let base_path = spec.get("basePath").and_then(Value::as_str).unwrap_or("");
+        upgraded["servers"] = json!([{ "url": format!("{}://{}{}", scheme, host, base_path) }]);
+    }
+    for key in ["x-spit-config", "tags", "security", "externalDocs"] {
+        if let Some(value) = spec.get(key) {
+            upgraded[key] = value.clone();
+        }
+    }
+
+    upgrade_schemas(&mut upgraded);
+    upgraded
+}
+
+fn upgrade_operation(
+    operation: &Value,
+    path_parameters: &[Value],
+    shared_parameters: Option<&Map<String, Value>>,
+    global_consumes: &[String],
+    global_produces: &[String],
+) -> Value {
+    let Some(source) = operation.as_object() else {
+        return operation.clone();
+    };
+    let consumes = media_types(source.get("consumes"));
+    let consumes = if consumes.is_empty() {
+        global_consumes
+    } else {
+        &consumes
+    };
+    let produces = media_types(source.get("produces"));
+    let produces = if produces.is_empty() {
+        global_produces
+    } else {
+        &produces
+    };
+
+    // Path-level parameters apply unless the operation redefines them.
+    let mut parameters = resolve_parameters(
+        source.get("parameters").and_then(Value::as_array),
+        shared_parameters,
+    );
+    let key = |param: &Value| (param.get("name").cloned(), param.get("in").cloned());
+    for param in path_parameters {
+        if !parameters.iter().any(|own| key(own) == key(param)) {
+            parameters.push(param.clone());
+        }
+    }
+
+    let mut operation = Map::new();
+    for (key, value) in source {
+        match key.as_str() {
+            "consumes" | "produces" | "parameters" | "responses" | "schemes" => {}
+            _ => {
+                operation.insert(key.clone(), value.clone());
+            }
+        }
+    }
+
+    let plain: Vec<Value> = parameters
+        .iter()
+        .filter(|param| !is_body_param(param) && !is_form_param(param))
+        .map(upgrade_parameter)
+        .collect();
+    if !plain.is_empty() {
+        operation.insert("parameters".to_string(), Value::Array(plain));
+    }
+    if let Some(body) = request_body(&parameters, consumes) {
+        operation.insert("requestBody".to_string(), body);
+    }
+
+    let responses: Map<String, Value> = source
+        .get("responses")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+        .map(|(status, response)| (status.clone(), upgrade_response(response, produces)))
+        .collect();
+    operation.insert("responses".to_string(), Value::Object(responses));
+
+    Value::Object(operation)
+}
+
+/// The `requestBody` for a `body` parameter, or for the `formData` ones as
+/// an object schema.
+fn request_body(parameters: &[Value], consumes: &[String]) -> Option<Value> {
+    if let Some(body) = parameters.iter().find(|param| is_body_param(param)) {
+        let schema = body.get("schema").cloned().unwrap_or_else(|| json!({}));
+        let types = if consumes.is_empty() {
+            vec!["application/json".to_string()]
+        } else {
+            consumes.to_vec()
+        };
+        let content: Map<String, Value> = types
+            .into_iter()
+            .map(|media_type| (media_type, json!({ "schema": schema })))
+            .collect();
+        let mut request_body = json!({
+            "content": content,
+            "required": body.get("required").cloned().unwrap_or(json!(false)),
+        });
+        if let Some(description) = body.get("description") {
+            request_body["description"] = description.clone();
+        }
+        return Some(request_body);
+    }
+
+    let fields: Vec<&Value> = parameters
+        .iter()
+        .filter(|param| is_form_param(param))
+        .collect();
+    if fields.is_empty() {
+        return None;
+    }
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    for field in fields {
+        let Some(name) = field.get("name").and_then(Value::as_str) else {
+            continue;
+        };
+        properties.insert(name.to_string(), parameter_schema(field));
+        if field.get("required").and_then(Value::as_bool) == Some(true) {
+            required.push(json!(name));
+        }
+    }
+    let media_type = consumes
+        .iter()
+        .find(|media_type| media_type.as_str() == "multipart/form-data")
+        .map_or("application/x-www-form-urlencoded", String::as_str);
+    let mut schema = json!({ "type": "object", "properties": properties });
+    if !required.is_empty() {
+        schema["required"] = Value::Array(required);
+    }
+    Some(json!({
+        "content": { media_type: { "schema": schema } },
+        "required": schema.get("required").is_some(),
+    }))
+}
+
+fn upgrade_parameter(param: &Value) -> Value {
+    let Some(source) = param.as_object() else {
+        return param.clone();
+    };
+    if source.contains_key("$ref") {
+        return param.clone();
+    }
+
+    let mut upgraded: Map<String, Value> = source
+        .iter()
+        .filter(|(key, _)| {
+            !SCHEMA_KEYWORDS.contains(&key.as_str())
+                && !matches!(key.as_str(), "collectionFormat" | "allowEmptyValue")
+        })
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    upgraded.insert("schema".to_string(), parameter_schema(param));
+
+    if source.get("type").and_then(Value::as_str) == Some("array") {
+        let (style, explode) = match source.get("collectionFormat").and_then(Value::as_str) {
+            Some("multi") => ("form", true),
+            Some("ssv") => ("spaceDelimited", false),
+            Some("pipes") => ("pipeDelimited", false),
+            _ if source.get("in").and_then(Value::as_str) == Some("query") => ("form", false),
+            _ => ("simple", false),
+        };
+        upgraded.insert("style".to_string(), json!(style));
+        upgraded.insert("explode".to_string(), json!(explode));
+    }
+    Value::Object(upgraded)
+}
+
+/// The schema keywords of a non-body parameter, with `type: file` as a
+/// binary string.
+fn parameter_schema(param: &Value) -> Value {
+    let mut schema: Map<String, Value> = SCHEMA_KEYWORDS
+        .iter()
+        .filter_map(|key| Some((key.to_string(), param.get(*key)?.clone())))
+        .collect();
+    if schema.get("type").and_then(Value::as_str) == Some("file") {
+        schema.insert("type".to_string(), json!("string"));
+        schema.insert("format".to_string(), json!("binary"));
+    }
+    Value::Object(schema)
+}
+
+fn upgrade_response(response: &Value, produces: &[String]) -> Value {
+    let Some(source) = response.as_object() else {
+        return response.clone();
+    };
+    if source.contains_key("$ref") {
+        return response.clone();
+    }
+
+    let mut upgraded = Map::new();
+    upgraded.insert(
+        "description".to_string(),
+        source.get("description").cloned().unwrap_or(json!("")),
+    );
+
+    let examples = source.get("examples").and_then(Value::as_object);
+    if let Some(schema) = source.get("schema") {
+        let types = if produces.is_empty() {
+            vec!["application/json".to_string()]
+        } else {
+            produces.to_vec()
+        };
+        let content: Map<String, Value> = types
+            .into_iter()
+            .map(|media_type| {
+                let mut entry = json!({ "schema": schema });
+                if let Some(example) = examples.and_then(|examples| examples.get(&media_type)) {
+                    entry["example"] = example.clone();
+                }
+                (media_type, entry)
+            })
+            .collect();
+        upgraded.insert("content".to_string(), Value::Object(content));
+    }
+
+    if let Some(headers) = source.get("headers").and_then(Value::as_object) {
+        let headers: Map<String, Value> = headers
+            .iter()
+            .map(|(name, header)| {
+                let mut upgraded = json!({ "schema": parameter_schema(header) });
+                if let Some(description) = header.get("description") {
+                    upgraded["description"] = description.clone();
+                }
+                (name.clone(), upgraded)
+            })
+            .collect();
+        upgraded.insert("headers".to_string(), Value::Object(headers));
+    }
+
+    for (key, value) in source {
+        if key.starts_with("x-") {
+            upgraded.insert(key.clone(), value.clone());
+        }
+    }
+    Value::Object(upgraded)
+}
+
+/// Inlines `#/parameters/...` refs so body and form parameters can be told
+/// apart from the others.
+fn resolve_parameters(
+    parameters: Option<&Vec<Value>>,
+    shared: Option<&Map<String, Value>>,
+) -> Vec<Value> {
+    parameters
+        .into_iter()
+        .flatten()
+        .map(|param| {
+            param
+                .get("$ref")
+                .and_then(Value::as_str)
+                .and_then(|target| target.strip_prefix("#/parameters/"))
+                .and_then(|name| shared?.get(name))
+                .unwrap_or(param)
+                .clone()
+        })
+        .collect()
+}
+
+/// Rewrites refs to their OpenAPI 3 locations and the 2.0-only schema
+/// spellings (`x-nullable`, string `discriminator`) throughout `node`.
+fn upgrade_schemas(node: &mut Value) {
+    match node {
+        Value::Object(map) => {
+            if let Some(Value::String(target)) = map.get_mut("$ref") {
+                for (from, to) in [
+                    ("#/definitions/", "#/components/schemas/"),
+                    ("#/parameters/", "#/components/parameters/"),
+                    ("#/responses/", "#/components/responses/"),
+                ] {
+                    if let Some(name) = target.strip_prefix(from) {
+                        *target = format!("{}{}", to, name);
+                        break;
+                    }
+                }
+            }
+            if let Some(nullable) = map.remove("x-nullable") {
+                map.insert("nullable".to_string(), nullable);
+            }
+            if let Some(Value::String(property)) = map.get("discriminator") {
+                let discriminator = json!({ "propertyName": property });
+                map.insert("discriminator".to_string(), discriminator);
+            }
+            for (key, child) in map.iter_mut() {
+                if !matches!(key.as_str(), "example" | "examples" | "default" | "enum") {
+                    upgrade_schemas(child);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(upgrade_schemas),
+        _ => {}
+    }
+}
+
+fn media_types(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(Value::as_array)
+        .map(|types| {
+            types
+                .iter()
+                .filter_map(Value::as_str)
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn is_body_param(param: &Value) -> bool {
+    param.get("in").and_then(Value::as_str) == Some("body")
+}
+
+fn is_form_param(param: &Value) -> bool {
+    param.get("in").and_then(Value::as_str) == Some("formData")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn petstore() -> Value {
+        json!({
+            "swagger": "2.0",
+            "info": { "title": "Petstore", "version": "1" },
+            "host": "petstore.test",
+            "basePath": "/v1",
+            "produces": ["application/json"],
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "parameters": [
+                            { "name": "tags", "in": "query", "type": "array", "items": { "type": "string" }, "collectionFormat": "multi" },
+                            { "$ref": "#/parameters/Limit" }
+                        ],
+                        "responses": {
+                            "200": { "description": "ok", "schema": { "type": "array", "items": { "$ref": "#/definitions/Pet" } } }
+                        }
+                    },
+                    "post": {
+                        "parameters": [
+                            { "name": "pet", "in": "body", "required": true, "schema": { "$ref": "#/definitions/Pet" } }
+                        ],
+                        "responses": { "201": { "description": "created" } }
+                    }
+                },
+                "/pets/{petId}/photo": {
+                    "parameters": [{ "name": "petId", "in": "path", "required": true, "type": "integer" }],
+                    "put": {
+                        "consumes": ["multipart/form-data"],
+                        "parameters": [
+                            { "name": "file", "in": "formData", "required": true, "type": "file" }
+                        ],
+                        "responses": { "204": { "description": "stored" } }
+                    }
+                }
+            },
+            "parameters": {
+                "Limit": { "name": "limit", "in": "query", "type": "integer", "maximum": 100 }
+            },
+            "definitions": {
+                "Pet": {
+                    "type": "object",
+                    "required": ["name"],
+                    "properties": {
+                        "name": { "type": "string" },
+                        "owner": { "type": "string", "x-nullable": true }
+                    }
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn detects_swagger2() {
+        assert!(is_swagger2(&petstore()));
+        assert!(!is_swagger2(&json!({ "openapi": "3.0.3" })));
+    }
+
+    #[test]
+    fn moves_definitions_and_rewrites_refs() {
+        let spec = upgrade(&petstore());
+        assert_eq!(spec["openapi"], "3.0.3");
+        assert_eq!(
+            spec["servers"],
+            json!([{ "url": "https://petstore.test/v1" }])
+        );
+        assert_eq!(
+            spec.pointer("/components/schemas/Pet/properties/owner"),
+            Some(&json!({ "type": "string", "nullable": true }))
+        );
+        assert_eq!(
+            spec.pointer("/paths/~1pets/get/responses/200/content/application~1json/schema/items"),
+            Some(&json!({ "$ref": "#/components/schemas/Pet" }))
+        );
+    }
+
+    #[test]
+    fn upgrades_parameters_and_bodies() {
+        let spec = upgrade(&petstore());
+        let list = &spec["paths"]["/pets"]["get"];
+        assert_eq!(
+            list["parameters"],
+            json!([
+                {
+                    "name": "tags",
+                    "in": "query",
+                    "schema": { "type": "array", "items": { "type": "string" } },
+                    "style": "form",
+                    "explode": true
+                },
+                { "name": "limit", "in": "query", "schema": { "type": "integer", "maximum": 100 } }
+            ])
+        );
+
+        assert_eq!(
+            spec["paths"]["/pets"]["post"]["requestBody"],
+            json!({
+                "content": { "application/json": { "schema": { "$ref": "#/components/schemas/Pet" } } },
+                "required": true
+            })
+        );
+
+        let photo = &spec["paths"]["/pets/{petId}/photo"];
+        assert_eq!(
+            photo["parameters"],
+            json!([{ "name": "petId", "in": "path", "required": true, "schema": { "type": "integer" } }])
+        );
+        assert_eq!(
+            photo["put"]["requestBody"],
+            json!({
+                "content": {
+                    "multipart/form-data": {
+                        "schema": {
+                            "type": "object",
+                            "properties": { "file": { "type": "string", "format": "binary" } },
+                            "required": ["file"]
+                        }
+                    }
+                },
+                "required": true
+            })
+        );
+    }
+}
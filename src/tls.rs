@@ -0,0 +1,30 @@
+use std::{fs::File, io::BufReader, path::Path, sync::Arc};
+
+use rustls::ServerConfig;
+
+use crate::MockServerError;
+
+/// Reads the PEM certificate chain at `cert` and private key at `key` into
+/// a rustls server configuration.
+pub fn load_tls_config(cert: &Path, key: &Path) -> Result<ServerConfig, MockServerError> {
+    let pem_error = |path: &Path, e: &dyn std::fmt::Display| {
+        MockServerError::Config(format!("{}: {}", path.display(), e))
+    };
+
+    let certs = rustls_pemfile::certs(&mut BufReader::new(File::open(cert)?))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| pem_error(cert, &e))?;
+    if certs.is_empty() {
+        return Err(pem_error(cert, &"no PEM certificate found"));
+    }
+    let private_key = rustls_pemfile::private_key(&mut BufReader::new(File::open(key)?))
+        .map_err(|e| pem_error(key, &e))?
+        .ok_or_else(|| pem_error(key, &"no PEM private key found"))?;
+
+    ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| MockServerError::Config(format!("TLS: {}", e)))?
+        .with_no_client_auth()
+        .with_single_cert(certs, private_key)
+        .map_err(|e| pem_error(key, &e))
+}
@@ -0,0 +1,182 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use log::{error, info, warn};
+
+use crate::{
+    config::{Delay, MockConfig, MockState},
+    load_service,
+    swagger::{swagger_snapshot, SpecSource, SwaggerState},
+    MockService,
+};
+
+/// How often watched files are checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Reads the config again on reload, with the CLI overrides applied.
+pub type ConfigLoader = Box<dyn Fn() -> Result<MockConfig, Box<dyn std::error::Error>>>;
+
+/// `--watch` settings: the config file to watch next to the spec, and how to
+/// load it.
+pub struct Watch {
+    pub config_path: Option<PathBuf>,
+    pub load_config: ConfigLoader,
+}
+
+/// A spec watched for changes, with the service answering from it.
+pub struct WatchedSpec {
+    pub source: SpecSource,
+    pub service: MockService,
+}
+
+/// A spec mounted for a virtual host under `hosts`.
+pub struct WatchedHost {
+    pub host: String,
+    pub spec: WatchedSpec,
+}
+
+/// Polls the config file and the local files of the main spec and of each
+/// virtual host's spec (those of their directories listed afresh each time,
+/// and those their refs point at), and reloads every spec whenever one of
+/// them changes, appears or goes away. A spec or config that fails to load
+/// leaves all the previous ones in place.
+pub async fn watch_sources(
+    main: WatchedSpec,
+    hosts: Vec<WatchedHost>,
+    delay: Option<u64>,
+    watch: Watch,
+) {
+    let specs: Vec<&WatchedSpec> = std::iter::once(&main)
+        .chain(hosts.iter().map(|host| &host.spec))
+        .collect();
+    let mut seen = modification_times(&specs, &watch);
+    for (file, _) in &seen {
+        info!("Watching {} for changes", file.display());
+    }
+
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        interval.tick().await;
+        if modification_times(&specs, &watch) == seen {
+            continue;
+        }
+        reload(&main, &hosts, delay, &watch).await;
+        // Taken after the reload, which may have changed the referenced files.
+        seen = modification_times(&specs, &watch);
+    }
+}
+
+/// Each watched file with its modification time, `None` when it is missing.
+fn modification_times(specs: &[&WatchedSpec], watch: &Watch) -> Vec<(PathBuf, Option<SystemTime>)> {
+    let mut files: Vec<PathBuf> = watch.config_path.iter().cloned().collect();
+    for spec in specs {
+        files.extend(spec.source.files());
+        files.extend(
+            swagger_snapshot(&spec.service.1)
+                .external_files
+                .iter()
+                .cloned(),
+        );
+    }
+    files
+        .into_iter()
+        .map(|file| {
+            let modified = std::fs::metadata(&file)
+                .and_then(|meta| meta.modified())
+                .ok();
+            (file, modified)
+        })
+        .collect()
+}
+
+async fn reload(main: &WatchedSpec, hosts: &[WatchedHost], delay: Option<u64>, watch: &Watch) {
+    let mut config = match (watch.load_config)() {
+        Ok(config) => config,
+        Err(e) => {
+            error!("Failed to reload config, keeping the previous one: {}", e);
+            return;
+        }
+    };
+    if config.delay.is_none() {
+        config.delay = delay.map(Delay::Fixed);
+    }
+
+    let Some(fresh) = load(&main.source, &config).await else {
+        return;
+    };
+    let mounted: HashMap<&str, String> = hosts
+        .iter()
+        .map(|host| (host.host.as_str(), host.spec.source.to_string()))
+        .collect();
+    let configured: HashMap<&str, String> = fresh
+        .0
+        .config
+        .hosts
+        .iter()
+        .flatten()
+        .map(|(host, source)| (host.as_str(), source.clone()))
+        .collect();
+    if mounted != configured {
+        warn!("`hosts` changed; restart the server to mount the new hosts");
+    }
+
+    // Like at startup, the hosts are served with the main spec's config.
+    let mut fresh_hosts = Vec::with_capacity(hosts.len());
+    for host in hosts {
+        match load(&host.spec.source, &fresh.0.config).await {
+            Some(service) => fresh_hosts.push(service),
+            None => return,
+        }
+    }
+
+    swap(main, fresh);
+    for (host, fresh) in hosts.iter().zip(fresh_hosts) {
+        swap(&host.spec, fresh);
+    }
+}
+
+/// The freshly loaded service for `source`, `None` (logged) when it fails
+/// to load.
+async fn load(source: &SpecSource, config: &MockConfig) -> Option<(MockState, SwaggerState)> {
+    match load_service(source, config).await {
+        Ok(service) => Some(service),
+        Err(e) => {
+            error!(
+                "Failed to reload {}, keeping the previous spec: {}",
+                source, e
+            );
+            None
+        }
+    }
+}
+
+fn swap(spec: &WatchedSpec, (fresh, swagger): (MockState, SwaggerState)) {
+    let (state, swagger_state) = &spec.service;
+    // Requests take their spec snapshot under the state lock once they see
+    // `reloading` unset, so the spec is only swapped while it is set.
+    if let Ok(mut state) = state.write() {
+        state.reloading = true;
+    }
+    if let Ok(mut current) = swagger_state.write() {
+        *current = Arc::new(swagger);
+    }
+    if let Ok(mut state) = state.write() {
+        state.routes = fresh.routes;
+        state.router = fresh.router;
+        state.status_routes = fresh.status_routes;
+        state.config = fresh.config;
+        state.response_cache = fresh.response_cache;
+        state.entity_cache = fresh.entity_cache;
+        state.example_cursors = fresh.example_cursors;
+        state.reloading = false;
+        info!(
+            "Reloaded {} routes from {}",
+            state.routes.len(),
+            spec.source
+        );
+    }
+}
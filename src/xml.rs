@@ -0,0 +1,268 @@
+use serde_json::Value;
+
+use crate::swagger::SwaggerState;
+
+pub const XML_CONTENT_TYPE: &str = "application/xml";
+
+/// Media types of `Accept` entries asking for XML and JSON.
+const XML_MEDIA_TYPES: [&str; 2] = ["application/xml", "text/xml"];
+const JSON_MEDIA_TYPES: [&str; 3] = ["application/json", "application/*", "*/*"];
+
+/// Whether an `Accept` header prefers XML over JSON: by quality, then by
+/// order of appearance.
+pub fn prefers_xml(accept: &str) -> bool {
+    let mut best: Option<(f64, bool)> = None;
+    for entry in accept.split(',') {
+        let mut params = entry.split(';');
+        let media_type = params
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        let quality = params
+            .find_map(|param| param.trim().strip_prefix("q="))
+            .and_then(|q| q.trim().parse::<f64>().ok())
+            .unwrap_or(1.0);
+
+        let xml = if XML_MEDIA_TYPES.contains(&media_type.as_str()) {
+            true
+        } else if JSON_MEDIA_TYPES.contains(&media_type.as_str()) {
+            false
+        } else {
+            continue;
+        };
+        if quality > 0.0 && best.is_none_or(|(best_quality, _)| quality > best_quality) {
+            best = Some((quality, xml));
+        }
+    }
+    best.is_some_and(|(_, xml)| xml)
+}
+
+/// Serializes a generated body as XML, naming elements and attributes after
+/// the schema's `xml` objects (`name`, `attribute`, `wrapped`) where given,
+/// and otherwise after property and component names.
+pub fn to_xml(swagger_state: &SwaggerState, value: &Value, schema: &Value) -> String {
+    let writer = XmlWriter { swagger_state };
+    let (schema, ref_name) = writer.resolve(schema);
+    let name = xml_name(&schema)
+        .or(ref_name)
+        .unwrap_or_else(|| "response".to_string());
+
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>");
+    match value {
+        Value::Array(items) => writer.write_array(&mut out, &name, items, &schema, true),
+        value => writer.write_element(&mut out, &name, value, &schema),
+    }
+    out
+}
+
+struct XmlWriter<'a> {
+    swagger_state: &'a SwaggerState,
+}
+
+impl XmlWriter<'_> {
+    /// Follows a `$ref`, returning the schema and the referenced component's
+    /// name.
+    fn resolve(&self, schema: &Value) -> (Value, Option<String>) {
+        match schema.get("$ref").and_then(Value::as_str) {
+            Some(ref_path) => (
+                self.swagger_state
+                    .resolve_ref(ref_path)
+                    .unwrap_or(Value::Null),
+                ref_path.rsplit('/').next().map(element_name),
+            ),
+            None => (schema.clone(), None),
+        }
+    }
+
+    fn write_element(&self, out: &mut String, name: &str, value: &Value, schema: &Value) {
+        match value {
+            Value::Object(fields) => {
+                let properties = schema.get("properties");
+                let mut attributes = String::new();
+                let mut children = String::new();
+                for (key, field) in fields {
+                    let (field_schema, _) = self.resolve(
+                        properties
+                            .and_then(|properties| properties.get(key))
+                            .unwrap_or(&Value::Null),
+                    );
+                    let field_name = xml_name(&field_schema).unwrap_or_else(|| element_name(key));
+                    let is_attribute = field_schema
+                        .pointer("/xml/attribute")
+                        .and_then(Value::as_bool)
+                        .unwrap_or(false);
+
+                    match field {
+                        Value::Object(_) | Value::Array(_) if is_attribute => {}
+                        Value::Null if is_attribute => {}
+                        scalar if is_attribute => attributes.push_str(&format!(
+                            " {}=\"{}\"",
+                            field_name,
+                            escape(&scalar_text(scalar))
+                        )),
+                        Value::Array(items) => {
+                            let wrapped = field_schema
+                                .pointer("/xml/wrapped")
+                                .and_then(Value::as_bool)
+                                .unwrap_or(false);
+                            self.write_array(
+                                &mut children,
+                                &field_name,
+                                items,
+                                &field_schema,
+                                wrapped,
+                            )
+                        }
+                        field => {
+                            self.write_element(&mut children, &field_name, field, &field_schema)
+                        }
+                    }
+                }
+                out.push_str(&format!("<{}{}>{}</{}>", name, attributes, children, name));
+            }
+            Value::Array(items) => self.write_array(out, name, items, schema, true),
+            Value::Null => out.push_str(&format!("<{}/>", name)),
+            scalar => out.push_str(&format!(
+                "<{}>{}</{}>",
+                name,
+                escape(&scalar_text(scalar)),
+                name
+            )),
+        }
+    }
+
+    /// Writes `items` as repeated elements, inside a `name` element when
+    /// `wrapped`. Items are named after their own `xml.name`, else their
+    /// component when wrapped, else `name`.
+    fn write_array(
+        &self,
+        out: &mut String,
+        name: &str,
+        items: &[Value],
+        schema: &Value,
+        wrapped: bool,
+    ) {
+        let (item_schema, ref_name) = self.resolve(schema.get("items").unwrap_or(&Value::Null));
+        let item_name = xml_name(&item_schema).unwrap_or_else(|| match (wrapped, ref_name) {
+            (true, Some(ref_name)) => ref_name,
+            (true, None) => "item".to_string(),
+            (false, _) => name.to_string(),
+        });
+
+        if wrapped {
+            out.push_str(&format!("<{}>", name));
+        }
+        for item in items {
+            self.write_element(out, &item_name, item, &item_schema);
+        }
+        if wrapped {
+            out.push_str(&format!("</{}>", name));
+        }
+    }
+}
+
+fn xml_name(schema: &Value) -> Option<String> {
+    schema
+        .pointer("/xml/name")
+        .and_then(Value::as_str)
+        .map(element_name)
+}
+
+/// `name` with the characters XML names cannot hold replaced by `_`.
+fn element_name(name: &str) -> String {
+    let mut element: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '_' | '-' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if !element
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+    {
+        element.insert(0, '_');
+    }
+    element
+}
+
+fn scalar_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::swagger::SpecSource;
+
+    #[test]
+    fn prefers_xml_by_quality_then_order() {
+        assert!(prefers_xml("application/xml"));
+        assert!(prefers_xml("text/xml, application/json"));
+        assert!(prefers_xml("application/json;q=0.5, application/xml"));
+        assert!(!prefers_xml("application/json, application/xml"));
+        assert!(!prefers_xml("application/xml;q=0, */*"));
+        assert!(!prefers_xml("text/html"));
+    }
+
+    #[test]
+    fn names_elements_after_the_schema() {
+        let spec = json!({
+            "openapi": "3.0.3",
+            "paths": {},
+            "components": {
+                "schemas": {
+                    "Pet": {
+                        "type": "object",
+                        "properties": {
+                            "id": { "type": "integer", "xml": { "attribute": true } },
+                            "name": { "type": "string" },
+                            "tags": {
+                                "type": "array",
+                                "xml": { "wrapped": true },
+                                "items": { "type": "string", "xml": { "name": "tag" } }
+                            },
+                            "owner": { "type": "string", "nullable": true }
+                        }
+                    }
+                }
+            }
+        });
+        let state = SwaggerState::from_spec(&spec, &SpecSource::Url(String::new())).unwrap();
+        let pets =
+            json!([{ "id": 1, "name": "Tom & \"Jerry\"", "tags": ["a", "b"], "owner": null }]);
+        let schema = json!({ "type": "array", "items": { "$ref": "#/components/schemas/Pet" } });
+
+        assert_eq!(
+            to_xml(&state, &pets, &schema),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?><response>\
+             <Pet id=\"1\"><name>Tom &amp; &quot;Jerry&quot;</name>\
+             <tags><tag>a</tag><tag>b</tag></tags><owner/></Pet></response>"
+        );
+    }
+
+    #[test]
+    fn replaces_characters_names_cannot_hold() {
+        assert_eq!(element_name("first name"), "first_name");
+        assert_eq!(element_name("2fa"), "_2fa");
+        assert_eq!(element_name("x-rate.limit"), "x-rate.limit");
+    }
+}
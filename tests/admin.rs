@@ -0,0 +1,193 @@
+mod common;
+
+use actix_web::{
+    body::{BodySize, MessageBody},
+    http::StatusCode,
+    test,
+};
+use serde_json::{json, Value};
+use spit::{config::RequestLog, replay::read_request_log};
+
+/// A `GET /users` answering 200 with an empty list.
+fn users_spec() -> Value {
+    common::spec(
+        json!({ "/users": common::get_returning(json!({ "type": "array", "maxItems": 0 })) }),
+        json!({}),
+    )
+}
+
+/// A `GET uri` from a loopback client.
+fn local_get(uri: &str) -> test::TestRequest {
+    test::TestRequest::get()
+        .uri(uri)
+        .peer_addr("127.0.0.1:40000".parse().unwrap())
+}
+
+#[actix_web::test]
+async fn request_log_reads_back_served_requests() {
+    let app = test::init_service(common::load(users_spec(), json!({})).await.app()).await;
+
+    let response = test::call_service(&app, local_get("/users?page=1").to_request()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = test::call_service(&app, local_get("/missing").to_request()).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let log: Vec<RequestLog> =
+        test::call_and_read_body_json(&app, local_get("/__spit/requests").to_request()).await;
+    let served: Vec<(&str, &str, u16)> = log
+        .iter()
+        .map(|entry| {
+            (
+                entry.method.as_str(),
+                entry.path.as_str(),
+                entry.response_status,
+            )
+        })
+        .collect();
+    assert_eq!(served, [("GET", "/users", 200), ("GET", "/missing", 404)]);
+    assert_eq!(log[0].query.as_deref(), Some("page=1"));
+
+    let log: Vec<RequestLog> = test::call_and_read_body_json(
+        &app,
+        local_get("/__spit/requests?status=404&limit=5").to_request(),
+    )
+    .await;
+    assert_eq!(log.len(), 1);
+    assert_eq!(log[0].path, "/missing");
+
+    let log: Vec<RequestLog> =
+        test::call_and_read_body_json(&app, local_get("/__spit/requests").to_request()).await;
+    assert_eq!(log.len(), 2, "admin requests are not logged");
+}
+
+#[actix_web::test]
+async fn admin_endpoints_refuse_remote_clients() {
+    let app = test::init_service(common::load(users_spec(), json!({})).await.app()).await;
+
+    let remote = test::TestRequest::get()
+        .uri("/__spit/requests")
+        .peer_addr("203.0.113.9:40000".parse().unwrap())
+        .to_request();
+    let response = test::call_service(&app, remote).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+/// `users_spec` plus a `GET /events` streaming two NDJSON lines.
+fn events_spec() -> Value {
+    let mut spec = users_spec();
+    spec["paths"]["/events"] = json!({
+        "get": {
+            "responses": {
+                "200": {
+                    "description": "ok",
+                    "content": {
+                        "application/x-ndjson": {
+                            "schema": { "type": "array", "minItems": 2, "maxItems": 2, "items": { "type": "integer" } }
+                        }
+                    }
+                }
+            }
+        }
+    });
+    spec
+}
+
+/// The entries of the log file at `path` once it has been written to,
+/// which happens every second.
+async fn flushed_log(path: &std::path::Path) -> Vec<RequestLog> {
+    let mut log = Vec::new();
+    for _ in 0..30 {
+        actix_web::rt::time::sleep(std::time::Duration::from_millis(100)).await;
+        log = read_request_log(path).unwrap_or_default();
+        if !log.is_empty() {
+            break;
+        }
+    }
+    log
+}
+
+#[actix_web::test]
+async fn log_file_entries_read_back() {
+    let dir = tempfile::tempdir().unwrap();
+    let log_file = dir.path().join("requests.jsonl");
+    let config = json!({ "log_file": log_file });
+    let app = test::init_service(common::load(users_spec(), config).await.app()).await;
+
+    let request = local_get("/users?page=2")
+        .insert_header(("X-Trace", "abc"))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let log = flushed_log(&log_file).await;
+    assert_eq!(log.len(), 1);
+    let entry = &log[0];
+    assert_eq!(
+        (entry.method.as_str(), entry.path.as_str()),
+        ("GET", "/users")
+    );
+    assert_eq!(entry.query.as_deref(), Some("page=2"));
+    assert_eq!(
+        entry.headers.get("x-trace").map(String::as_str),
+        Some("abc")
+    );
+    assert_eq!(entry.response_status, 200);
+    assert_eq!(entry.response_body.as_deref(), Some("[]"));
+}
+
+#[actix_web::test]
+async fn access_log_lines_cannot_be_forged() {
+    let dir = tempfile::tempdir().unwrap();
+    let access_log = dir.path().join("access.log");
+    let spec = events_spec();
+    let config = json!({ "access_log": access_log });
+    let app = test::init_service(common::load(spec, config).await.app()).await;
+
+    let request = local_get("/users%0A1.2.3.4%20-%20-%20%22")
+        .insert_header(("Referer", r#"a" "b\c"#))
+        .insert_header((
+            "User-Agent",
+            actix_web::http::header::HeaderValue::from_bytes(b"caf\xc3\xa9\tx").unwrap(),
+        ))
+        .to_request();
+    test::call_service(&app, request).await;
+    let response = test::call_service(&app, local_get("/events").to_request()).await;
+    test::read_body(response).await;
+
+    let log = std::fs::read_to_string(&access_log).unwrap();
+    let lines: Vec<&str> = log.lines().collect();
+    assert_eq!(lines.len(), 2, "{}", log);
+    let forged = r#""GET /users%0A1.2.3.4%20-%20-%20%22 HTTP/1.1" 404 "#;
+    assert!(lines[0].contains(forged), "{}", lines[0]);
+    assert!(
+        lines[0].ends_with(r#" "a\" \"b\\c" "caf\xc3\xa9\tx""#),
+        "{}",
+        lines[0]
+    );
+    assert!(
+        lines[1].ends_with(r#""GET /events HTTP/1.1" 200 - "-" "-""#),
+        "{}",
+        lines[1]
+    );
+}
+
+#[actix_web::test]
+async fn log_file_keeps_streamed_bodies_streaming() {
+    let dir = tempfile::tempdir().unwrap();
+    let log_file = dir.path().join("requests.jsonl");
+    let config = json!({ "log_file": log_file });
+    let app = test::init_service(common::load(events_spec(), config).await.app()).await;
+
+    let response = test::call_service(&app, local_get("/events").to_request()).await;
+    assert_eq!(response.response().body().size(), BodySize::Stream);
+    let body = test::read_body(response).await;
+    assert_eq!(body.iter().filter(|b| **b == b'\n').count(), 2);
+
+    let log = flushed_log(&log_file).await;
+    assert_eq!(log.len(), 1);
+    assert_eq!(
+        log[0].response_body.as_deref(),
+        Some(String::from_utf8_lossy(&body).as_ref())
+    );
+    assert_eq!(log[0].response_size, body.len() as u64);
+}
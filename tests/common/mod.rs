@@ -0,0 +1,86 @@
+//! Builds mock apps from inline specs for the integration tests.
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+use serde_json::{json, Value};
+use spit::{swagger::SpecSource, MockApp};
+
+/// Loads `spec` with `config` (a JSON `MockConfig`), as `spit file` would.
+pub async fn load(spec: Value, config: Value) -> MockApp {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write(dir.path(), "openapi.json", &spec);
+    load_source(SpecSource::Files(vec![path]), config).await
+}
+
+pub async fn load_source(source: SpecSource, config: Value) -> MockApp {
+    let config = serde_json::from_value(config).unwrap();
+    MockApp::load(&source, &config).await.unwrap()
+}
+
+/// Writes `content` as JSON to `name` in `dir`.
+pub fn write(dir: &Path, name: &str, content: &Value) -> PathBuf {
+    let path = dir.join(name);
+    std::fs::write(&path, serde_json::to_vec(content).unwrap()).unwrap();
+    path
+}
+
+/// An OpenAPI 3 spec with the given `paths` and component `schemas`.
+pub fn spec(paths: Value, schemas: Value) -> Value {
+    json!({
+        "openapi": "3.0.3",
+        "info": { "title": "test", "version": "1" },
+        "paths": paths,
+        "components": { "schemas": schemas }
+    })
+}
+
+/// A `GET` operation answering 200 with a body of `schema`.
+pub fn get_returning(schema: Value) -> Value {
+    json!({
+        "get": {
+            "responses": {
+                "200": {
+                    "description": "ok",
+                    "content": { "application/json": { "schema": schema } }
+                }
+            }
+        }
+    })
+}
+
+/// Starts a backend answering every request with 202 and a JSON echo of its
+/// method, path, query and body, and returns its base URL.
+pub fn echo_upstream() -> String {
+    let server = HttpServer::new(|| App::new().default_service(web::route().to(echo)))
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+    let addr = server.addrs()[0];
+    actix_web::rt::spawn(server.run());
+    format!("http://{}", addr)
+}
+
+async fn echo(req: HttpRequest, body: web::Bytes) -> HttpResponse {
+    HttpResponse::Accepted()
+        .insert_header(("X-Upstream", "echo"))
+        .json(json!({
+            "method": req.method().as_str(),
+            "path": req.path(),
+            "query": req.query_string(),
+            "body": String::from_utf8_lossy(&body)
+        }))
+}
+
+/// Serves `mock` on a free local port with four workers, and returns its
+/// base URL.
+pub fn serve(mock: MockApp) -> String {
+    let server = HttpServer::new(move || mock.app())
+        .workers(4)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+    let addr = server.addrs()[0];
+    actix_web::rt::spawn(server.run());
+    format!("http://{}", addr)
+}
@@ -0,0 +1,38 @@
+mod common;
+
+use serde_json::json;
+use spit::config::RequestLog;
+use tokio::task::JoinSet;
+
+#[actix_web::test]
+async fn concurrent_requests_are_all_logged() {
+    let spec = common::spec(
+        json!({ "/items/{id}": common::get_returning(json!({ "type": "object" })) }),
+        json!({}),
+    );
+    let base = common::serve(common::load(spec, json!({})).await);
+    let client = reqwest::Client::new();
+
+    let mut requests = JoinSet::new();
+    for id in 0..100 {
+        let request = client.get(format!("{}/items/{}", base, id)).send();
+        requests.spawn(async move { request.await.unwrap().status().as_u16() });
+    }
+    while let Some(status) = requests.join_next().await {
+        assert_eq!(status.unwrap(), 200);
+    }
+
+    let log: Vec<RequestLog> = client
+        .get(format!("{}/__spit/requests", base))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let mut paths: Vec<String> = log.into_iter().map(|entry| entry.path).collect();
+    paths.sort();
+    let mut expected: Vec<String> = (0..100).map(|id| format!("/items/{}", id)).collect();
+    expected.sort();
+    assert_eq!(paths, expected);
+}
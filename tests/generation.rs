@@ -0,0 +1,631 @@
+mod common;
+
+use actix_web::test;
+use serde_json::{json, Value};
+use spit::swagger::SpecSource;
+
+/// The body `GET /x` answers with when it returns `schema`, under `config`.
+async fn generated(schema: Value, config: Value) -> Value {
+    let spec = common::spec(json!({ "/x": common::get_returning(schema) }), json!({}));
+    let app = test::init_service(common::load(spec, config).await.app()).await;
+    test::call_and_read_body_json(&app, test::TestRequest::get().uri("/x").to_request()).await
+}
+
+#[actix_web::test]
+async fn type_arrays_generate_their_first_non_null_type() {
+    let body = generated(
+        json!({
+            "type": "object",
+            "required": ["count"],
+            "properties": { "count": { "type": ["null", "integer"] } }
+        }),
+        json!({}),
+    )
+    .await;
+    assert!(body["count"].is_i64(), "{}", body);
+}
+
+#[actix_web::test]
+async fn discriminated_one_of_generates_a_consistent_variant() {
+    let spec = common::spec(
+        json!({ "/x": common::get_returning(json!({ "$ref": "#/components/schemas/Pet" })) }),
+        json!({
+            "Pet": {
+                "oneOf": [{ "$ref": "#/components/schemas/Cat" }, { "$ref": "#/components/schemas/Dog" }],
+                "discriminator": { "propertyName": "kind" }
+            },
+            "Cat": {
+                "type": "object",
+                "required": ["kind", "meows"],
+                "properties": { "kind": { "type": "string" }, "meows": { "type": "boolean" } }
+            },
+            "Dog": {
+                "type": "object",
+                "required": ["kind", "barks"],
+                "properties": { "kind": { "type": "string" }, "barks": { "type": "boolean" } }
+            }
+        }),
+    );
+    let app = test::init_service(common::load(spec, json!({})).await.app()).await;
+
+    for _ in 0..20 {
+        let pet: Value =
+            test::call_and_read_body_json(&app, test::TestRequest::get().uri("/x").to_request())
+                .await;
+        match pet["kind"].as_str() {
+            Some("Cat") => assert!(pet["meows"].is_boolean(), "{}", pet),
+            Some("Dog") => assert!(pet["barks"].is_boolean(), "{}", pet),
+            _ => panic!("unexpected pet {}", pet),
+        }
+    }
+}
+
+#[actix_web::test]
+async fn all_of_merges_every_part() {
+    let body = generated(
+        json!({
+            "allOf": [
+                { "type": "object", "required": ["id"], "properties": { "id": { "type": "integer" } } },
+                { "type": "object", "required": ["name"], "properties": { "name": { "type": "string" } } }
+            ]
+        }),
+        json!({}),
+    )
+    .await;
+    assert!(body["id"].is_i64() && body["name"].is_string(), "{}", body);
+}
+
+#[actix_web::test]
+async fn response_refs_resolve() {
+    let mut spec = common::spec(
+        json!({ "/x": { "get": { "responses": { "200": { "$ref": "#/components/responses/Named" } } } } }),
+        json!({}),
+    );
+    spec["components"]["responses"] = json!({
+        "Named": {
+            "description": "ok",
+            "content": {
+                "application/json": {
+                    "schema": {
+                        "type": "object",
+                        "required": ["name"],
+                        "properties": { "name": { "type": "string" } }
+                    }
+                }
+            }
+        }
+    });
+    let app = test::init_service(common::load(spec, json!({})).await.app()).await;
+
+    let body: Value =
+        test::call_and_read_body_json(&app, test::TestRequest::get().uri("/x").to_request()).await;
+    assert!(body["name"].is_string(), "{}", body);
+}
+
+#[actix_web::test]
+async fn file_refs_resolve_relative_to_the_spec() {
+    let dir = tempfile::tempdir().unwrap();
+    common::write(
+        dir.path(),
+        "common.json",
+        &json!({
+            "Count": {
+                "type": "object",
+                "required": ["count"],
+                "properties": { "count": { "type": "integer", "minimum": 7, "maximum": 7 } }
+            }
+        }),
+    );
+    let spec = common::write(
+        dir.path(),
+        "openapi.json",
+        &common::spec(
+            json!({ "/x": common::get_returning(json!({ "$ref": "./common.json#/Count" })) }),
+            json!({}),
+        ),
+    );
+    let app = common::load_source(SpecSource::Files(vec![spec]), json!({})).await;
+    let app = test::init_service(app.app()).await;
+
+    let body: Value =
+        test::call_and_read_body_json(&app, test::TestRequest::get().uri("/x").to_request()).await;
+    assert_eq!(body, json!({ "count": 7 }));
+}
+
+#[actix_web::test]
+async fn schema_examples_round_trip() {
+    let schema = json!({
+        "type": "object",
+        "required": ["name", "tags"],
+        "properties": {
+            "name": { "type": "string", "example": "Rex" },
+            "tags": { "type": "array", "items": { "type": "string" }, "example": ["good", "boy"] }
+        }
+    });
+    let body = generated(schema.clone(), json!({})).await;
+    assert_eq!(body, json!({ "name": "Rex", "tags": ["good", "boy"] }));
+
+    let body = generated(schema, json!({ "prefer_examples": false })).await;
+    assert_ne!(body["name"], json!("Rex"), "{}", body);
+}
+
+#[actix_web::test]
+async fn content_examples_round_trip() {
+    let example = json!({ "id": 7, "nested": { "list": [1, 2, 3], "none": null } });
+    let spec = common::spec(
+        json!({
+            "/x": {
+                "get": {
+                    "responses": {
+                        "200": {
+                            "description": "ok",
+                            "content": {
+                                "application/json": {
+                                    "schema": { "type": "object" },
+                                    "examples": { "only": { "value": example } }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }),
+        json!({}),
+    );
+    let app = test::init_service(common::load(spec, json!({})).await.app()).await;
+
+    let body: Value =
+        test::call_and_read_body_json(&app, test::TestRequest::get().uri("/x").to_request()).await;
+    assert_eq!(body, example);
+}
+
+/// The raw body `GET /x` answers with, from a fresh app seeded with `seed`.
+async fn seeded_body(seed: u64) -> actix_web::web::Bytes {
+    let spec = common::spec(
+        json!({
+            "/x": common::get_returning(json!({
+                "type": "array",
+                "minItems": 3,
+                "items": {
+                    "type": "object",
+                    "required": ["id", "name", "score", "created", "active"],
+                    "properties": {
+                        "id": { "type": "string", "format": "uuid" },
+                        "name": { "type": "string" },
+                        "score": { "type": "number" },
+                        "created": { "type": "string", "format": "date-time" },
+                        "active": { "type": "boolean" }
+                    }
+                }
+            }))
+        }),
+        json!({}),
+    );
+    let app = common::load(spec, json!({ "seed": seed })).await;
+    let app = test::init_service(app.app()).await;
+    test::call_and_read_body(&app, test::TestRequest::get().uri("/x").to_request()).await
+}
+
+#[actix_web::test]
+async fn same_seed_servers_answer_identically() {
+    assert_eq!(seeded_body(42).await, seeded_body(42).await);
+    assert_ne!(seeded_body(42).await, seeded_body(43).await);
+}
+
+#[actix_web::test]
+async fn seeded_servers_keep_the_real_clock() {
+    let schema = json!({
+        "type": "object",
+        "required": ["stamp"],
+        "properties": { "stamp": { "type": "string" } }
+    });
+    let spec = common::spec(json!({ "/x": common::get_returning(schema) }), json!({}));
+    let config = json!({
+        "seed": 42,
+        "clock_offset": "+1d",
+        "fields": { "patterns": { "stamp": { "type": "template", "template": "{now}" } } }
+    });
+    let app = test::init_service(common::load(spec, config).await.app()).await;
+    let response = test::call_service(&app, test::TestRequest::get().uri("/x").to_request()).await;
+
+    let expected = chrono::Utc::now() + chrono::Duration::days(1);
+    let date = response.headers().get("Date").unwrap().to_str().unwrap();
+    let date = chrono::DateTime::parse_from_rfc2822(date).unwrap();
+    assert!(
+        (date.to_utc() - expected).num_seconds().abs() < 60,
+        "{}",
+        date
+    );
+    let body: Value = test::read_body_json(response).await;
+    let stamp = chrono::DateTime::parse_from_rfc3339(body["stamp"].as_str().unwrap()).unwrap();
+    assert!(
+        (stamp.to_utc() - expected).num_seconds().abs() < 60,
+        "{}",
+        stamp
+    );
+}
+
+#[actix_web::test]
+async fn defaults_win_over_faker_but_lose_to_patterns() {
+    let schema = json!({
+        "type": "object",
+        "required": ["status", "retries", "enabled", "tags"],
+        "properties": {
+            "status": { "type": "string", "default": "active" },
+            "retries": { "type": "integer", "default": 3 },
+            "enabled": { "type": "boolean", "default": true },
+            "tags": { "type": "array", "items": { "type": "string" }, "default": ["a", "b"] }
+        }
+    });
+    let body = generated(schema.clone(), json!({})).await;
+    assert_eq!(
+        body,
+        json!({ "status": "active", "retries": 3, "enabled": true, "tags": ["a", "b"] })
+    );
+
+    let config = json!({
+        "fields": { "patterns": { "status": { "type": "enum", "values": ["paused"] } } }
+    });
+    let body = generated(schema, config).await;
+    assert_eq!(body["status"], json!("paused"));
+    assert_eq!(body["retries"], json!(3));
+}
+
+#[actix_web::test]
+async fn arrays_honour_a_min_items_above_the_default_maximum() {
+    let body = generated(
+        json!({ "type": "array", "minItems": 20, "items": { "type": "integer" } }),
+        json!({}),
+    )
+    .await;
+    assert_eq!(body.as_array().unwrap().len(), 20);
+}
+
+#[actix_web::test]
+async fn patterned_strings_match_their_pattern() {
+    let pattern = r"^[A-Z]{3}-\d{4}$";
+    let body = generated(
+        json!({ "type": "array", "minItems": 20, "items": { "type": "string", "pattern": pattern } }),
+        json!({}),
+    )
+    .await;
+    let regex = regex::Regex::new(pattern).unwrap();
+    for value in body.as_array().unwrap() {
+        assert!(regex.is_match(value.as_str().unwrap()), "{}", value);
+    }
+}
+
+/// `count` values generated for `schema`, as the items of one array.
+async fn samples(schema: Value, count: usize) -> Vec<Value> {
+    let body = generated(
+        json!({ "type": "array", "minItems": count, "maxItems": count, "items": schema }),
+        json!({}),
+    )
+    .await;
+    body.as_array().unwrap().clone()
+}
+
+#[actix_web::test]
+async fn strings_respect_length_bounds() {
+    for value in samples(json!({ "type": "string", "maxLength": 3 }), 100).await {
+        let length = value.as_str().unwrap().chars().count();
+        assert!((1..=3).contains(&length), "{}", value);
+    }
+    for value in samples(json!({ "type": "string", "minLength": 50 }), 100).await {
+        assert!(value.as_str().unwrap().chars().count() >= 50, "{}", value);
+    }
+    let schema = json!({ "type": "string", "minLength": 4, "maxLength": 6 });
+    for value in samples(schema, 100).await {
+        let length = value.as_str().unwrap().chars().count();
+        assert!((4..=6).contains(&length), "{}", value);
+    }
+}
+
+/// The string generated for `format`.
+async fn formatted(format: &str) -> String {
+    let body = generated(json!({ "type": "string", "format": format }), json!({})).await;
+    body.as_str().unwrap().to_string()
+}
+
+#[actix_web::test]
+async fn formatted_strings_parse() {
+    let date = formatted("date").await;
+    assert!(date.parse::<chrono::NaiveDate>().is_ok(), "{}", date);
+    let ipv4 = formatted("ipv4").await;
+    assert!(ipv4.parse::<std::net::Ipv4Addr>().is_ok(), "{}", ipv4);
+    let ipv6 = formatted("ipv6").await;
+    assert!(ipv6.parse::<std::net::Ipv6Addr>().is_ok(), "{}", ipv6);
+    let uri = formatted("uri").await;
+    assert!(reqwest::Url::parse(&uri).is_ok(), "{}", uri);
+    let hostname = formatted("hostname").await;
+    assert!(
+        hostname.contains('.') && !hostname.contains(char::is_whitespace),
+        "{}",
+        hostname
+    );
+}
+
+#[actix_web::test]
+async fn numbers_respect_their_constraints() {
+    type Satisfies = fn(f64) -> bool;
+    let cases: [(Value, Satisfies); 6] = [
+        (
+            json!({ "type": "integer", "minimum": -3, "maximum": 3 }),
+            |n| (-3.0..=3.0).contains(&n) && n.fract() == 0.0,
+        ),
+        (
+            json!({ "type": "integer", "minimum": 0, "maximum": 10, "exclusiveMinimum": true, "exclusiveMaximum": true }),
+            |n| n > 0.0 && n < 10.0,
+        ),
+        (
+            json!({ "type": "integer", "minimum": 1, "maximum": 100, "multipleOf": 7 }),
+            |n| (1.0..=100.0).contains(&n) && n % 7.0 == 0.0,
+        ),
+        (
+            json!({ "type": "number", "exclusiveMinimum": 0.5, "exclusiveMaximum": 0.75 }),
+            |n| n > 0.5 && n < 0.75,
+        ),
+        (
+            json!({ "type": "number", "minimum": 0, "maximum": 5, "multipleOf": 0.25 }),
+            |n| (0.0..=5.0).contains(&n) && (n * 4.0).fract() == 0.0,
+        ),
+        (json!({ "type": "integer", "format": "int32" }), |n| {
+            (i32::MIN as f64..=i32::MAX as f64).contains(&n)
+        }),
+    ];
+    for (schema, satisfies) in cases {
+        for value in samples(schema.clone(), 1000).await {
+            let number = value.as_f64().unwrap();
+            assert!(satisfies(number), "{} for {}", value, schema);
+        }
+    }
+}
+
+#[actix_web::test]
+async fn echoed_params_fill_matching_fields() {
+    let mut user = common::get_returning(json!({
+        "type": "object",
+        "required": ["id", "verbose", "name"],
+        "properties": {
+            "id": { "type": "integer" },
+            "verbose": { "type": "boolean" },
+            "name": { "type": "string" }
+        }
+    }));
+    user["get"]["parameters"] = json!([
+        { "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } },
+        { "name": "verbose", "in": "query", "schema": { "type": "boolean" } }
+    ]);
+    let spec = common::spec(json!({ "/users/{id}": user }), json!({}));
+    let app = test::init_service(
+        common::load(spec, json!({ "echo_params": true }))
+            .await
+            .app(),
+    )
+    .await;
+
+    for id in [7, 1234] {
+        let uri = format!("/users/{}?verbose=true", id);
+        let body: Value =
+            test::call_and_read_body_json(&app, test::TestRequest::get().uri(&uri).to_request())
+                .await;
+        assert_eq!(body["id"], json!(id), "{}", body);
+        assert_eq!(body["verbose"], json!(true), "{}", body);
+        assert!(body["name"].is_string(), "{}", body);
+    }
+}
+
+/// Fifty names generated under `locale`.
+async fn names(locale: &str) -> Vec<String> {
+    let schema = json!({
+        "type": "array",
+        "minItems": 50,
+        "maxItems": 50,
+        "items": { "type": "string", "format": "name" }
+    });
+    let body = generated(schema, json!({ "locale": locale, "seed": 1 })).await;
+    body.as_array()
+        .unwrap()
+        .iter()
+        .map(|name| name.as_str().unwrap().to_string())
+        .collect()
+}
+
+#[actix_web::test]
+async fn locales_change_generated_names() {
+    let english = names("en").await;
+    assert!(english.iter().all(|name| name.is_ascii()), "{:?}", english);
+
+    let japanese = names("ja").await;
+    assert!(
+        japanese.iter().all(|name| !name.is_ascii()),
+        "{:?}",
+        japanese
+    );
+
+    for locale in ["fr", "pt_br", "zh_cn", "zh_tw", "ar"] {
+        let localized = names(locale).await;
+        let shared = localized
+            .iter()
+            .filter(|name| english.contains(name))
+            .count();
+        assert!(shared < 5, "{} {:?}", locale, localized);
+    }
+}
+
+#[actix_web::test]
+async fn regex_patterns_generate_matching_strings() {
+    let pattern = "ORD-[0-9]{6}";
+    let body = generated(
+        json!({
+            "type": "array",
+            "minItems": 20,
+            "items": { "type": "object", "required": ["number"], "properties": { "number": { "type": "string" } } }
+        }),
+        json!({ "fields": { "patterns": { "number": { "type": "regex", "pattern": pattern } } } }),
+    )
+    .await;
+    let regex = regex::Regex::new(&format!("^{}$", pattern)).unwrap();
+    for order in body.as_array().unwrap() {
+        assert!(
+            regex.is_match(order["number"].as_str().unwrap()),
+            "{}",
+            order
+        );
+    }
+}
+
+/// The message loading a spec fails with when `number` has `pattern`.
+async fn pattern_error(pattern: Value) -> String {
+    let dir = tempfile::tempdir().unwrap();
+    let spec = common::spec(json!({ "/x": common::get_returning(json!({})) }), json!({}));
+    let path = common::write(dir.path(), "openapi.json", &spec);
+    let config =
+        serde_json::from_value(json!({ "fields": { "patterns": { "number": pattern } } })).unwrap();
+
+    spit::MockApp::load(&SpecSource::Files(vec![path]), &config)
+        .await
+        .err()
+        .expect("the pattern is invalid")
+        .to_string()
+}
+
+#[actix_web::test]
+async fn invalid_regex_patterns_fail_on_load() {
+    let message = pattern_error(json!({ "type": "regex", "pattern": "ORD-[0-9" })).await;
+    assert!(message.contains("fields.patterns.number"), "{}", message);
+}
+
+#[actix_web::test]
+async fn sequence_patterns_count_across_requests() {
+    let schema = json!({
+        "type": "object",
+        "required": ["number"],
+        "properties": { "number": { "type": "integer" } }
+    });
+    let spec = common::spec(json!({ "/x": common::get_returning(schema) }), json!({}));
+    let config = json!({
+        "fields": { "patterns": { "number": { "type": "sequence", "start": 10, "step": 5 } } }
+    });
+    let app = test::init_service(common::load(spec, config).await.app()).await;
+
+    let mut numbers = Vec::new();
+    for _ in 0..3 {
+        let body: Value =
+            test::call_and_read_body_json(&app, test::TestRequest::get().uri("/x").to_request())
+                .await;
+        numbers.push(body["number"].clone());
+    }
+    assert_eq!(numbers, [json!(10), json!(15), json!(20)]);
+}
+
+#[actix_web::test]
+async fn templates_interpolate_fields_and_tokens() {
+    let string = json!({ "type": "string" });
+    let schema = json!({
+        "type": "object",
+        "required": ["first", "last", "email", "reference"],
+        "properties": { "first": string, "last": string, "email": string, "reference": string }
+    });
+    let config = json!({
+        "fields": {
+            "patterns": {
+                "first": { "type": "enum", "values": ["ann"] },
+                "last": { "type": "enum", "values": ["lee"] },
+                "email": { "type": "template", "template": "{first}.{last}@example.com" },
+                "reference": { "type": "template", "template": "{seq}/{uuid}/{now}/{unknown}" }
+            }
+        }
+    });
+    let body = generated(schema, config).await;
+    assert_eq!(body["email"], json!("ann.lee@example.com"));
+
+    let reference = body["reference"].as_str().unwrap();
+    let parts: Vec<&str> = reference.splitn(4, '/').collect();
+    assert_eq!(parts[0], "1", "{}", reference);
+    assert!(uuid::Uuid::parse_str(parts[1]).is_ok(), "{}", reference);
+    assert!(
+        chrono::DateTime::parse_from_rfc3339(parts[2]).is_ok(),
+        "{}",
+        reference
+    );
+    assert_eq!(parts[3], "{unknown}", "{}", reference);
+}
+
+#[actix_web::test]
+async fn ref_patterns_use_their_faker_kind() {
+    let string = json!({ "type": "string" });
+    let schema = json!({
+        "type": "object",
+        "required": ["address", "phone", "color"],
+        "properties": { "address": string, "phone": string, "color": string }
+    });
+    let config = json!({
+        "fields": {
+            "patterns": {
+                "address": { "type": "ref", "kind": "address" },
+                "phone": { "type": "ref", "kind": "phone" },
+                "color": { "type": "ref", "kind": "color" }
+            }
+        }
+    });
+    let body = generated(schema, config).await;
+
+    let address = body["address"].as_str().unwrap();
+    assert!(address.contains(", "), "{}", address);
+    let phone = body["phone"].as_str().unwrap();
+    assert!(
+        phone.chars().filter(char::is_ascii_digit).count() >= 7,
+        "{}",
+        phone
+    );
+    let color = regex::Regex::new("^#[0-9a-f]{6}$").unwrap();
+    assert!(color.is_match(body["color"].as_str().unwrap()), "{}", body);
+}
+
+#[actix_web::test]
+async fn unknown_ref_kinds_fail_on_load() {
+    let message = pattern_error(json!({ "type": "ref", "kind": "horoscope" })).await;
+    assert!(message.contains("unknown kind `horoscope`"), "{}", message);
+}
+
+#[actix_web::test]
+async fn dotted_pattern_keys_target_one_field() {
+    let id = json!({ "type": "string" });
+    let owned = |properties: Value| {
+        let required: Vec<&String> = properties.as_object().unwrap().keys().collect();
+        json!({ "type": "object", "required": required, "properties": properties })
+    };
+    let schema = owned(json!({
+        "id": id,
+        "user": owned(json!({ "id": id })),
+        "order": owned(json!({
+            "id": id,
+            "items": {
+                "type": "array",
+                "minItems": 2,
+                "maxItems": 2,
+                "items": owned(json!({ "id": id, "sku": id }))
+            }
+        }))
+    }));
+    let enumerated = |value: &str| json!({ "type": "enum", "values": [value] });
+    let config = json!({
+        "fields": {
+            "patterns": {
+                "user.id": enumerated("user-1"),
+                "order.id": enumerated("order-1"),
+                "order.items[].sku": enumerated("SKU-1"),
+                "id": enumerated("other")
+            }
+        }
+    });
+    let body = generated(schema, config).await;
+
+    assert_eq!(body["user"]["id"], json!("user-1"));
+    assert_eq!(body["order"]["id"], json!("order-1"));
+    assert_eq!(body["id"], json!("other"));
+    for item in body["order"]["items"].as_array().unwrap() {
+        assert_eq!(item, &json!({ "id": "other", "sku": "SKU-1" }));
+    }
+}
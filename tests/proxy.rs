@@ -0,0 +1,98 @@
+mod common;
+
+use actix_web::{http::StatusCode, test, web};
+use serde_json::{json, Value};
+use spit::{
+    config::RequestLog,
+    proxy::Proxy,
+    record::{fixtures_spec, recording_app, Recorder},
+};
+
+/// A mocked `GET /users`, and a `GET /live` marked for passthrough.
+fn partly_mocked_spec() -> Value {
+    let mut live = common::get_returning(json!({ "type": "string", "example": "mocked" }));
+    live["get"]["x-spit-passthrough"] = json!(true);
+    common::spec(
+        json!({
+            "/users": common::get_returning(json!({ "type": "string", "example": "mocked" })),
+            "/live": live
+        }),
+        json!({}),
+    )
+}
+
+#[actix_web::test]
+async fn unmatched_requests_are_forwarded_upstream() {
+    let config = json!({ "proxy": common::echo_upstream() });
+    let app = test::init_service(common::load(partly_mocked_spec(), config).await.app()).await;
+
+    let request = test::TestRequest::post()
+        .uri("/orders?page=2")
+        .insert_header(("Content-Type", "text/plain"))
+        .set_payload("hello")
+        .peer_addr("127.0.0.1:40000".parse().unwrap())
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    assert_eq!(response.headers().get("X-Upstream").unwrap(), "echo");
+    assert_eq!(
+        response.headers().get("Content-Type").unwrap(),
+        "application/json"
+    );
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(
+        body,
+        json!({ "method": "POST", "path": "/orders", "query": "page=2", "body": "hello" })
+    );
+
+    let mocked: Value =
+        test::call_and_read_body_json(&app, test::TestRequest::get().uri("/users").to_request())
+            .await;
+    assert_eq!(mocked, json!("mocked"));
+
+    let log: Vec<RequestLog> = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri("/__spit/requests?path=/orders")
+            .peer_addr("127.0.0.1:40000".parse().unwrap())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(log.len(), 1);
+    assert_eq!(log[0].response_status, 202);
+}
+
+#[actix_web::test]
+async fn passthrough_operations_are_forwarded_upstream() {
+    let config = json!({ "proxy": common::echo_upstream() });
+    let app = test::init_service(common::load(partly_mocked_spec(), config).await.app()).await;
+
+    let body: Value =
+        test::call_and_read_body_json(&app, test::TestRequest::get().uri("/live").to_request())
+            .await;
+    assert_eq!(body["path"], json!("/live"));
+}
+
+#[actix_web::test]
+async fn recorded_fixtures_replay_identically() {
+    let upstream = common::echo_upstream();
+    let proxy = web::Data::new(Proxy::new(&upstream).unwrap().uncompressed());
+    let recorder = web::Data::new(Recorder::default());
+    let recording = test::init_service(recording_app(proxy, recorder.clone())).await;
+
+    let request = test::TestRequest::get().uri("/pets?kind=cat").to_request();
+    let response = test::call_service(&recording, request).await;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let recorded = test::read_body(response).await;
+
+    let fixtures = fixtures_spec(&upstream, &recorder.entries());
+    let app = test::init_service(common::load(fixtures, json!({})).await.app()).await;
+    let response =
+        test::call_service(&app, test::TestRequest::get().uri("/pets").to_request()).await;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let replayed: Value = test::read_body_json(response).await;
+    assert_eq!(
+        replayed,
+        serde_json::from_slice::<Value>(&recorded).unwrap()
+    );
+}
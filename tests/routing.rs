@@ -0,0 +1,138 @@
+mod common;
+
+use actix_web::{http::StatusCode, test};
+use serde_json::{json, Value};
+
+/// The `X-Source` header of `response`.
+fn source<B>(response: &actix_web::dev::ServiceResponse<B>) -> &str {
+    response
+        .headers()
+        .get("X-Source")
+        .unwrap()
+        .to_str()
+        .unwrap()
+}
+
+#[actix_web::test]
+async fn route_overrides_apply_most_specific_last() {
+    let mut user = common::get_returning(json!({ "type": "object" }));
+    user["post"] = user["get"].clone();
+    let spec = common::spec(
+        json!({
+            "/users/{id}": user,
+            "/orders": common::get_returning(json!({ "type": "object" }))
+        }),
+        json!({}),
+    );
+    let config = json!({
+        "headers": { "X-Source": "global", "X-Keep": "yes" },
+        "routes": {
+            "/users/*": { "headers": { "X-Source": "glob" } },
+            "GET /users/*": { "headers": { "X-Source": "method glob" }, "status_code": 203 },
+            "GET /users/{id}": { "headers": { "X-Source": "exact" } }
+        }
+    });
+    let app = test::init_service(common::load(spec, config).await.app()).await;
+
+    let response =
+        test::call_service(&app, test::TestRequest::get().uri("/users/1").to_request()).await;
+    assert_eq!(source(&response), "exact");
+    assert_eq!(response.headers().get("X-Keep").unwrap(), "yes");
+    assert_eq!(response.status(), StatusCode::NON_AUTHORITATIVE_INFORMATION);
+
+    let response =
+        test::call_service(&app, test::TestRequest::post().uri("/users/1").to_request()).await;
+    assert_eq!(source(&response), "glob");
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response =
+        test::call_service(&app, test::TestRequest::get().uri("/orders").to_request()).await;
+    assert_eq!(source(&response), "global");
+}
+
+#[actix_web::test]
+async fn requested_statuses_select_their_responses() {
+    let spec = common::spec(
+        json!({
+            "/users": {
+                "post": {
+                    "responses": {
+                        "201": {
+                            "description": "created",
+                            "content": { "application/json": { "example": { "id": 1 } } }
+                        },
+                        "404": {
+                            "description": "missing",
+                            "content": { "application/json": { "example": { "error": "gone" } } }
+                        }
+                    }
+                }
+            }
+        }),
+        json!({}),
+    );
+    let app = test::init_service(common::load(spec, json!({})).await.app()).await;
+
+    let request = test::TestRequest::post()
+        .uri("/users?__spit_status=201")
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body, json!({ "id": 1 }));
+
+    let request = test::TestRequest::post()
+        .uri("/users")
+        .insert_header(("X-Spit-Status", "404"))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body, json!({ "error": "gone" }));
+
+    let request = test::TestRequest::post()
+        .uri("/users?__spit_status=418")
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn virtual_hosts_match_over_http1_and_http2() {
+    let dir = tempfile::tempdir().unwrap();
+    let greeting = |text: &str| {
+        common::spec(
+            json!({ "/greeting": common::get_returning(json!({ "type": "string", "example": text })) }),
+            json!({}),
+        )
+    };
+    let host_spec = common::write(dir.path(), "host.json", &greeting("from host"));
+    let config = json!({ "hosts": { "api.test": host_spec.to_str().unwrap() } });
+    let mock = common::load(greeting("from main"), config).await;
+    let server = actix_web::HttpServer::new(move || mock.app())
+        .workers(1)
+        .bind_auto_h2c(("127.0.0.1", 0))
+        .unwrap();
+    let addr = server.addrs()[0];
+    actix_web::rt::spawn(server.run());
+
+    let client = || {
+        reqwest::Client::builder()
+            .resolve("api.test", addr)
+            .resolve("other.test", addr)
+    };
+    let http1 = client().http1_only().build().unwrap();
+    let http2 = client().http2_prior_knowledge().build().unwrap();
+    for client in [http1, http2] {
+        for (host, expected) in [("api.test", "from host"), ("other.test", "from main")] {
+            let response = client
+                .get(format!("http://{}:{}/greeting", host, addr.port()))
+                .send()
+                .await
+                .unwrap();
+            let version = response.version();
+            let body: String = response.json().await.unwrap();
+            assert_eq!(body, expected, "{} over {:?}", host, version);
+        }
+    }
+}
@@ -0,0 +1,267 @@
+mod common;
+
+use actix_web::{http::StatusCode, test, web, App, HttpResponse, HttpServer};
+use serde_json::{json, Value};
+use spit::{
+    fetch_swagger,
+    lint::{lint_spec, validate_spec},
+    routes::routes_json,
+    swagger::{process_swagger_paths, SpecSource},
+};
+
+#[actix_web::test]
+async fn lint_reports_each_problem_of_a_broken_spec() {
+    let mut spec = common::spec(
+        json!({
+            "/users/{id}": common::get_returning(json!({ "$ref": "#/components/schemas/Missing" })),
+            "/users/{userId}": common::get_returning(json!({ "type": "string", "pattern": "[a-" })),
+            "/Users": common::get_returning(json!({ "type": "object" })),
+            "/users": { "get": { "responses": {} } }
+        }),
+        json!({}),
+    );
+    spec["paths"]["/text"] = json!({
+        "get": {
+            "responses": {
+                "200": { "description": "ok", "content": { "text/plain": {} } }
+            }
+        }
+    });
+    let dir = tempfile::tempdir().unwrap();
+    let path = common::write(dir.path(), "openapi.json", &spec);
+    let source = SpecSource::Files(vec![path.clone()]);
+
+    let problems = lint_spec(&spec, &process_swagger_paths(&spec), &source);
+    let expected = [
+        "unresolvable $ref `#/components/schemas/Missing`",
+        "invalid pattern",
+        "GET /users documents no responses",
+        "GET /text response 200 has no application/json content",
+        "paths differ only by case: /Users, /users",
+        "paths differ only by parameter names, so only the first is served: /users/{id}, /users/{userId}",
+    ];
+    for problem in expected {
+        assert!(
+            problems.iter().any(|found| found.contains(problem)),
+            "{:?} not in {:#?}",
+            problem,
+            problems
+        );
+    }
+    assert_eq!(problems.len(), expected.len(), "{:#?}", problems);
+
+    assert!(validate_spec(path.to_str().unwrap()).await.is_err());
+}
+
+#[actix_web::test]
+async fn lint_passes_a_sound_spec() {
+    let spec = common::spec(
+        json!({ "/users": common::get_returning(json!({ "$ref": "#/components/schemas/User" })) }),
+        json!({ "User": { "type": "object", "properties": { "id": { "type": "integer" } } } }),
+    );
+    let dir = tempfile::tempdir().unwrap();
+    let path = common::write(dir.path(), "openapi.json", &spec);
+
+    assert!(validate_spec(path.to_str().unwrap()).await.is_ok());
+}
+
+#[actix_web::test]
+async fn routes_json_lists_every_path() {
+    let mut spec = common::spec(
+        json!({
+            "/pets": common::get_returning(json!({ "type": "array" })),
+            "/pets/{petId}": common::get_returning(json!({ "type": "object" })),
+            "/owners/{ownerId}/pets/{petId}": common::get_returning(json!({ "type": "object" }))
+        }),
+        json!({}),
+    );
+    spec["paths"]["/pets"]["post"] = json!({
+        "responses": { "201": { "description": "created" }, "default": { "description": "error" } }
+    });
+
+    let routes = routes_json(&process_swagger_paths(&spec));
+    let paths: Vec<&str> = routes
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|route| route["path"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        paths,
+        ["/owners/{ownerId}/pets/{petId}", "/pets", "/pets/{petId}"]
+    );
+
+    assert_eq!(routes[0]["params"], json!(["ownerId", "petId"]));
+    let mut methods = routes[1]["methods"].as_array().unwrap().clone();
+    methods.sort_by_key(|method| method["method"].to_string());
+    assert_eq!(
+        methods,
+        [
+            json!({ "method": "GET", "statuses": ["200"] }),
+            json!({ "method": "POST", "statuses": ["201", "default"] })
+        ]
+    );
+}
+
+#[actix_web::test]
+async fn swagger2_petstore_is_served() {
+    let spec = json!({
+        "swagger": "2.0",
+        "info": { "title": "Petstore", "version": "1" },
+        "paths": {
+            "/pets": {
+                "post": {
+                    "parameters": [
+                        { "name": "pet", "in": "body", "required": true, "schema": { "$ref": "#/definitions/Pet" } }
+                    ],
+                    "responses": { "201": { "description": "created", "schema": { "$ref": "#/definitions/Pet" } } }
+                }
+            },
+            "/pets/{petId}": {
+                "get": {
+                    "parameters": [{ "name": "petId", "in": "path", "required": true, "type": "integer" }],
+                    "responses": { "200": { "description": "ok", "schema": { "$ref": "#/definitions/Pet" } } }
+                }
+            }
+        },
+        "definitions": {
+            "Pet": {
+                "type": "object",
+                "required": ["id", "name"],
+                "properties": { "id": { "type": "integer" }, "name": { "type": "string" } }
+            }
+        }
+    });
+    let app = test::init_service(common::load(spec, json!({})).await.app()).await;
+
+    let pet: Value =
+        test::call_and_read_body_json(&app, test::TestRequest::get().uri("/pets/1").to_request())
+            .await;
+    assert!(pet["id"].is_i64() && pet["name"].is_string(), "{}", pet);
+
+    let request = test::TestRequest::get().uri("/pets/abc").to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let request = test::TestRequest::post()
+        .uri("/pets")
+        .set_json(json!({ "id": 2, "name": "Rex" }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let request = test::TestRequest::post()
+        .uri("/pets")
+        .set_json(json!({ "id": 2 }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn split_specs_share_components() {
+    let dir = tempfile::tempdir().unwrap();
+    let users = common::write(
+        dir.path(),
+        "users.json",
+        &common::spec(
+            json!({
+                "/users": common::get_returning(json!({ "$ref": "#/components/schemas/User" })),
+                "/shared": common::get_returning(json!({ "type": "string", "example": "first" }))
+            }),
+            json!({}),
+        ),
+    );
+    let models = common::write(
+        dir.path(),
+        "models.json",
+        &common::spec(
+            json!({
+                "/shared": common::get_returning(json!({ "type": "string", "example": "second" }))
+            }),
+            json!({
+                "User": {
+                    "type": "object",
+                    "required": ["id"],
+                    "properties": { "id": { "type": "integer", "minimum": 5, "maximum": 5 } }
+                }
+            }),
+        ),
+    );
+    let mock = common::load_source(SpecSource::Files(vec![users, models]), json!({})).await;
+    let app = test::init_service(mock.app()).await;
+
+    let user: Value =
+        test::call_and_read_body_json(&app, test::TestRequest::get().uri("/users").to_request())
+            .await;
+    assert_eq!(user, json!({ "id": 5 }));
+    let shared: Value =
+        test::call_and_read_body_json(&app, test::TestRequest::get().uri("/shared").to_request())
+            .await;
+    assert_eq!(shared, json!("first"));
+
+    // A directory is read in file name order, so models.json comes first.
+    let mock = common::load_source(SpecSource::Files(vec![dir.path().into()]), json!({})).await;
+    let app = test::init_service(mock.app()).await;
+    let shared: Value =
+        test::call_and_read_body_json(&app, test::TestRequest::get().uri("/shared").to_request())
+            .await;
+    assert_eq!(shared, json!("second"));
+}
+
+const YAML_SPEC: &str = r#"
+openapi: 3.0.3
+info: { title: yaml, version: "1" }
+paths:
+  /greeting:
+    get:
+      responses:
+        "200":
+          description: ok
+          content:
+            application/json:
+              schema: { type: string, example: hello }
+"#;
+
+/// Starts a server answering `GET /spec` with `YAML_SPEC` as `content_type`
+/// (and `/spec.yaml` without one), and returns its base URL.
+fn yaml_server(content_type: &'static str) -> String {
+    let server = HttpServer::new(move || {
+        App::new()
+            .route(
+                "/spec",
+                web::get().to(move || async move {
+                    HttpResponse::Ok()
+                        .content_type(content_type)
+                        .body(YAML_SPEC)
+                }),
+            )
+            .route(
+                "/spec.yaml",
+                web::get().to(|| async { HttpResponse::Ok().body(YAML_SPEC) }),
+            )
+    })
+    .workers(1)
+    .bind(("127.0.0.1", 0))
+    .unwrap();
+    let addr = server.addrs()[0];
+    actix_web::rt::spawn(server.run());
+    format!("http://{}", addr)
+}
+
+#[actix_web::test]
+async fn yaml_specs_load_over_http() {
+    for content_type in ["application/yaml", "text/plain"] {
+        let url = format!("{}/spec", yaml_server(content_type));
+        let spec = fetch_swagger(&SpecSource::Url(url)).await.unwrap();
+        assert_eq!(spec["info"]["title"], "yaml", "{}", content_type);
+    }
+
+    let url = format!("{}/spec.yaml", yaml_server("application/yaml"));
+    let mock = common::load_source(SpecSource::Url(url), json!({})).await;
+    let app = test::init_service(mock.app()).await;
+    let greeting: Value =
+        test::call_and_read_body_json(&app, test::TestRequest::get().uri("/greeting").to_request())
+            .await;
+    assert_eq!(greeting, json!("hello"));
+}
@@ -0,0 +1,178 @@
+mod common;
+
+use actix_web::{http::StatusCode, test};
+use serde_json::{json, Value};
+
+/// `/users` and `/users/{id}` CRUD operations on `User`s of `user_schema`.
+fn users_spec(user_schema: Value) -> Value {
+    let user = json!({ "$ref": "#/components/schemas/User" });
+    let returning = |status: &str, schema: &Value| {
+        json!({
+            "responses": {
+                status: {
+                    "description": "ok",
+                    "content": { "application/json": { "schema": schema } }
+                }
+            }
+        })
+    };
+    let accepting = |status: &str, schema: &Value| {
+        let mut operation = returning(status, schema);
+        operation["requestBody"] = json!({
+            "required": true,
+            "content": { "application/json": { "schema": schema } }
+        });
+        operation
+    };
+    common::spec(
+        json!({
+            "/users": {
+                "get": returning("200", &json!({ "type": "array", "items": user })),
+                "post": accepting("201", &user)
+            },
+            "/users/{id}": {
+                "get": returning("200", &user),
+                "put": accepting("200", &user),
+                "patch": accepting("200", &json!({ "type": "object" })),
+                "delete": { "responses": { "204": { "description": "deleted" } } }
+            }
+        }),
+        json!({ "User": user_schema }),
+    )
+}
+
+/// A `User` with an integer id, a required name and an optional role.
+fn user_schema() -> Value {
+    json!({
+        "type": "object",
+        "required": ["name"],
+        "properties": {
+            "id": { "type": "integer" },
+            "name": { "type": "string" },
+            "role": { "type": "string" }
+        }
+    })
+}
+
+#[actix_web::test]
+async fn entities_go_through_their_whole_lifecycle() {
+    let spec = users_spec(user_schema());
+    let app = test::init_service(common::load(spec, json!({ "stateful": true })).await.app()).await;
+
+    let request = test::TestRequest::post()
+        .uri("/users")
+        .set_json(json!({ "name": "Ann", "role": "admin" }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let created: Value = test::read_body_json(response).await;
+    let id = created["id"].as_i64().expect("an integer id");
+    let item = format!("/users/{}", id);
+    assert_eq!(created, json!({ "id": id, "name": "Ann", "role": "admin" }));
+
+    let read: Value =
+        test::call_and_read_body_json(&app, test::TestRequest::get().uri(&item).to_request()).await;
+    assert_eq!(read, created);
+    let listed: Value =
+        test::call_and_read_body_json(&app, test::TestRequest::get().uri("/users").to_request())
+            .await;
+    assert_eq!(listed, json!([created]));
+
+    let request = test::TestRequest::put()
+        .uri(&item)
+        .set_json(json!({ "name": "Bea" }))
+        .to_request();
+    let replaced: Value = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(replaced, json!({ "name": "Bea", "id": id }));
+
+    let request = test::TestRequest::patch()
+        .uri(&item)
+        .set_json(json!({ "role": "viewer" }))
+        .to_request();
+    let patched: Value = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(
+        patched,
+        json!({ "name": "Bea", "id": id, "role": "viewer" })
+    );
+
+    let response =
+        test::call_service(&app, test::TestRequest::delete().uri(&item).to_request()).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    for request in [
+        test::TestRequest::get().uri(&item),
+        test::TestRequest::delete().uri(&item),
+    ] {
+        let response = test::call_service(&app, request.to_request()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+    let listed: Value =
+        test::call_and_read_body_json(&app, test::TestRequest::get().uri("/users").to_request())
+            .await;
+    assert_eq!(listed, json!([]));
+}
+
+#[actix_web::test]
+async fn conflicting_ids_are_refused() {
+    let spec = users_spec(user_schema());
+    let app = test::init_service(common::load(spec, json!({ "stateful": true })).await.app()).await;
+
+    let create = || {
+        test::TestRequest::post()
+            .uri("/users")
+            .set_json(json!({ "id": 7, "name": "Ann" }))
+            .to_request()
+    };
+    let response = test::call_service(&app, create()).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let response = test::call_service(&app, create()).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let request = test::TestRequest::put()
+        .uri("/users/7")
+        .set_json(json!({ "id": 8, "name": "Ann" }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn read_only_ids_and_write_only_passwords_travel_one_way() {
+    let account = json!({
+        "type": "object",
+        "required": ["id", "name", "password"],
+        "properties": {
+            "id": { "type": "integer", "readOnly": true },
+            "name": { "type": "string" },
+            "password": { "type": "string", "writeOnly": true }
+        }
+    });
+    for stateful in [true, false] {
+        let spec = users_spec(account.clone());
+        let config = json!({ "stateful": stateful });
+        let app = test::init_service(common::load(spec, config).await.app()).await;
+
+        let request = test::TestRequest::post()
+            .uri("/users")
+            .set_json(json!({ "name": "Ann", "password": "hunter2" }))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(
+            response.status(),
+            StatusCode::CREATED,
+            "stateful: {}",
+            stateful
+        );
+        let created: Value = test::read_body_json(response).await;
+        assert!(created["id"].is_i64(), "{}", created);
+        assert!(created.get("password").is_none(), "{}", created);
+
+        let listed: Value = test::call_and_read_body_json(
+            &app,
+            test::TestRequest::get().uri("/users").to_request(),
+        )
+        .await;
+        for user in listed.as_array().unwrap() {
+            assert!(user.get("password").is_none(), "{}", user);
+        }
+    }
+}
@@ -0,0 +1,63 @@
+mod common;
+
+use std::path::{Path, PathBuf};
+
+use actix_web::HttpServer;
+use serde_json::json;
+use spit::tls::load_tls_config;
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+#[actix_web::test]
+async fn mocks_are_served_over_tls() {
+    let spec = common::spec(
+        json!({ "/greeting": common::get_returning(json!({ "type": "string", "example": "hello" })) }),
+        json!({}),
+    );
+    let mock = common::load(spec, json!({})).await;
+    let tls = load_tls_config(&fixture("cert.pem"), &fixture("key.pem")).unwrap();
+    let server = HttpServer::new(move || mock.app())
+        .workers(1)
+        .bind_rustls_0_23(("127.0.0.1", 0), tls)
+        .unwrap();
+    let addr = server.addrs()[0];
+    actix_web::rt::spawn(server.run());
+
+    let client = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap();
+    let response = client
+        .get(format!("https://localhost:{}/greeting", addr.port()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.json::<String>().await.unwrap(), "hello");
+
+    let plaintext = reqwest::get(format!("http://localhost:{}/greeting", addr.port())).await;
+    assert!(plaintext.is_err());
+}
+
+#[test]
+fn unusable_pem_files_are_reported() {
+    let error = load_tls_config(&fixture("key.pem"), &fixture("key.pem")).unwrap_err();
+    assert!(
+        error.to_string().contains("no PEM certificate found"),
+        "{}",
+        error
+    );
+
+    let error = load_tls_config(&fixture("cert.pem"), &fixture("cert.pem")).unwrap_err();
+    assert!(
+        error.to_string().contains("no PEM private key found"),
+        "{}",
+        error
+    );
+
+    assert!(load_tls_config(&fixture("missing.pem"), &fixture("key.pem")).is_err());
+}
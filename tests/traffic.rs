@@ -0,0 +1,271 @@
+mod common;
+
+use std::{
+    io::Read,
+    time::{Duration, Instant},
+};
+
+use actix_web::{http::StatusCode, test};
+use flate2::read::GzDecoder;
+use serde_json::{json, Value};
+
+/// A `GET /items` answering 200 with an empty object.
+fn items_spec() -> Value {
+    common::spec(
+        json!({ "/items": common::get_returning(json!({ "type": "object" })) }),
+        json!({}),
+    )
+}
+
+/// The value of header `name` in `response`, if it has one.
+fn header<'a, B>(response: &'a actix_web::dev::ServiceResponse<B>, name: &str) -> Option<&'a str> {
+    response
+        .headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+}
+
+#[actix_web::test]
+async fn cors_preflight_is_answered_with_allow_headers() {
+    let config = json!({
+        "cors": {
+            "allow_origins": ["https://app.test"],
+            "allow_methods": ["GET", "POST"],
+            "allow_headers": ["X-Token"]
+        }
+    });
+    let app = test::init_service(common::load(items_spec(), config).await.app()).await;
+
+    let preflight = test::TestRequest::default()
+        .method(actix_web::http::Method::OPTIONS)
+        .uri("/items")
+        .insert_header(("Origin", "https://app.test"))
+        .insert_header(("Access-Control-Request-Method", "POST"))
+        .to_request();
+    let response = test::call_service(&app, preflight).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert_eq!(
+        header(&response, "Access-Control-Allow-Origin"),
+        Some("https://app.test")
+    );
+    assert_eq!(
+        header(&response, "Access-Control-Allow-Methods"),
+        Some("GET, POST")
+    );
+    assert_eq!(
+        header(&response, "Access-Control-Allow-Headers"),
+        Some("X-Token")
+    );
+
+    let request = test::TestRequest::get()
+        .uri("/items")
+        .insert_header(("Origin", "https://app.test"))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        header(&response, "Access-Control-Allow-Origin"),
+        Some("https://app.test")
+    );
+}
+
+#[actix_web::test]
+async fn chaos_fails_the_configured_share_of_requests() {
+    let config = json!({ "chaos": { "failure_rate": 0.3, "statuses": [500, 503] } });
+    let app = test::init_service(common::load(items_spec(), config).await.app()).await;
+
+    let mut failures = 0;
+    for _ in 0..1000 {
+        let response =
+            test::call_service(&app, test::TestRequest::get().uri("/items").to_request()).await;
+        match response.status().as_u16() {
+            200 => {}
+            500 | 503 => failures += 1,
+            status => panic!("unexpected status {}", status),
+        }
+    }
+    // Over five standard deviations either side of 300.
+    assert!((220..=380).contains(&failures), "{} failures", failures);
+}
+
+#[actix_web::test]
+async fn requests_over_the_rate_limit_get_429() {
+    let config = json!({ "rate_limit": { "requests": 5, "window_secs": 60 } });
+    let app = test::init_service(common::load(items_spec(), config).await.app()).await;
+
+    for _ in 0..5 {
+        let response =
+            test::call_service(&app, test::TestRequest::get().uri("/items").to_request()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let response =
+        test::call_service(&app, test::TestRequest::get().uri("/items").to_request()).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = header(&response, "Retry-After").unwrap().parse().unwrap();
+    assert!((1..=12).contains(&retry_after), "{}", retry_after);
+}
+
+#[actix_web::test]
+async fn jittered_delays_stay_within_their_band() {
+    let config = json!({ "delay": { "base": 60, "jitter": 40 } });
+    let app = test::init_service(common::load(items_spec(), config).await.app()).await;
+
+    for _ in 0..5 {
+        let started = Instant::now();
+        let response =
+            test::call_service(&app, test::TestRequest::get().uri("/items").to_request()).await;
+        let elapsed = started.elapsed();
+        assert_eq!(response.status(), StatusCode::OK);
+        // Up to 100ms of slack for a busy machine above the band's top.
+        assert!(
+            elapsed >= Duration::from_millis(20) && elapsed < Duration::from_millis(200),
+            "{:?}",
+            elapsed
+        );
+    }
+}
+
+#[actix_web::test]
+async fn metrics_count_requests_by_route_template() {
+    let spec = common::spec(
+        json!({ "/items/{id}": common::get_returning(json!({ "type": "object" })) }),
+        json!({}),
+    );
+    let app = test::init_service(common::load(spec, json!({ "metrics": true })).await.app()).await;
+
+    for uri in ["/items/1", "/items/2", "/items/3", "/missing"] {
+        test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+    }
+    let request = test::TestRequest::with_uri("/items/4")
+        .method(actix_web::http::Method::from_bytes(b"PURGE").unwrap())
+        .to_request();
+    test::call_service(&app, request).await;
+
+    let response =
+        test::call_service(&app, test::TestRequest::get().uri("/metrics").to_request()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(header(&response, "Content-Type")
+        .unwrap()
+        .starts_with("text/plain"));
+    let body = test::read_body(response).await;
+    let metrics = String::from_utf8_lossy(&body);
+    for line in [
+        r#"spit_requests_total{method="GET",path="/items/{id}",status="200"} 3"#,
+        r#"spit_requests_total{method="GET",path="unmatched",status="404"} 1"#,
+        r#"spit_request_duration_seconds_count{method="GET",path="/items/{id}"} 3"#,
+    ] {
+        assert!(metrics.contains(line), "{} not in\n{}", line, metrics);
+    }
+    assert!(metrics.contains(r#"method="other""#), "{}", metrics);
+    assert!(!metrics.contains("/items/1"), "{}", metrics);
+
+    let request = test::TestRequest::get()
+        .uri("/metrics")
+        .peer_addr("203.0.113.7:40000".parse().unwrap())
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn xml_is_negotiated_with_accept() {
+    let schema = json!({
+        "type": "object",
+        "xml": { "name": "user" },
+        "properties": { "name": { "type": "string", "example": "Ann <admin>" } }
+    });
+    let spec = common::spec(
+        json!({
+            "/offered": {
+                "get": {
+                    "responses": {
+                        "200": {
+                            "description": "ok",
+                            "content": {
+                                "application/json": { "schema": schema },
+                                "application/xml": { "schema": schema }
+                            }
+                        }
+                    }
+                }
+            },
+            "/json": common::get_returning(schema.clone())
+        }),
+        json!({}),
+    );
+    let xml =
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?><user><name>Ann &lt;admin&gt;</name></user>";
+    let get = |uri: &str| {
+        test::TestRequest::get()
+            .uri(uri)
+            .insert_header(("Accept", "application/xml"))
+            .to_request()
+    };
+
+    let app = test::init_service(common::load(spec.clone(), json!({})).await.app()).await;
+    let response = test::call_service(&app, get("/offered")).await;
+    assert!(header(&response, "Content-Type")
+        .unwrap()
+        .starts_with("application/xml"));
+    assert_eq!(test::read_body(response).await, xml);
+    let response = test::call_service(&app, get("/json")).await;
+    assert!(header(&response, "Content-Type")
+        .unwrap()
+        .starts_with("application/json"));
+
+    let config = json!({ "xml_responses": true });
+    let app = test::init_service(common::load(spec, config).await.app()).await;
+    let response = test::call_service(&app, get("/json")).await;
+    assert!(header(&response, "Content-Type")
+        .unwrap()
+        .starts_with("application/xml"));
+    assert_eq!(test::read_body(response).await, xml);
+}
+
+#[actix_web::test]
+async fn responses_are_gzipped_when_accepted() {
+    let spec = common::spec(
+        json!({
+            "/items": common::get_returning(json!({
+                "type": "array",
+                "minItems": 200,
+                "maxItems": 200,
+                "items": { "type": "string", "example": "a repetitive item" }
+            }))
+        }),
+        json!({}),
+    );
+    let config = json!({ "compress": true, "metrics": true });
+    let app = test::init_service(common::load(spec, config).await.app()).await;
+
+    let request = test::TestRequest::get()
+        .uri("/items")
+        .insert_header(("Accept-Encoding", "gzip"))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(header(&response, "Content-Encoding"), Some("gzip"));
+    let compressed = test::read_body(response).await;
+
+    let mut body = String::new();
+    GzDecoder::new(&compressed[..])
+        .read_to_string(&mut body)
+        .unwrap();
+    assert!(
+        compressed.len() < body.len() / 10,
+        "{} bytes",
+        compressed.len()
+    );
+    let items: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(items, json!(vec!["a repetitive item"; 200]));
+
+    let response =
+        test::call_service(&app, test::TestRequest::get().uri("/items").to_request()).await;
+    assert_eq!(header(&response, "Content-Encoding"), None);
+
+    let request = test::TestRequest::get()
+        .uri("/metrics")
+        .insert_header(("Accept-Encoding", "gzip"))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(header(&response, "Content-Encoding"), Some("gzip"));
+}
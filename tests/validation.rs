@@ -0,0 +1,545 @@
+mod common;
+
+use actix_web::{http::StatusCode, test};
+use serde_json::{json, Value};
+use spit::{config::MockConfig, swagger::SpecSource, MockApp};
+
+/// A `POST /items` accepting `body` and answering 201 with `{}`.
+fn post_accepting(body: Value) -> Value {
+    common::spec(
+        json!({
+            "/items": {
+                "post": {
+                    "requestBody": body,
+                    "responses": {
+                        "201": {
+                            "description": "created",
+                            "content": { "application/json": { "schema": { "type": "object" } } }
+                        }
+                    }
+                }
+            }
+        }),
+        json!({}),
+    )
+}
+
+#[actix_web::test]
+async fn required_text_body_without_schema_must_be_sent() {
+    let spec = post_accepting(json!({
+        "required": true,
+        "content": { "text/plain": {} }
+    }));
+    let app = test::init_service(common::load(spec, json!({})).await.app()).await;
+
+    let missing = test::TestRequest::post().uri("/items").to_request();
+    let response = test::call_service(&app, missing).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let sent = test::TestRequest::post()
+        .uri("/items")
+        .insert_header(("Content-Type", "text/plain"))
+        .set_payload("hello")
+        .to_request();
+    let response = test::call_service(&app, sent).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+/// A JSON `POST` of `body` to `uri`.
+fn post_json(uri: &str, body: Value) -> test::TestRequest {
+    test::TestRequest::post().uri(uri).set_json(body)
+}
+
+/// A `POST /items` whose JSON body is validated against `schema`.
+fn post_validating(schema: Value) -> Value {
+    post_accepting(json!({
+        "required": true,
+        "content": { "application/json": { "schema": schema } }
+    }))
+}
+
+#[actix_web::test]
+async fn type_arrays_allow_each_listed_type() {
+    let spec = post_validating(json!({
+        "type": "object",
+        "properties": { "count": { "type": ["integer", "null"] } }
+    }));
+    let app = test::init_service(common::load(spec, json!({})).await.app()).await;
+
+    for (count, expected) in [
+        (json!(42), StatusCode::CREATED),
+        (json!(null), StatusCode::CREATED),
+        (json!("foo"), StatusCode::BAD_REQUEST),
+    ] {
+        let request = post_json("/items", json!({ "count": count })).to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), expected, "count {}", count);
+    }
+}
+
+/// A `Pet` union of `Cat` and `Dog`, discriminated by `kind`.
+fn pet_schemas() -> Value {
+    json!({
+        "Pet": {
+            "oneOf": [{ "$ref": "#/components/schemas/Cat" }, { "$ref": "#/components/schemas/Dog" }],
+            "discriminator": {
+                "propertyName": "kind",
+                "mapping": { "cat": "#/components/schemas/Cat", "dog": "#/components/schemas/Dog" }
+            }
+        },
+        "Cat": {
+            "type": "object",
+            "required": ["kind", "meows"],
+            "properties": { "kind": { "type": "string" }, "meows": { "type": "boolean" } }
+        },
+        "Dog": {
+            "type": "object",
+            "required": ["kind", "barks"],
+            "properties": { "kind": { "type": "string" }, "barks": { "type": "boolean" } }
+        }
+    })
+}
+
+#[actix_web::test]
+async fn discriminated_one_of_validates_the_selected_variant() {
+    let mut spec = post_validating(json!({ "$ref": "#/components/schemas/Pet" }));
+    spec["components"]["schemas"] = pet_schemas();
+    let app = test::init_service(common::load(spec, json!({})).await.app()).await;
+
+    for (pet, expected) in [
+        (json!({ "kind": "cat", "meows": true }), StatusCode::CREATED),
+        (
+            json!({ "kind": "dog", "barks": false }),
+            StatusCode::CREATED,
+        ),
+        (
+            json!({ "kind": "cat", "barks": true }),
+            StatusCode::BAD_REQUEST,
+        ),
+        (
+            json!({ "kind": "fish", "swims": true }),
+            StatusCode::BAD_REQUEST,
+        ),
+    ] {
+        let response =
+            test::call_service(&app, post_json("/items", pet.clone()).to_request()).await;
+        assert_eq!(response.status(), expected, "pet {}", pet);
+    }
+}
+
+#[actix_web::test]
+async fn all_of_requires_every_part() {
+    let spec = post_validating(json!({
+        "allOf": [
+            { "type": "object", "required": ["id"], "properties": { "id": { "type": "integer" } } },
+            { "type": "object", "required": ["name"], "properties": { "name": { "type": "string" } } }
+        ]
+    }));
+    let app = test::init_service(common::load(spec, json!({})).await.app()).await;
+
+    for (body, expected) in [
+        (json!({ "id": 1, "name": "a" }), StatusCode::CREATED),
+        (json!({ "id": 1 }), StatusCode::BAD_REQUEST),
+        (json!({ "name": "a" }), StatusCode::BAD_REQUEST),
+    ] {
+        let response =
+            test::call_service(&app, post_json("/items", body.clone()).to_request()).await;
+        assert_eq!(response.status(), expected, "body {}", body);
+    }
+}
+
+#[actix_web::test]
+async fn parameter_refs_resolve() {
+    let mut spec = common::spec(
+        json!({
+            "/items": {
+                "get": {
+                    "parameters": [{ "$ref": "#/components/parameters/PageParam" }],
+                    "responses": { "200": { "description": "ok" } }
+                }
+            }
+        }),
+        json!({}),
+    );
+    spec["components"]["parameters"] = json!({
+        "PageParam": { "name": "page", "in": "query", "required": true, "schema": { "type": "integer" } }
+    });
+    let app = test::init_service(common::load(spec, json!({})).await.app()).await;
+
+    for (uri, expected) in [
+        ("/items?page=2", StatusCode::OK),
+        ("/items", StatusCode::BAD_REQUEST),
+        ("/items?page=two", StatusCode::BAD_REQUEST),
+    ] {
+        let response =
+            test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+        assert_eq!(response.status(), expected, "{}", uri);
+    }
+}
+
+/// A spec whose `GET path` declares `parameters` and answers 200.
+fn get_with_parameters(path: &str, parameters: Value) -> Value {
+    common::spec(
+        json!({
+            path: {
+                "get": {
+                    "parameters": parameters,
+                    "responses": { "200": { "description": "ok" } }
+                }
+            }
+        }),
+        json!({}),
+    )
+}
+
+#[actix_web::test]
+async fn query_parameters_are_required_and_validated() {
+    let spec = get_with_parameters(
+        "/items",
+        json!([
+            { "name": "limit", "in": "query", "required": true, "schema": { "type": "integer", "minimum": 1, "maximum": 10 } },
+            { "name": "sort", "in": "query", "schema": { "type": "string", "enum": ["asc", "desc"] } }
+        ]),
+    );
+    let app = test::init_service(common::load(spec, json!({})).await.app()).await;
+
+    let response = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri("/items?limit=5&sort=asc")
+            .to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    for (uri, key, names) in [
+        ("/items", "missing_parameters", json!(["limit"])),
+        ("/items?limit=11", "invalid_parameters", json!(["limit"])),
+        (
+            "/items?limit=5&sort=up",
+            "invalid_parameters",
+            json!(["sort"]),
+        ),
+    ] {
+        let response =
+            test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", uri);
+        let body: Value = test::read_body_json(response).await;
+        assert_eq!(body[key], names, "{}", uri);
+    }
+}
+
+#[actix_web::test]
+async fn path_parameters_are_validated_against_their_schemas() {
+    let spec = get_with_parameters(
+        "/users/{id}/keys/{key}/{state}",
+        json!([
+            { "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } },
+            { "name": "key", "in": "path", "required": true, "schema": { "type": "string", "format": "uuid" } },
+            { "name": "state", "in": "path", "required": true, "schema": { "type": "string", "enum": ["on", "off"] } }
+        ]),
+    );
+    let config = json!({ "strict_formats": true });
+    let app = test::init_service(common::load(spec, config).await.app()).await;
+    let key = "8f14e45f-ceea-467f-a0e6-1b2c3d4e5f60";
+
+    let uri = format!("/users/42/keys/{}/on", key);
+    let response = test::call_service(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    for (uri, invalid) in [
+        (format!("/users/abc/keys/{}/on", key), "id"),
+        ("/users/42/keys/not-a-uuid/on".to_string(), "key"),
+        (format!("/users/42/keys/{}/maybe", key), "state"),
+    ] {
+        let response =
+            test::call_service(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", uri);
+        let body: Value = test::read_body_json(response).await;
+        assert_eq!(body["invalid_parameters"], json!([invalid]), "{}", uri);
+    }
+}
+
+#[actix_web::test]
+async fn invalid_patterns_fail_on_load() {
+    let dir = tempfile::tempdir().unwrap();
+    let spec = post_validating(json!({
+        "type": "object",
+        "properties": { "code": { "type": "string", "pattern": "^[A-Z" } }
+    }));
+    let path = common::write(dir.path(), "openapi.json", &spec);
+
+    let error = MockApp::load(&SpecSource::Files(vec![path]), &MockConfig::default())
+        .await
+        .err()
+        .expect("the spec has an invalid pattern");
+    let message = error.to_string();
+    assert!(message.contains("invalid pattern"), "{}", message);
+    assert!(message.contains("/code/pattern"), "{}", message);
+}
+
+#[actix_web::test]
+async fn patterns_are_enforced() {
+    let spec = post_validating(json!({
+        "type": "object",
+        "properties": { "code": { "type": "string", "pattern": "^[A-Z]{3}-\\d{4}$" } }
+    }));
+    let app = test::init_service(common::load(spec, json!({})).await.app()).await;
+
+    for (code, expected) in [
+        ("ABC-1234", StatusCode::CREATED),
+        ("abc-1234", StatusCode::BAD_REQUEST),
+    ] {
+        let request = post_json("/items", json!({ "code": code })).to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), expected, "{}", code);
+    }
+}
+
+/// Status of `POST /items` with `{ "value": value }`, for each value, where
+/// `value` is validated against `schema`.
+async fn statuses_for(schema: Value, config: Value, values: &[Value]) -> Vec<StatusCode> {
+    let spec = post_validating(json!({
+        "type": "object",
+        "properties": { "value": schema }
+    }));
+    let app = test::init_service(common::load(spec, config).await.app()).await;
+
+    let mut statuses = Vec::new();
+    for value in values {
+        let request = post_json("/items", json!({ "value": value })).to_request();
+        statuses.push(test::call_service(&app, request).await.status());
+    }
+    statuses
+}
+
+const OK: StatusCode = StatusCode::CREATED;
+const BAD: StatusCode = StatusCode::BAD_REQUEST;
+
+#[actix_web::test]
+async fn exclusive_bounds_reject_the_boundary() {
+    let values = [json!(0), json!(0.001), json!(9.999), json!(10)];
+    let boolean_form = json!({
+        "type": "number",
+        "minimum": 0,
+        "maximum": 10,
+        "exclusiveMinimum": true,
+        "exclusiveMaximum": true
+    });
+    assert_eq!(
+        statuses_for(boolean_form, json!({}), &values).await,
+        [BAD, OK, OK, BAD]
+    );
+    let numeric_form = json!({ "type": "number", "exclusiveMinimum": 0, "exclusiveMaximum": 10 });
+    assert_eq!(
+        statuses_for(numeric_form, json!({}), &values).await,
+        [BAD, OK, OK, BAD]
+    );
+}
+
+#[actix_web::test]
+async fn currency_amounts_are_multiples_of_a_cent() {
+    let values = [
+        json!(19.99),
+        json!(0.1),
+        json!(1000.01),
+        json!(0.3),
+        json!(19.999),
+    ];
+    assert_eq!(
+        statuses_for(
+            json!({ "type": "number", "multipleOf": 0.01 }),
+            json!({}),
+            &values
+        )
+        .await,
+        [OK, OK, OK, OK, BAD]
+    );
+}
+
+#[actix_web::test]
+async fn string_enums_reject_other_values() {
+    let spec = post_validating(json!({
+        "type": "object",
+        "properties": { "value": { "type": "string", "enum": ["a", "b"] } }
+    }));
+    let app = test::init_service(common::load(spec, json!({})).await.app()).await;
+
+    let response = test::call_service(
+        &app,
+        post_json("/items", json!({ "value": "b" })).to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), OK);
+    let response = test::call_service(
+        &app,
+        post_json("/items", json!({ "value": "z" })).to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), BAD);
+    let body = test::read_body(response).await;
+    let body = String::from_utf8_lossy(&body);
+    assert!(body.contains(r#"["a","b"]"#), "{}", body);
+}
+
+#[actix_web::test]
+async fn formats_are_checked_with_strict_formats() {
+    let schema = json!({ "type": "string", "format": "uuid" });
+    let values = [
+        json!("8f14e45f-ceea-467f-a0e6-1b2c3d4e5f60"),
+        json!("not-a-uuid"),
+    ];
+    assert_eq!(
+        statuses_for(schema.clone(), json!({ "strict_formats": true }), &values).await,
+        [OK, BAD]
+    );
+    assert_eq!(statuses_for(schema, json!({}), &values).await, [OK, OK]);
+}
+
+#[actix_web::test]
+async fn additional_properties_are_enforced() {
+    let strict = json!({
+        "type": "object",
+        "properties": { "name": { "type": "string" } },
+        "additionalProperties": false
+    });
+    let values = [json!({ "name": "a" }), json!({ "name": "a", "extra": 1 })];
+    assert_eq!(statuses_for(strict, json!({}), &values).await, [OK, BAD]);
+
+    let typed_map = json!({ "type": "object", "additionalProperties": { "type": "integer" } });
+    let values = [json!({ "a": 1, "b": 2 }), json!({ "a": 1, "b": "two" })];
+    assert_eq!(statuses_for(typed_map, json!({}), &values).await, [OK, BAD]);
+}
+
+#[actix_web::test]
+async fn unexpected_keys_are_listed() {
+    let spec = post_validating(json!({
+        "type": "object",
+        "properties": { "name": { "type": "string" } },
+        "additionalProperties": false
+    }));
+    let app = test::init_service(common::load(spec, json!({})).await.app()).await;
+
+    let request = post_json("/items", json!({ "name": "a", "colour": "red" })).to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), BAD);
+    let body = test::read_body(response).await;
+    let body = String::from_utf8_lossy(&body);
+    assert!(body.contains("colour"), "{}", body);
+}
+
+#[actix_web::test]
+async fn string_lengths_count_characters() {
+    // Six characters in eleven bytes.
+    let value = [json!("héllo🎉")];
+    for (keyword, limit, expected) in [
+        ("maxLength", 6, OK),
+        ("maxLength", 10, OK),
+        ("minLength", 6, OK),
+        ("maxLength", 5, BAD),
+        ("minLength", 7, BAD),
+    ] {
+        let schema = json!({ "type": "string", keyword: limit });
+        let statuses = statuses_for(schema, json!({}), &value).await;
+        assert_eq!(statuses, [expected], "{} {}", keyword, limit);
+    }
+}
+
+#[actix_web::test]
+async fn form_bodies_are_validated() {
+    let form_schema = json!({
+        "type": "object",
+        "required": ["name"],
+        "properties": { "name": { "type": "string" }, "age": { "type": "integer" } }
+    });
+    let spec = post_accepting(json!({
+        "required": true,
+        "content": {
+            "application/x-www-form-urlencoded": { "schema": form_schema },
+            "multipart/form-data": { "schema": form_schema }
+        }
+    }));
+    let app = test::init_service(common::load(spec, json!({})).await.app()).await;
+
+    let form = |body: &'static str| {
+        test::TestRequest::post()
+            .uri("/items")
+            .insert_header(("Content-Type", "application/x-www-form-urlencoded"))
+            .set_payload(body)
+            .to_request()
+    };
+    for (body, expected) in [
+        ("name=Ann&age=30", OK),
+        ("age=30", BAD),
+        ("name=Ann&age=thirty", BAD),
+    ] {
+        let response = test::call_service(&app, form(body)).await;
+        assert_eq!(response.status(), expected, "{}", body);
+    }
+
+    let multipart = |field: &str| {
+        let body = format!(
+            "--XYZ\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\nAnn\r\n--XYZ--\r\n",
+            field
+        );
+        test::TestRequest::post()
+            .uri("/items")
+            .insert_header(("Content-Type", "multipart/form-data; boundary=XYZ"))
+            .set_payload(body)
+            .to_request()
+    };
+    let response = test::call_service(&app, multipart("name")).await;
+    assert_eq!(response.status(), OK);
+    let response = test::call_service(&app, multipart("nickname")).await;
+    assert_eq!(response.status(), BAD);
+
+    let response = test::call_service(
+        &app,
+        post_json("/items", json!({ "name": "Ann" })).to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+}
+
+#[actix_web::test]
+async fn content_types_must_match_the_declared_ones() {
+    let spec = post_validating(json!({ "type": "object" }));
+    let app = test::init_service(common::load(spec, json!({})).await.app()).await;
+
+    for (content_type, expected) in [
+        ("application/json", OK),
+        ("application/json; charset=utf-8", OK),
+        ("application/vnd.api+json", OK),
+        ("text/plain", StatusCode::UNSUPPORTED_MEDIA_TYPE),
+        ("application/xml", StatusCode::UNSUPPORTED_MEDIA_TYPE),
+    ] {
+        let request = test::TestRequest::post()
+            .uri("/items")
+            .insert_header(("Content-Type", content_type))
+            .set_payload(r#"{"name":"Ann"}"#)
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), expected, "{}", content_type);
+    }
+}
+
+#[actix_web::test]
+async fn huge_open_maps_are_accepted_and_max_properties_is_enforced() {
+    let huge: serde_json::Map<String, Value> =
+        (0..10_001).map(|n| (format!("k{}", n), json!(n))).collect();
+    let statuses = statuses_for(
+        json!({ "type": "object", "additionalProperties": { "type": "integer" } }),
+        json!({}),
+        &[Value::Object(huge), json!({ "k": "not an integer" })],
+    )
+    .await;
+    assert_eq!(statuses, [OK, BAD]);
+
+    let statuses = statuses_for(
+        json!({ "type": "object", "maxProperties": 2 }),
+        json!({}),
+        &[json!({ "a": 1, "b": 2 }), json!({ "a": 1, "b": 2, "c": 3 })],
+    )
+    .await;
+    assert_eq!(statuses, [OK, BAD]);
+}
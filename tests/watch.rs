@@ -0,0 +1,138 @@
+mod common;
+
+use std::{
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use actix_web::{http::StatusCode, test};
+use serde_json::{json, Value};
+use spit::{swagger::SpecSource, watch::Watch, MockApp};
+
+/// Writes a spec serving `GET path` to `openapi.json` in `dir`, stamped
+/// `ahead` seconds into the future so the change is seen whatever the mtime
+/// granularity.
+fn write_spec(dir: &Path, path: &str, ahead: u64) {
+    let spec = common::spec(json!({ path: common::get_returning(json!({})) }), json!({}));
+    let file = common::write(dir, "openapi.json", &spec);
+    std::fs::File::options()
+        .write(true)
+        .open(file)
+        .unwrap()
+        .set_modified(SystemTime::now() + Duration::from_secs(ahead))
+        .unwrap();
+}
+
+/// Watches `source` for `mock`, reloading with `config`.
+fn watch_with(source: SpecSource, mock: &MockApp, config: Value) {
+    actix_web::rt::spawn(mock.watch(
+        source,
+        None,
+        Watch {
+            config_path: None,
+            load_config: Box::new(move || Ok(serde_json::from_value(config.clone())?)),
+        },
+    ));
+}
+
+/// Watches `source` for `mock`, reloading with the default config.
+fn watch(source: SpecSource, mock: &MockApp) {
+    watch_with(source, mock, json!({}));
+}
+
+/// Status `app` answers `request` with once it is no longer 404, or after
+/// five seconds.
+async fn status_once_found<S, R, B>(app: &S, request: impl Fn() -> R) -> StatusCode
+where
+    S: actix_web::dev::Service<
+        R,
+        Response = actix_web::dev::ServiceResponse<B>,
+        Error = actix_web::Error,
+    >,
+{
+    let mut status = StatusCode::NOT_FOUND;
+    for _ in 0..50 {
+        actix_web::rt::time::sleep(Duration::from_millis(100)).await;
+        status = test::call_service(app, request()).await.status();
+        if status != StatusCode::NOT_FOUND {
+            break;
+        }
+    }
+    status
+}
+
+#[actix_web::test]
+async fn changed_specs_are_reloaded() {
+    let dir = tempfile::tempdir().unwrap();
+    write_spec(dir.path(), "/before", 0);
+    let source = SpecSource::Files(vec![dir.path().join("openapi.json")]);
+    let mock = common::load_source(source.clone(), json!({})).await;
+    let app = test::init_service(mock.app()).await;
+    watch(source, &mock);
+    // Let the watcher take its first look before the spec changes.
+    actix_web::rt::time::sleep(Duration::from_millis(100)).await;
+
+    write_spec(dir.path(), "/after", 10);
+    let status =
+        status_once_found(&app, || test::TestRequest::get().uri("/after").to_request()).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let request = test::TestRequest::get().uri("/before").to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn broken_specs_keep_the_previous_routes() {
+    let dir = tempfile::tempdir().unwrap();
+    write_spec(dir.path(), "/before", 0);
+    let file = dir.path().join("openapi.json");
+    let source = SpecSource::Files(vec![file.clone()]);
+    let mock = common::load_source(source.clone(), json!({})).await;
+    let app = test::init_service(mock.app()).await;
+    watch(source, &mock);
+    actix_web::rt::time::sleep(Duration::from_millis(100)).await;
+
+    std::fs::write(&file, "{ not json").unwrap();
+    // Two poll intervals, so the broken spec has been read.
+    actix_web::rt::time::sleep(Duration::from_millis(1100)).await;
+
+    let request = test::TestRequest::get().uri("/before").to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn virtual_host_specs_are_reloaded() {
+    let main_dir = tempfile::tempdir().unwrap();
+    let host_dir = tempfile::tempdir().unwrap();
+    write_spec(main_dir.path(), "/main", 0);
+    write_spec(host_dir.path(), "/before", 0);
+    let source = SpecSource::Files(vec![main_dir.path().join("openapi.json")]);
+    let host_spec = host_dir.path().join("openapi.json");
+    let config = json!({ "hosts": { "api.test": host_spec.to_str().unwrap() } });
+    let mock = common::load_source(source.clone(), config.clone()).await;
+    let app = test::init_service(mock.app()).await;
+    watch_with(source, &mock, config);
+    actix_web::rt::time::sleep(Duration::from_millis(100)).await;
+
+    write_spec(host_dir.path(), "/after", 10);
+    let on_host = |uri: &'static str| {
+        move || {
+            test::TestRequest::get()
+                .uri(uri)
+                .insert_header(("Host", "api.test"))
+                .to_request()
+        }
+    };
+    let status = status_once_found(&app, on_host("/after")).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let response = test::call_service(&app, on_host("/before")()).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let request = test::TestRequest::get().uri("/main").to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::OK
+    );
+}